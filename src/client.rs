@@ -1,6 +1,62 @@
+use crate::finance::order_service_client::OrderServiceClient;
 use crate::finance::stock_service_client::StockServiceClient;
-use crate::finance::{MultiplePricesRequest, PriceRequest, StatsRequest, TickerListRequest};
+use crate::finance::{
+    indicator_spec, BetaRequest, BollingerSpec, CancelOrderRequest, CandleInterval, CandlesRequest,
+    CorporateActionsRequest, CorrelationMatrixRequest, Decimal, DepthRequest, EmaSpec,
+    GetOrderRequest, HaltsRequest, IndicatorSpec, IndicatorsRequest, MacdSpec, MarketStatusRequest,
+    MultiplePricesRequest, OrderSide, OrderType, PriceRequest, QuoteRequest, ReplaceOrderRequest,
+    RsiSpec, ServerInfoRequest, SmaSpec, StatsRequest, SubmitOrderRequest, TickerListRequest,
+    TimeInForce,
+};
+use crate::price::Price;
 use tokio::io::{AsyncBufReadExt, BufReader};
+use tonic::transport::Channel;
+
+/// Reads a price typed at the prompt.
+fn parse_price(text: &str) -> Option<Decimal> {
+    let price = Price::from_f64(text.parse().ok()?)?;
+    Some(Decimal {
+        units: price.units(),
+        nanos: price.subunit_nanos(),
+    })
+}
+
+/// Reads `buy|sell <ticker> <quantity> [limit] [day|gtc|ioc|fok]`.
+fn parse_order(side: OrderSide, args: &[&str]) -> Option<SubmitOrderRequest> {
+    let (ticker, quantity, rest) = match args {
+        [ticker, quantity, rest @ ..] => (ticker, quantity.parse().ok()?, rest),
+        _ => return None,
+    };
+    let (limit_price, rest) = match rest.split_first() {
+        Some((first, rest)) if first.parse::<f64>().is_ok() => (parse_price(first), rest),
+        _ => (None, rest),
+    };
+    let time_in_force = match rest {
+        [] => TimeInForce::Day,
+        [tif] => match tif.to_lowercase().as_str() {
+            "day" => TimeInForce::Day,
+            "gtc" => TimeInForce::GoodTillCancel,
+            "ioc" => TimeInForce::ImmediateOrCancel,
+            "fok" => TimeInForce::FillOrKill,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(SubmitOrderRequest {
+        ticker: ticker.to_string(),
+        side: side.into(),
+        order_type: if limit_price.is_some() {
+            OrderType::Limit
+        } else {
+            OrderType::Market
+        }
+        .into(),
+        quantity,
+        limit_price,
+        time_in_force: time_in_force.into(),
+        client_order_id: String::new(),
+    })
+}
 
 pub async fn start_client(host: &str, port: u16) -> Result<(), Box<dyn std::error::Error>> {
     // Add retry logic for Docker container startup timing
@@ -10,9 +66,9 @@ pub async fn start_client(host: &str, port: u16) -> Result<(), Box<dyn std::erro
 
     println!("Attempting to connect to {}", addr);
 
-    let mut client = loop {
-        match StockServiceClient::connect(addr.clone()).await {
-            Ok(client) => break client,
+    let channel = loop {
+        match Channel::from_shared(addr.clone())?.connect().await {
+            Ok(channel) => break channel,
             Err(e) => {
                 retry_count += 1;
                 if retry_count >= max_retries {
@@ -29,10 +85,22 @@ pub async fn start_client(host: &str, port: u16) -> Result<(), Box<dyn std::erro
         }
     };
 
+    let mut client = StockServiceClient::new(channel.clone());
+    let mut orders = OrderServiceClient::new(channel);
+
     println!("Successfully connected to gRPC server");
     println!("\nAvailable commands:");
     println!("- list: Show available tickers");
+    println!("- info: Show server and simulation info");
     println!("- stats <ticker>: Show statistics for a ticker");
+    println!("- quote <ticker>: Show the bid/ask quote for a ticker");
+    println!("- depth <ticker> [levels]: Show a ticker's paper order book by price level");
+    println!("- candles <ticker> <1s|1m|5m|1h>: Show recent OHLCV candles for a ticker");
+    println!("- indicators <ticker>: Show SMA, EMA, RSI, MACD and Bollinger bands for a ticker");
+    println!("- corr [tickers...]: Show the return correlation matrix (all tickers by default)");
+    println!("- beta <ticker> <benchmark>: Show a ticker's beta against a benchmark ticker");
+    println!("- buy|sell <ticker> <quantity> [limit] [day|gtc|ioc|fok]: Submit a paper order");
+    println!("- cancel <order id>, replace <order id> <quantity> [limit], order <order id>: Manage paper orders");
     println!("- <ticker> [count]: Get current price(s) for a ticker");
     println!("- quit or exit: Disconnect from server\n");
 
@@ -56,23 +124,240 @@ pub async fn start_client(host: &str, port: u16) -> Result<(), Box<dyn std::erro
         if command == "list" {
             match client.get_ticker_list(TickerListRequest {}).await {
                 Ok(response) => {
-                    let tickers = response.into_inner().tickers;
-                    println!("Available tickers: {}", tickers.join(", "));
+                    let response = response.into_inner();
+                    if response.instruments.is_empty() {
+                        println!("Available tickers: {}", response.tickers.join(", "));
+                    } else {
+                        println!("Available tickers:");
+                        for instrument in response.instruments {
+                            println!(
+                                "  {:<6} {} ({}, {}) tick {:.*} lot {}",
+                                instrument.symbol,
+                                instrument.name,
+                                instrument.exchange,
+                                instrument.currency,
+                                instrument.price_decimals as usize,
+                                instrument.tick_size,
+                                instrument.lot_size
+                            );
+                        }
+                    }
                 }
                 Err(e) => eprintln!("Error getting ticker list: {}", e),
             }
+        } else if command == "info" {
+            match client.get_server_info(ServerInfoRequest {}).await {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting server info: {}", e),
+            }
+        } else if command == "status" {
+            match client.get_market_status(MarketStatusRequest {}).await {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting market status: {}", e),
+            }
         } else if command == "quit" || command == "exit" {
             println!("Disconnecting from server...");
             break;
         } else if command.starts_with("stats ") {
             let ticker = command.strip_prefix("stats ").unwrap().to_string();
-            match client.get_stats(StatsRequest { ticker }).await {
+            match client
+                .get_stats(StatsRequest {
+                    ticker,
+                    omit_prices: true,
+                    ..Default::default()
+                })
+                .await
+            {
                 Ok(response) => {
                     let stats = response.into_inner();
                     println!("{}", stats.formatted_message);
                 }
                 Err(e) => eprintln!("Error getting stats: {}", e),
             }
+        } else if command.starts_with("quote ") {
+            let ticker = command.strip_prefix("quote ").unwrap().to_string();
+            match client.get_quote(QuoteRequest { ticker }).await {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting quote: {}", e),
+            }
+        } else if command.starts_with("depth ") {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            let levels = match parts.get(2).map(|levels| levels.parse()) {
+                None => 0,
+                Some(Ok(levels)) => levels,
+                Some(Err(_)) => {
+                    println!("Usage: depth <ticker> [levels]");
+                    continue;
+                }
+            };
+            let request = DepthRequest {
+                ticker: parts[1].to_string(),
+                levels,
+            };
+            match client.get_depth_snapshot(request).await {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting depth: {}", e),
+            }
+        } else if command == "halts" || command.starts_with("halts ") {
+            let tickers = command
+                .split_whitespace()
+                .skip(1)
+                .map(str::to_string)
+                .collect();
+            match client.get_halts(HaltsRequest { tickers }).await {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting halts: {}", e),
+            }
+        } else if command == "actions" || command.starts_with("actions ") {
+            let ticker = command.strip_prefix("actions").unwrap().trim().to_string();
+            match client
+                .get_corporate_actions(CorporateActionsRequest { ticker })
+                .await
+            {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting corporate actions: {}", e),
+            }
+        } else if command.starts_with("buy ") || command.starts_with("sell ") {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            let side = if parts[0] == "buy" {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            };
+            let Some(request) = parse_order(side, &parts[1..]) else {
+                println!(
+                    "Usage: {} <ticker> <quantity> [limit] [day|gtc|ioc|fok]",
+                    parts[0]
+                );
+                continue;
+            };
+            match orders.submit_order(request).await {
+                Ok(response) => print!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error submitting order: {}", e),
+            }
+        } else if command.starts_with("cancel ") || command.starts_with("order ") {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            let Some(order_id) = parts.get(1).and_then(|id| id.parse().ok()) else {
+                println!("Usage: {} <order id>", parts[0]);
+                continue;
+            };
+            let response = if parts[0] == "cancel" {
+                orders.cancel_order(CancelOrderRequest { order_id }).await
+            } else {
+                orders.get_order(GetOrderRequest { order_id }).await
+            };
+            match response {
+                Ok(response) => print!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error with order {}: {}", order_id, e),
+            }
+        } else if command.starts_with("replace ") {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            let (order_id, quantity) = match (
+                parts.get(1).and_then(|id| id.parse().ok()),
+                parts.get(2).and_then(|quantity| quantity.parse().ok()),
+            ) {
+                (Some(order_id), Some(quantity)) if parts.len() <= 4 => (order_id, quantity),
+                _ => {
+                    println!("Usage: replace <order id> <quantity> [limit]");
+                    continue;
+                }
+            };
+            let limit_price = match parts.get(3) {
+                Some(text) => match parse_price(text) {
+                    Some(price) => Some(price),
+                    None => {
+                        println!("Invalid limit price: {}", text);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            match orders
+                .replace_order(ReplaceOrderRequest {
+                    order_id,
+                    quantity,
+                    limit_price,
+                })
+                .await
+            {
+                Ok(response) => print!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error replacing order {}: {}", order_id, e),
+            }
+        } else if command == "corr" || command.starts_with("corr ") {
+            let tickers = command
+                .split_whitespace()
+                .skip(1)
+                .map(str::to_string)
+                .collect();
+            match client
+                .get_correlation_matrix(CorrelationMatrixRequest { tickers, window: 0 })
+                .await
+            {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting correlation matrix: {}", e),
+            }
+        } else if command.starts_with("beta ") {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            if parts.len() != 3 {
+                println!("Usage: beta <ticker> <benchmark>");
+                continue;
+            }
+            match client
+                .get_beta(BetaRequest {
+                    ticker: parts[1].to_string(),
+                    benchmark: parts[2].to_string(),
+                    window: 0,
+                })
+                .await
+            {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting beta: {}", e),
+            }
+        } else if command.starts_with("indicators ") {
+            let ticker = command.strip_prefix("indicators ").unwrap().to_string();
+            // Zero periods ask the server for the conventional defaults.
+            let specs = [
+                indicator_spec::Kind::Sma(SmaSpec::default()),
+                indicator_spec::Kind::Ema(EmaSpec::default()),
+                indicator_spec::Kind::Rsi(RsiSpec::default()),
+                indicator_spec::Kind::Macd(MacdSpec::default()),
+                indicator_spec::Kind::Bollinger(BollingerSpec::default()),
+            ]
+            .into_iter()
+            .map(|kind| IndicatorSpec { kind: Some(kind) })
+            .collect();
+            match client
+                .get_indicators(IndicatorsRequest { ticker, specs })
+                .await
+            {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting indicators: {}", e),
+            }
+        } else if command.starts_with("candles ") {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            let interval = match parts.get(2).copied() {
+                Some("1s") => CandleInterval::OneSecond,
+                Some("1m") => CandleInterval::OneMinute,
+                Some("5m") => CandleInterval::FiveMinutes,
+                Some("1h") => CandleInterval::OneHour,
+                _ => {
+                    println!("Usage: candles <ticker> <1s|1m|5m|1h>");
+                    continue;
+                }
+            };
+            match client
+                .get_candles(CandlesRequest {
+                    ticker: parts[1].to_string(),
+                    interval: interval.into(),
+                    start: None,
+                    end: None,
+                    adjusted: false,
+                })
+                .await
+            {
+                Ok(response) => println!("{}", response.into_inner().formatted_message),
+                Err(e) => eprintln!("Error getting candles: {}", e),
+            }
         } else {
             // Handle ticker requests (single price or multiple prices)
             let parts: Vec<&str> = command.split_whitespace().collect();
@@ -102,6 +387,7 @@ pub async fn start_client(host: &str, port: u16) -> Result<(), Box<dyn std::erro
                     match client
                         .get_price(PriceRequest {
                             ticker: ticker.to_string(),
+                            interval: None,
                         })
                         .await
                     {
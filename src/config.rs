@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
@@ -7,12 +8,27 @@ use std::path::Path;
 pub struct Config {
     pub server: ServerConfig,
     pub client: ClientConfig,
+    #[serde(default)]
+    pub market: MarketConfig,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    #[serde(default)]
+    pub streams: StreamConfig,
+}
+
+/// Server policy for client-requested stream update intervals.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamConfig {
+    /// Shortest interval a client may request, in milliseconds.
+    #[serde(default = "default_min_stream_interval_ms")]
+    pub min_interval_ms: u64,
+    /// Longest interval a client may request, in milliseconds.
+    #[serde(default = "default_max_stream_interval_ms")]
+    pub max_interval_ms: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,17 +37,477 @@ pub struct ClientConfig {
     pub port: u16,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarketConfig {
+    /// Simulated market time covered by one generated tick, in seconds.
+    #[serde(default = "default_time_step_secs")]
+    pub time_step_secs: f64,
+    /// Wall-clock interval between market ticks, in milliseconds.
+    #[serde(default = "default_tick_interval_ms")]
+    pub tick_interval_ms: u64,
+    /// Seed for the simulation's random number generator. When set, the whole
+    /// simulated market is reproducible run to run; when absent a seed is
+    /// drawn at startup.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Per-ticker model parameters; tickers not listed here use built-in defaults.
+    #[serde(default)]
+    pub tickers: HashMap<String, TickerConfig>,
+    /// Correlation between the Brownian shocks of the listed tickers.
+    #[serde(default)]
+    pub correlation: Option<CorrelationConfig>,
+    /// When present, prices are replayed from recorded tick files instead of
+    /// being simulated.
+    #[serde(default)]
+    pub replay: Option<ReplayConfig>,
+    /// Per-ticker bid/ask settings; tickers not listed here use [`QuoteConfig::default`].
+    #[serde(default)]
+    pub quotes: HashMap<String, QuoteConfig>,
+    /// How much price history is kept per ticker.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    /// Instrument metadata by symbol. Symbols beyond the built-in universe
+    /// are listed in addition to it.
+    #[serde(default)]
+    pub instruments: HashMap<String, InstrumentConfig>,
+    /// Trading hours. Without one the simulated market never closes.
+    #[serde(default)]
+    pub calendar: Option<CalendarConfig>,
+    /// Limit-up/limit-down bands and circuit breakers. Without them no
+    /// ticker is ever halted.
+    #[serde(default)]
+    pub halts: Option<HaltConfig>,
+    /// Splits and dividends to apply while the market runs.
+    #[serde(default)]
+    pub corporate_actions: Vec<CorporateActionConfig>,
+    /// Simulated liquidity in the paper-trading order books.
+    #[serde(default)]
+    pub order_book: OrderBookConfig,
+}
+
+/// How much simulated liquidity each ticker's order book shows.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OrderBookConfig {
+    /// Price levels a side, starting at the quoted bid and ask.
+    #[serde(default = "default_book_levels")]
+    pub levels: u32,
+    /// Ticks between adjacent levels.
+    #[serde(default = "default_level_spacing_ticks")]
+    pub level_spacing_ticks: u32,
+}
+
+/// A scheduled corporate action.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CorporateActionConfig {
+    pub ticker: String,
+    /// When the action takes effect, as an RFC 3339 timestamp.
+    pub effective: String,
+    #[serde(flatten)]
+    pub kind: CorporateActionKindConfig,
+}
+
+/// Corporate action selected with the `action` key of an entry.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CorporateActionKindConfig {
+    /// `new_shares` shares for every `old_shares`, e.g. 4 for 1.
+    Split {
+        new_shares: u32,
+        #[serde(default = "default_old_shares")]
+        old_shares: u32,
+    },
+    /// Cash paid per share; the price drops by it on the ex-date.
+    Dividend { amount: f64 },
+}
+
+/// When price moves halt trading.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HaltConfig {
+    /// Percentage a ticker may move from its reference price before it is
+    /// halted.
+    #[serde(default = "default_band_pct")]
+    pub band_pct: f64,
+    /// Per-ticker overrides of `band_pct`.
+    #[serde(default)]
+    pub bands: HashMap<String, f64>,
+    /// How often a ticker's reference price is reset to its current price,
+    /// in seconds.
+    #[serde(default = "default_reference_secs")]
+    pub reference_secs: f64,
+    /// How long a band breach halts the ticker, in seconds.
+    #[serde(default = "default_halt_secs")]
+    pub halt_secs: f64,
+    /// Market-wide declines that halt every ticker, each triggering at most
+    /// once per regular session.
+    #[serde(default)]
+    pub circuit_breakers: Vec<CircuitBreakerLevel>,
+}
+
+/// A market-wide circuit breaker level.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CircuitBreakerLevel {
+    /// Average decline across tickers, in percent, that trips the level.
+    pub decline_pct: f64,
+    pub halt_secs: f64,
+}
+
+/// When the market trades, in the exchange's local time.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CalendarConfig {
+    /// IANA time zone name, e.g. `"America/New_York"`.
+    pub timezone: String,
+    #[serde(default)]
+    pub pre_market: Option<SessionHours>,
+    pub regular: SessionHours,
+    #[serde(default)]
+    pub after_hours: Option<SessionHours>,
+    /// Days the market opens, as three-letter English names.
+    #[serde(default = "default_trading_days")]
+    pub trading_days: Vec<String>,
+    /// Dates (`YYYY-MM-DD`) the market stays closed on a trading day.
+    #[serde(default)]
+    pub holidays: Vec<String>,
+}
+
+/// One session's local opening and closing times, as `HH:MM`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionHours {
+    pub open: String,
+    pub close: String,
+}
+
+/// Metadata for one instrument. Fields left out keep the built-in values for
+/// that symbol, or generic defaults for a new one.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct InstrumentConfig {
+    pub name: Option<String>,
+    pub exchange: Option<String>,
+    pub currency: Option<String>,
+    pub sector: Option<String>,
+    /// Smallest price increment.
+    pub tick_size: Option<f64>,
+    /// Number of shares in one round lot.
+    pub lot_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StatsConfig {
+    /// Span of the exponentially weighted moving average in `GetStats`, in
+    /// prices; each new price is weighted `2 / (span + 1)`.
+    #[serde(default = "default_ewma_span")]
+    pub ewma_span: f64,
+}
+
+/// Limits on the price history kept per ticker. With no sample limit and no
+/// age limit, history grows without bound.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RetentionConfig {
+    /// Most prices kept per ticker, oldest evicted first; 0 means no limit.
+    #[serde(default = "default_max_samples")]
+    pub max_samples: usize,
+    /// Prices older than this many seconds before a ticker's newest price are
+    /// evicted.
+    #[serde(default)]
+    pub max_age_secs: Option<f64>,
+}
+
+/// How the bid/ask quote around a ticker's last price is generated.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QuoteConfig {
+    /// Base spread between bid and ask, in basis points of the last price.
+    #[serde(default = "default_spread_bps")]
+    pub spread_bps: f64,
+    /// Narrowest spread quoted, in price units.
+    #[serde(default = "default_min_spread")]
+    pub min_spread: f64,
+    /// Extra spread per unit of absolute log return since the previous
+    /// price, so quotes widen when the market moves sharply.
+    #[serde(default = "default_volatility_widening")]
+    pub volatility_widening: f64,
+    /// Average size shown on each side of the quote, in shares; at most one
+    /// billion.
+    #[serde(default = "default_quote_size")]
+    pub mean_size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ReplayConfig {
+    /// Playback rate relative to the recorded inter-arrival times; 2.0 plays
+    /// twice as fast.
+    #[serde(default = "default_replay_speed")]
+    pub speed: f64,
+    #[serde(default)]
+    pub on_end: ReplayEnd,
+    /// Tick file per ticker. Files ending in `.jsonl` hold one JSON object per
+    /// line; anything else is read as CSV with a `timestamp,price[,volume]`
+    /// header. Timestamps are Unix seconds and may be fractional.
+    pub files: HashMap<String, String>,
+}
+
+/// What a replayed ticker does once its file is exhausted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayEnd {
+    /// Start again from the first tick.
+    #[default]
+    Loop,
+    /// Keep the last price and publish nothing further.
+    Stop,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CorrelationConfig {
+    /// Tickers covered by `matrix`, in row order; unlisted tickers move independently.
+    pub tickers: Vec<String>,
+    pub matrix: Vec<Vec<f64>>,
+}
+
+/// A ticker entry; one without a `model` key, as written before other models
+/// existed, is a GBM entry.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "toml::Table")]
+pub struct TickerConfig {
+    pub initial_price: f64,
+    pub model: ModelConfig,
+}
+
+#[derive(Deserialize)]
+struct TaggedTickerConfig {
+    initial_price: f64,
+    #[serde(flatten)]
+    model: ModelConfig,
+}
+
+impl TryFrom<toml::Table> for TickerConfig {
+    type Error = toml::de::Error;
+
+    fn try_from(mut entry: toml::Table) -> Result<Self, Self::Error> {
+        entry
+            .entry("model")
+            .or_insert_with(|| toml::Value::from("gbm"));
+        let TaggedTickerConfig {
+            initial_price,
+            model,
+        } = toml::Value::Table(entry).try_into()?;
+        Ok(TickerConfig {
+            initial_price,
+            model,
+        })
+    }
+}
+
+/// Price model selected with the `model` key of a ticker entry. All rates and
+/// volatilities are annualized.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum ModelConfig {
+    Gbm {
+        mu: f64,
+        sigma: f64,
+    },
+    /// Merton jump-diffusion: GBM plus Poisson jumps with log-normal sizes.
+    JumpDiffusion {
+        mu: f64,
+        sigma: f64,
+        /// Expected number of jumps per year.
+        jump_intensity: f64,
+        /// Mean of the log jump size.
+        jump_mean: f64,
+        /// Standard deviation of the log jump size.
+        jump_std: f64,
+    },
+    /// Mean-reverting Ornstein-Uhlenbeck process, suited to spreads and rates.
+    OrnsteinUhlenbeck {
+        /// Speed of mean reversion.
+        theta: f64,
+        /// Long-run level the process reverts to.
+        mean: f64,
+        sigma: f64,
+    },
+    /// Heston stochastic volatility.
+    Heston {
+        mu: f64,
+        /// Speed at which variance reverts to `theta`.
+        kappa: f64,
+        /// Long-run variance.
+        theta: f64,
+        /// Volatility of variance.
+        xi: f64,
+        /// Correlation between price and variance shocks.
+        rho: f64,
+        initial_variance: f64,
+    },
+}
+
+impl TickerConfig {
+    /// The `model` key this entry was configured with.
+    pub fn model_name(&self) -> &'static str {
+        match self.model {
+            ModelConfig::Gbm { .. } => "gbm",
+            ModelConfig::JumpDiffusion { .. } => "jump_diffusion",
+            ModelConfig::OrnsteinUhlenbeck { .. } => "ornstein_uhlenbeck",
+            ModelConfig::Heston { .. } => "heston",
+        }
+    }
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        MarketConfig {
+            time_step_secs: default_time_step_secs(),
+            tick_interval_ms: default_tick_interval_ms(),
+            seed: None,
+            tickers: HashMap::new(),
+            correlation: None,
+            replay: None,
+            quotes: HashMap::new(),
+            retention: RetentionConfig::default(),
+            stats: StatsConfig::default(),
+            instruments: HashMap::new(),
+            calendar: None,
+            halts: None,
+            corporate_actions: Vec::new(),
+            order_book: OrderBookConfig::default(),
+        }
+    }
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig {
+            ewma_span: default_ewma_span(),
+        }
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        StreamConfig {
+            min_interval_ms: default_min_stream_interval_ms(),
+            max_interval_ms: default_max_stream_interval_ms(),
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            max_samples: default_max_samples(),
+            max_age_secs: None,
+        }
+    }
+}
+
+impl Default for OrderBookConfig {
+    fn default() -> Self {
+        OrderBookConfig {
+            levels: default_book_levels(),
+            level_spacing_ticks: default_level_spacing_ticks(),
+        }
+    }
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig {
+            spread_bps: default_spread_bps(),
+            min_spread: default_min_spread(),
+            volatility_widening: default_volatility_widening(),
+            mean_size: default_quote_size(),
+        }
+    }
+}
+
+fn default_trading_days() -> Vec<String> {
+    ["Mon", "Tue", "Wed", "Thu", "Fri"]
+        .iter()
+        .map(|day| day.to_string())
+        .collect()
+}
+
+fn default_old_shares() -> u32 {
+    1
+}
+
+fn default_band_pct() -> f64 {
+    5.0
+}
+
+fn default_reference_secs() -> f64 {
+    300.0
+}
+
+fn default_halt_secs() -> f64 {
+    300.0
+}
+
+fn default_time_step_secs() -> f64 {
+    1.0
+}
+
+fn default_tick_interval_ms() -> u64 {
+    1000
+}
+
+fn default_replay_speed() -> f64 {
+    1.0
+}
+
+fn default_spread_bps() -> f64 {
+    2.0
+}
+
+fn default_min_spread() -> f64 {
+    0.01
+}
+
+fn default_volatility_widening() -> f64 {
+    1.0
+}
+
+fn default_quote_size() -> u64 {
+    500
+}
+
+fn default_book_levels() -> u32 {
+    5
+}
+
+fn default_level_spacing_ticks() -> u32 {
+    1
+}
+
+fn default_min_stream_interval_ms() -> u64 {
+    10
+}
+
+fn default_max_stream_interval_ms() -> u64 {
+    60_000
+}
+
+fn default_max_samples() -> usize {
+    10_000
+}
+
+fn default_ewma_span() -> f64 {
+    20.0
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
             server: ServerConfig {
                 host: "0.0.0.0".to_string(),
                 port: 50051,
+                streams: StreamConfig::default(),
             },
             client: ClientConfig {
                 host: get_default_client_host(),
                 port: 50051,
             },
+            market: MarketConfig::default(),
         }
     }
 }
@@ -64,6 +540,14 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
         config.client.host = host;
     }
 
+    if let Ok(seed) = env::var("SIMULATION_SEED") {
+        let seed = seed
+            .parse()
+            .map_err(|e| format!("Invalid SIMULATION_SEED {:?}: {}", seed, e))?;
+        println!("Environment override applied for SIMULATION_SEED: {}", seed);
+        config.market.seed = Some(seed);
+    }
+
     println!("Loaded configuration: {:?}", config);
     Ok(config)
 }
@@ -86,10 +570,12 @@ mod tests {
         // Store current env vars
         let original_client_host = env::var("GRPC_CLIENT_HOST").ok();
         let original_config_path = env::var("CONFIG_PATH").ok();
+        let original_seed = env::var("SIMULATION_SEED").ok();
         
         // Clean environment
         env::remove_var("GRPC_CLIENT_HOST");
         env::remove_var("CONFIG_PATH");
+        env::remove_var("SIMULATION_SEED");
         
         // Run the test
         let result = std::panic::catch_unwind(test);
@@ -103,6 +589,10 @@ mod tests {
             Some(val) => env::set_var("CONFIG_PATH", val),
             None => env::remove_var("CONFIG_PATH"),
         }
+        match original_seed {
+            Some(val) => env::set_var("SIMULATION_SEED", val),
+            None => env::remove_var("SIMULATION_SEED"),
+        }
         
         // Re-panic if the test panicked
         if let Err(err) = result {
@@ -150,6 +640,8 @@ mod tests {
 [server]
 host = "0.0.0.0"
 port = 50051
+[server.streams]
+min_interval_ms = 50
 [client]
 host = "grpc-finance-server"
 port = 50051
@@ -160,6 +652,8 @@ port = 50051
             let config = load_config().unwrap();
             assert_eq!(config.server.host, "0.0.0.0");
             assert_eq!(config.server.port, 50051);
+            assert_eq!(config.server.streams.min_interval_ms, 50);
+            assert_eq!(config.server.streams.max_interval_ms, 60_000);
             assert_eq!(config.client.host, "grpc-finance-server");
             assert_eq!(config.client.port, 50051);
 
@@ -168,4 +662,129 @@ port = 50051
             assert_eq!(config.client.host, "test-host");
         });
     }
+
+    #[test]
+    fn test_load_market_config() {
+        with_clean_env(|| {
+            let dir = tempdir().unwrap();
+            let config_path = dir.path().join("config.toml");
+            let config_content = r#"
+[server]
+host = "0.0.0.0"
+port = 50051
+[client]
+host = "grpc-finance-server"
+port = 50051
+[market]
+time_step_secs = 60.0
+[market.tickers.AAPL]
+initial_price = 190.0
+model = "gbm"
+mu = 0.08
+sigma = 0.25
+[market.tickers.TSLA]
+initial_price = 180.0
+model = "jump_diffusion"
+mu = 0.05
+sigma = 0.5
+jump_intensity = 4.0
+jump_mean = -0.02
+jump_std = 0.08
+[market.correlation]
+tickers = ["NVDA", "AMD"]
+matrix = [[1.0, 0.8], [0.8, 1.0]]
+[market.quotes.AAPL]
+spread_bps = 1.5
+mean_size = 200
+[market.retention]
+max_age_secs = 3600.0
+"#;
+            fs::write(&config_path, config_content).unwrap();
+            env::set_var("CONFIG_PATH", config_path.to_str().unwrap());
+
+            let config = load_config().unwrap();
+            assert_eq!(config.market.time_step_secs, 60.0);
+            assert_eq!(
+                config.market.tickers.get("AAPL"),
+                Some(&TickerConfig {
+                    initial_price: 190.0,
+                    model: ModelConfig::Gbm {
+                        mu: 0.08,
+                        sigma: 0.25,
+                    },
+                })
+            );
+            assert_eq!(
+                config.market.tickers.get("TSLA").map(|t| &t.model),
+                Some(&ModelConfig::JumpDiffusion {
+                    mu: 0.05,
+                    sigma: 0.5,
+                    jump_intensity: 4.0,
+                    jump_mean: -0.02,
+                    jump_std: 0.08,
+                })
+            );
+            assert_eq!(
+                config.market.correlation,
+                Some(CorrelationConfig {
+                    tickers: vec!["NVDA".to_string(), "AMD".to_string()],
+                    matrix: vec![vec![1.0, 0.8], vec![0.8, 1.0]],
+                })
+            );
+            assert_eq!(
+                config.market.quotes.get("AAPL"),
+                Some(&QuoteConfig {
+                    spread_bps: 1.5,
+                    mean_size: 200,
+                    ..QuoteConfig::default()
+                })
+            );
+            assert_eq!(
+                config.market.retention,
+                RetentionConfig {
+                    max_samples: 10_000,
+                    max_age_secs: Some(3600.0),
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_ticker_without_model_is_gbm() {
+        let tickers: HashMap<String, TickerConfig> = toml::from_str(
+            r#"
+[AAPL]
+initial_price = 190.0
+mu = 0.08
+sigma = 0.25
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            tickers["AAPL"],
+            TickerConfig {
+                initial_price: 190.0,
+                model: ModelConfig::Gbm {
+                    mu: 0.08,
+                    sigma: 0.25,
+                },
+            }
+        );
+        assert!(toml::from_str::<HashMap<String, TickerConfig>>(
+            "[AAPL]\ninitial_price = 190.0\nmodel = \"heston\"\nmu = 0.08\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_seed_env_override() {
+        with_clean_env(|| {
+            env::set_var("SIMULATION_SEED", "42");
+            let config = load_config().unwrap();
+            assert_eq!(config.market.seed, Some(42));
+
+            env::set_var("SIMULATION_SEED", "not-a-number");
+            assert!(load_config().is_err());
+        });
+    }
 }
@@ -0,0 +1,409 @@
+//! Technical indicators computed incrementally from a ticker's prices.
+
+use std::collections::VecDeque;
+
+/// Longest lookback accepted for any indicator.
+pub const MAX_INDICATOR_PERIOD: usize = 10_000;
+
+/// An indicator and its parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndicatorSpec {
+    /// Simple moving average.
+    Sma { period: usize },
+    /// Exponential moving average, seeded with the simple average of its
+    /// first `period` prices.
+    Ema { period: usize },
+    /// Wilder's relative strength index, between 0 and 100.
+    Rsi { period: usize },
+    /// Difference of a fast and a slow EMA, with an EMA of that difference
+    /// as the signal line.
+    Macd {
+        fast: usize,
+        slow: usize,
+        signal: usize,
+    },
+    /// Simple moving average with bands `num_std_dev` population standard
+    /// deviations above and below.
+    Bollinger { period: usize, num_std_dev: f64 },
+}
+
+impl IndicatorSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        let check_period = |name: &str, period: usize| {
+            if (1..=MAX_INDICATOR_PERIOD).contains(&period) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} period must be between 1 and {}, got {}",
+                    name, MAX_INDICATOR_PERIOD, period
+                ))
+            }
+        };
+        match *self {
+            IndicatorSpec::Sma { period } => check_period("SMA", period),
+            IndicatorSpec::Ema { period } => check_period("EMA", period),
+            IndicatorSpec::Rsi { period } => check_period("RSI", period),
+            IndicatorSpec::Macd { fast, slow, signal } => {
+                check_period("MACD fast", fast)?;
+                check_period("MACD slow", slow)?;
+                check_period("MACD signal", signal)?;
+                if fast >= slow {
+                    return Err(format!(
+                        "MACD fast period must be shorter than slow period, got {} and {}",
+                        fast, slow
+                    ));
+                }
+                Ok(())
+            }
+            IndicatorSpec::Bollinger {
+                period,
+                num_std_dev,
+            } => {
+                check_period("Bollinger", period)?;
+                if !(num_std_dev.is_finite() && num_std_dev > 0.0) {
+                    return Err(format!(
+                        "Bollinger num_std_dev must be positive, got {}",
+                        num_std_dev
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Short description such as `"MACD(12,26,9)"`.
+    pub fn label(&self) -> String {
+        match *self {
+            IndicatorSpec::Sma { period } => format!("SMA({})", period),
+            IndicatorSpec::Ema { period } => format!("EMA({})", period),
+            IndicatorSpec::Rsi { period } => format!("RSI({})", period),
+            IndicatorSpec::Macd { fast, slow, signal } => {
+                format!("MACD({},{},{})", fast, slow, signal)
+            }
+            IndicatorSpec::Bollinger {
+                period,
+                num_std_dev,
+            } => format!("BB({},{})", period, num_std_dev),
+        }
+    }
+}
+
+/// Current value of an indicator. Single-line indicators only use `value`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IndicatorValue {
+    /// The average for SMA and EMA, the index for RSI, the MACD line, or
+    /// the Bollinger middle band.
+    pub value: f64,
+    /// MACD signal line.
+    pub signal: f64,
+    /// MACD line minus signal line.
+    pub histogram: f64,
+    /// Upper Bollinger band.
+    pub upper: f64,
+    /// Lower Bollinger band.
+    pub lower: f64,
+}
+
+/// Simple moving average over a ring buffer of the last `period` prices.
+#[derive(Debug, Clone)]
+struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Sma {
+    fn new(period: usize) -> Self {
+        Sma {
+            period,
+            window: VecDeque::with_capacity(period),
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    fn update(&mut self, price: f64) -> Option<f64> {
+        self.window.push_back(price);
+        self.sum += price;
+        self.sum_squares += price * price;
+        if self.window.len() > self.period {
+            let old = self.window.pop_front().unwrap_or_default();
+            self.sum -= old;
+            self.sum_squares -= old * old;
+        }
+        self.value()
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+
+    /// Population standard deviation of the window.
+    fn std_deviation(&self) -> f64 {
+        let mean = self.sum / self.period as f64;
+        (self.sum_squares / self.period as f64 - mean * mean)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Ema {
+    alpha: f64,
+    seed: Sma,
+    value: Option<f64>,
+}
+
+impl Ema {
+    fn new(period: usize) -> Self {
+        Ema {
+            alpha: 2.0 / (period as f64 + 1.0),
+            seed: Sma::new(period),
+            value: None,
+        }
+    }
+
+    fn update(&mut self, price: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(value) => Some(value + self.alpha * (price - value)),
+            None => self.seed.update(price),
+        };
+        self.value
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rsi {
+    period: usize,
+    previous: Option<f64>,
+    changes: usize,
+    average_gain: f64,
+    average_loss: f64,
+}
+
+impl Rsi {
+    fn new(period: usize) -> Self {
+        Rsi {
+            period,
+            previous: None,
+            changes: 0,
+            average_gain: 0.0,
+            average_loss: 0.0,
+        }
+    }
+
+    fn update(&mut self, price: f64) -> Option<f64> {
+        let previous = self.previous.replace(price)?;
+        let change = price - previous;
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+        self.changes += 1;
+        let n = self.period as f64;
+        if self.changes <= self.period {
+            // The first averages are plain means of the first `period` changes.
+            self.average_gain += gain / n;
+            self.average_loss += loss / n;
+        } else {
+            // Wilder's smoothing afterwards.
+            self.average_gain = (self.average_gain * (n - 1.0) + gain) / n;
+            self.average_loss = (self.average_loss * (n - 1.0) + loss) / n;
+        }
+        if self.changes < self.period {
+            return None;
+        }
+        Some(if self.average_loss == 0.0 {
+            100.0
+        } else {
+            100.0 - 100.0 / (1.0 + self.average_gain / self.average_loss)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum State {
+    Sma(Sma),
+    Ema(Ema),
+    Rsi(Rsi),
+    Macd { fast: Ema, slow: Ema, signal: Ema },
+    Bollinger { sma: Sma, num_std_dev: f64 },
+}
+
+/// An indicator updated one price at a time, in constant time per price.
+#[derive(Debug, Clone)]
+pub struct Indicator {
+    spec: IndicatorSpec,
+    state: State,
+    value: Option<IndicatorValue>,
+}
+
+impl Indicator {
+    pub fn new(spec: IndicatorSpec) -> Result<Self, String> {
+        spec.validate()?;
+        let state = match spec {
+            IndicatorSpec::Sma { period } => State::Sma(Sma::new(period)),
+            IndicatorSpec::Ema { period } => State::Ema(Ema::new(period)),
+            IndicatorSpec::Rsi { period } => State::Rsi(Rsi::new(period)),
+            IndicatorSpec::Macd { fast, slow, signal } => State::Macd {
+                fast: Ema::new(fast),
+                slow: Ema::new(slow),
+                signal: Ema::new(signal),
+            },
+            IndicatorSpec::Bollinger {
+                period,
+                num_std_dev,
+            } => State::Bollinger {
+                sma: Sma::new(period),
+                num_std_dev,
+            },
+        };
+        Ok(Indicator {
+            spec,
+            state,
+            value: None,
+        })
+    }
+
+    pub fn spec(&self) -> IndicatorSpec {
+        self.spec
+    }
+
+    /// The latest value, or `None` until enough prices have been seen.
+    pub fn value(&self) -> Option<IndicatorValue> {
+        self.value
+    }
+
+    pub fn update(&mut self, price: f64) -> Option<IndicatorValue> {
+        let single = |value: Option<f64>| {
+            value.map(|value| IndicatorValue {
+                value,
+                ..IndicatorValue::default()
+            })
+        };
+        self.value = match &mut self.state {
+            State::Sma(sma) => single(sma.update(price)),
+            State::Ema(ema) => single(ema.update(price)),
+            State::Rsi(rsi) => single(rsi.update(price)),
+            State::Macd { fast, slow, signal } => {
+                let fast = fast.update(price);
+                let slow = slow.update(price);
+                match (fast, slow) {
+                    (Some(fast), Some(slow)) => {
+                        let macd = fast - slow;
+                        signal.update(macd).map(|signal| IndicatorValue {
+                            value: macd,
+                            signal,
+                            histogram: macd - signal,
+                            ..IndicatorValue::default()
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            State::Bollinger { sma, num_std_dev } => sma.update(price).map(|middle| {
+                let width = *num_std_dev * sma.std_deviation();
+                IndicatorValue {
+                    value: middle,
+                    upper: middle + width,
+                    lower: middle - width,
+                    ..IndicatorValue::default()
+                }
+            }),
+        };
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(spec: IndicatorSpec, prices: &[f64]) -> Option<IndicatorValue> {
+        let mut indicator = Indicator::new(spec).unwrap();
+        prices
+            .iter()
+            .map(|&price| indicator.update(price))
+            .last()
+            .flatten()
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_moving_averages() {
+        let prices = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(run(IndicatorSpec::Sma { period: 6 }, &prices), None);
+        assert_close(
+            run(IndicatorSpec::Sma { period: 3 }, &prices)
+                .unwrap()
+                .value,
+            4.0,
+        );
+
+        // Seeded with SMA(3) = 2 at the third price, then alpha = 0.5.
+        let ema = run(IndicatorSpec::Ema { period: 3 }, &prices).unwrap();
+        assert_close(ema.value, 4.0);
+        assert_close(
+            run(IndicatorSpec::Ema { period: 3 }, &[1.0, 2.0, 3.0, 10.0])
+                .unwrap()
+                .value,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn test_rsi() {
+        assert_eq!(run(IndicatorSpec::Rsi { period: 2 }, &[1.0, 2.0]), None);
+        let rising = run(IndicatorSpec::Rsi { period: 2 }, &[1.0, 2.0, 3.0]).unwrap();
+        assert_close(rising.value, 100.0);
+        // Changes +2 and -1: average gain 1, average loss 0.5, RS 2.
+        let mixed = run(IndicatorSpec::Rsi { period: 2 }, &[10.0, 12.0, 11.0]).unwrap();
+        assert_close(mixed.value, 100.0 - 100.0 / 3.0);
+    }
+
+    #[test]
+    fn test_macd_and_bollinger() {
+        let spec = IndicatorSpec::Macd {
+            fast: 2,
+            slow: 3,
+            signal: 2,
+        };
+        assert_eq!(run(spec, &[1.0, 2.0, 3.0]), None);
+        let macd = run(spec, &[1.0, 2.0, 3.0, 4.0]).unwrap();
+        // fast EMA: 1.5, 2.5, 3.5; slow EMA: 2, 3, so the MACD line is 0.5
+        // on both of the last two prices and the signal catches up.
+        assert_close(macd.value, 0.5);
+        assert_close(macd.signal, 0.5);
+        assert_close(macd.histogram, 0.0);
+
+        let bands = run(
+            IndicatorSpec::Bollinger {
+                period: 4,
+                num_std_dev: 2.0,
+            },
+            &[2.0, 4.0, 4.0, 6.0],
+        )
+        .unwrap();
+        // Mean 4, population standard deviation sqrt(2).
+        assert_close(bands.value, 4.0);
+        assert_close(bands.upper, 4.0 + 2.0 * 2f64.sqrt());
+        assert_close(bands.lower, 4.0 - 2.0 * 2f64.sqrt());
+    }
+
+    #[test]
+    fn test_invalid_specs_are_rejected() {
+        assert!(Indicator::new(IndicatorSpec::Sma { period: 0 }).is_err());
+        assert!(Indicator::new(IndicatorSpec::Macd {
+            fast: 26,
+            slow: 12,
+            signal: 9
+        })
+        .is_err());
+        assert!(Indicator::new(IndicatorSpec::Bollinger {
+            period: 20,
+            num_std_dev: -1.0
+        })
+        .is_err());
+    }
+}
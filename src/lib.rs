@@ -1,9 +1,14 @@
 pub mod client;
 pub mod config;
+pub mod indicators;
+pub mod market;
+pub mod price;
 pub mod server;
+pub mod stats;
 pub mod utils;
 
 // Include the generated protobuf code
+#[allow(clippy::large_enum_variant)]
 pub mod finance {
     tonic::include_proto!("finance");
 }
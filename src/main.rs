@@ -11,7 +11,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     match args.get(1).map(String::as_str) {
         Some("server") => {
             println!("Starting server...");
-            server::run_server(&config.server.host, config.server.port).await?;
+            server::run_server(&config).await?;
         }
         Some("client") => {
             println!("Starting client...");
@@ -0,0 +1,297 @@
+use crate::config::{CalendarConfig, SessionHours};
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+/// How far ahead [`TradingCalendar::next_change`] looks before concluding the
+/// session never changes; long enough to cover any run of holidays.
+const MAX_LOOKAHEAD_DAYS: u64 = 31;
+
+/// The part of the trading day the market is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarketSession {
+    PreMarket,
+    Regular,
+    AfterHours,
+    Closed,
+}
+
+impl MarketSession {
+    /// Whether prices are generated in this session.
+    pub fn is_open(self) -> bool {
+        self != MarketSession::Closed
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MarketSession::PreMarket => "pre-market",
+            MarketSession::Regular => "regular session",
+            MarketSession::AfterHours => "after-hours",
+            MarketSession::Closed => "closed",
+        }
+    }
+}
+
+/// The market moving from one session to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionChange {
+    pub previous: MarketSession,
+    pub current: MarketSession,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+struct Schedule {
+    timezone: Tz,
+    /// Non-overlapping `[open, close)` local times, earliest first.
+    sessions: Vec<(NaiveTime, NaiveTime, MarketSession)>,
+    trading_days: HashSet<Weekday>,
+    holidays: HashSet<NaiveDate>,
+}
+
+/// Trading hours in the exchange's time zone: optional pre-market and
+/// after-hours sessions around the regular session, on the configured
+/// weekdays except holidays.
+///
+/// A calendar built without a schedule is always in the regular session,
+/// which is how the simulator behaved before calendars existed.
+#[derive(Debug, Clone, Default)]
+pub struct TradingCalendar {
+    schedule: Option<Schedule>,
+}
+
+impl TradingCalendar {
+    /// A market that never closes.
+    pub fn always_open() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(config: &CalendarConfig) -> Result<Self, String> {
+        let timezone: Tz = config
+            .timezone
+            .parse()
+            .map_err(|_| format!("Unknown calendar timezone: {}", config.timezone))?;
+
+        let mut sessions = Vec::new();
+        for (hours, session) in [
+            (config.pre_market.as_ref(), MarketSession::PreMarket),
+            (Some(&config.regular), MarketSession::Regular),
+            (config.after_hours.as_ref(), MarketSession::AfterHours),
+        ] {
+            if let Some(hours) = hours {
+                let (open, close) = parse_hours(hours, session)?;
+                sessions.push((open, close, session));
+            }
+        }
+        // Sessions must run in order through the day without overlapping.
+        for pair in sessions.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                return Err(format!(
+                    "Calendar {} overlaps the {} before it",
+                    pair[1].2.label(),
+                    pair[0].2.label()
+                ));
+            }
+        }
+
+        let trading_days = config
+            .trading_days
+            .iter()
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| format!("Invalid calendar trading day: {}", day))
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+        let holidays = config
+            .holidays
+            .iter()
+            .map(|date| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid calendar holiday {:?}: use YYYY-MM-DD", date))
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        Ok(TradingCalendar {
+            schedule: Some(Schedule {
+                timezone,
+                sessions,
+                trading_days,
+                holidays,
+            }),
+        })
+    }
+
+    /// The configured time zone's name, or `"UTC"` for a market that never
+    /// closes.
+    pub fn timezone(&self) -> String {
+        self.schedule.as_ref().map_or_else(
+            || "UTC".to_string(),
+            |schedule| schedule.timezone.to_string(),
+        )
+    }
+
+    /// Whether the calendar has trading hours at all.
+    pub fn is_scheduled(&self) -> bool {
+        self.schedule.is_some()
+    }
+
+    pub fn session_at(&self, time: SystemTime) -> MarketSession {
+        let Some(schedule) = &self.schedule else {
+            return MarketSession::Regular;
+        };
+        let local = DateTime::<Utc>::from(time).with_timezone(&schedule.timezone);
+        if !schedule.is_trading_day(local.date_naive()) {
+            return MarketSession::Closed;
+        }
+        let now = local.time();
+        schedule
+            .sessions
+            .iter()
+            .find(|(open, close, _)| *open <= now && now < *close)
+            .map_or(MarketSession::Closed, |&(_, _, session)| session)
+    }
+
+    /// When the session next differs from the one at `time`, and what it
+    /// becomes; `None` for a market that never closes or never opens.
+    pub fn next_change(&self, time: SystemTime) -> Option<(SystemTime, MarketSession)> {
+        let schedule = self.schedule.as_ref()?;
+        let current = self.session_at(time);
+        let today = DateTime::<Utc>::from(time)
+            .with_timezone(&schedule.timezone)
+            .date_naive();
+        (0..=MAX_LOOKAHEAD_DAYS)
+            .filter_map(|offset| today.checked_add_days(Days::new(offset)))
+            .filter(|date| schedule.is_trading_day(*date))
+            .flat_map(|date| {
+                schedule
+                    .sessions
+                    .iter()
+                    .flat_map(move |&(open, close, _)| [date.and_time(open), date.and_time(close)])
+            })
+            // A boundary that falls in a daylight-saving gap does not exist.
+            .filter_map(|local| schedule.timezone.from_local_datetime(&local).earliest())
+            .map(SystemTime::from)
+            .filter(|boundary| *boundary > time)
+            .map(|boundary| (boundary, self.session_at(boundary)))
+            .find(|&(_, session)| session != current)
+    }
+}
+
+impl Schedule {
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.trading_days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+}
+
+fn parse_hours(
+    hours: &SessionHours,
+    session: MarketSession,
+) -> Result<(NaiveTime, NaiveTime), String> {
+    let parse = |time: &str| {
+        NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!("Invalid {} time {:?}: use HH:MM", session.label(), time))
+    };
+    let (open, close) = (parse(&hours.open)?, parse(&hours.close)?);
+    if open >= close {
+        return Err(format!(
+            "Calendar {} must open before it closes, got {} to {}",
+            session.label(),
+            hours.open,
+            hours.close
+        ));
+    }
+    Ok((open, close))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(open: &str, close: &str) -> SessionHours {
+        SessionHours {
+            open: open.to_string(),
+            close: close.to_string(),
+        }
+    }
+
+    fn new_york() -> CalendarConfig {
+        CalendarConfig {
+            timezone: "America/New_York".to_string(),
+            pre_market: Some(hours("04:00", "09:30")),
+            regular: hours("09:30", "16:00"),
+            after_hours: Some(hours("16:00", "20:00")),
+            trading_days: ["Mon", "Tue", "Wed", "Thu", "Fri"]
+                .iter()
+                .map(|day| day.to_string())
+                .collect(),
+            holidays: vec!["2024-07-04".to_string()],
+        }
+    }
+
+    /// `local` New York wall time on 2024-07-`day`, during daylight saving.
+    fn july(day: u32, local: &str) -> SystemTime {
+        let time = NaiveTime::parse_from_str(local, "%H:%M").unwrap();
+        let utc = NaiveDate::from_ymd_opt(2024, 7, day)
+            .unwrap()
+            .and_time(time)
+            + chrono::Duration::hours(4);
+        SystemTime::from(utc.and_utc())
+    }
+
+    #[test]
+    fn test_sessions_follow_local_time() {
+        let calendar = TradingCalendar::from_config(&new_york()).unwrap();
+        // Wednesday 3 July 2024.
+        assert_eq!(calendar.session_at(july(3, "03:59")), MarketSession::Closed);
+        assert_eq!(
+            calendar.session_at(july(3, "04:00")),
+            MarketSession::PreMarket
+        );
+        assert_eq!(
+            calendar.session_at(july(3, "09:30")),
+            MarketSession::Regular
+        );
+        assert_eq!(
+            calendar.session_at(july(3, "16:00")),
+            MarketSession::AfterHours
+        );
+        assert_eq!(calendar.session_at(july(3, "20:00")), MarketSession::Closed);
+        // Independence Day and a Saturday.
+        assert_eq!(calendar.session_at(july(4, "10:00")), MarketSession::Closed);
+        assert_eq!(calendar.session_at(july(6, "10:00")), MarketSession::Closed);
+
+        assert_eq!(
+            calendar.next_change(july(3, "12:00")),
+            Some((july(3, "16:00"), MarketSession::AfterHours))
+        );
+        // From the close on the 3rd, the holiday is skipped.
+        assert_eq!(
+            calendar.next_change(july(3, "21:00")),
+            Some((july(5, "04:00"), MarketSession::PreMarket))
+        );
+
+        let always = TradingCalendar::always_open();
+        assert_eq!(always.session_at(july(6, "10:00")), MarketSession::Regular);
+        assert_eq!(always.next_change(july(6, "10:00")), None);
+    }
+
+    #[test]
+    fn test_invalid_calendars_are_rejected() {
+        let mut config = new_york();
+        config.timezone = "Mars/Olympus_Mons".to_string();
+        assert!(TradingCalendar::from_config(&config).is_err());
+
+        let mut config = new_york();
+        config.after_hours = Some(hours("15:00", "20:00"));
+        assert!(TradingCalendar::from_config(&config).is_err());
+
+        let mut config = new_york();
+        config.regular = hours("16:00", "09:30");
+        assert!(TradingCalendar::from_config(&config).is_err());
+
+        let mut config = new_york();
+        config.holidays = vec!["July 4th".to_string()];
+        assert!(TradingCalendar::from_config(&config).is_err());
+    }
+}
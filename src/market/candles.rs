@@ -0,0 +1,324 @@
+use crate::price::Price;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Closed bars kept per ticker and interval; the oldest are dropped first.
+/// An hour of one-second bars fits.
+pub const MAX_CANDLES_PER_SERIES: usize = 3600;
+
+/// Bar width of an OHLCV candle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CandleInterval {
+    OneSecond,
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 4] = [
+        CandleInterval::OneSecond,
+        CandleInterval::OneMinute,
+        CandleInterval::FiveMinutes,
+        CandleInterval::OneHour,
+    ];
+
+    pub fn seconds(self) -> u64 {
+        match self {
+            CandleInterval::OneSecond => 1,
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 300,
+            CandleInterval::OneHour => 3600,
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        Duration::from_secs(self.seconds())
+    }
+
+    /// Short label such as `"5m"`, as accepted by [`parse`](Self::parse).
+    pub fn label(self) -> &'static str {
+        match self {
+            CandleInterval::OneSecond => "1s",
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::OneHour => "1h",
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|interval| interval.label().eq_ignore_ascii_case(label))
+    }
+
+    /// Start of the bar containing `time`. Bars are aligned to the Unix epoch,
+    /// so every one-minute bar starts on a whole minute.
+    pub fn bar_start(self, time: SystemTime) -> SystemTime {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        UNIX_EPOCH + Duration::from_secs(secs - secs % self.seconds())
+    }
+}
+
+/// Open, high, low, close and volume of one ticker over one bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub ticker: String,
+    pub interval: CandleInterval,
+    pub start: SystemTime,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    /// Sum of the volume reported with the bar's ticks; ticks without a
+    /// volume add nothing.
+    pub volume: u64,
+    pub tick_count: u64,
+    /// False while the bar is still forming.
+    pub complete: bool,
+}
+
+impl Candle {
+    fn open_at(
+        ticker: &str,
+        interval: CandleInterval,
+        price: Price,
+        volume: Option<u64>,
+        timestamp: SystemTime,
+    ) -> Self {
+        Candle {
+            ticker: ticker.to_string(),
+            interval,
+            start: interval.bar_start(timestamp),
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: volume.unwrap_or(0),
+            tick_count: 1,
+            complete: false,
+        }
+    }
+
+    pub fn end(&self) -> SystemTime {
+        self.start + self.interval.duration()
+    }
+
+    /// The bar with its prices and volume multiplied by the given factors,
+    /// as for a corporate action after it.
+    pub fn adjusted(&self, price_factor: f64, volume_factor: f64) -> Candle {
+        Candle {
+            open: self.open.scaled(price_factor),
+            high: self.high.scaled(price_factor),
+            low: self.low.scaled(price_factor),
+            close: self.close.scaled(price_factor),
+            volume: (self.volume as f64 * volume_factor).round() as u64,
+            ..self.clone()
+        }
+    }
+
+    fn update(&mut self, price: Price, volume: Option<u64>) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += volume.unwrap_or(0);
+        self.tick_count += 1;
+    }
+}
+
+#[derive(Default)]
+struct CandleSeries {
+    forming: Option<Candle>,
+    closed: VecDeque<Candle>,
+}
+
+impl CandleSeries {
+    fn close(&mut self) -> Option<Candle> {
+        let mut candle = self.forming.take()?;
+        candle.complete = true;
+        if self.closed.len() == MAX_CANDLES_PER_SERIES {
+            self.closed.pop_front();
+        }
+        self.closed.push_back(candle.clone());
+        Some(candle)
+    }
+}
+
+/// Builds candles at every [`CandleInterval`] from the stream of ticks.
+///
+/// A bar closes when a tick arrives in a later bar or, through
+/// [`close_until`](Self::close_until), when the wall clock passes its end,
+/// whichever comes first. Intervals without ticks produce no bar.
+#[derive(Default)]
+pub struct CandleAggregator {
+    series: HashMap<(String, CandleInterval), CandleSeries>,
+}
+
+impl CandleAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds one tick into every interval and returns the bars it closed.
+    pub fn record(
+        &mut self,
+        ticker: &str,
+        price: Price,
+        volume: Option<u64>,
+        timestamp: SystemTime,
+    ) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        for interval in CandleInterval::ALL {
+            let series = self
+                .series
+                .entry((ticker.to_string(), interval))
+                .or_default();
+            let bar_start = interval.bar_start(timestamp);
+            match &mut series.forming {
+                // A tick stamped slightly before the forming bar, e.g. from
+                // a replay restart, still belongs to the newest bar.
+                Some(candle) if candle.start >= bar_start => candle.update(price, volume),
+                _ => {
+                    closed.extend(series.close());
+                    series.forming =
+                        Some(Candle::open_at(ticker, interval, price, volume, timestamp));
+                }
+            }
+        }
+        closed
+    }
+
+    /// Closes every forming bar that ended at or before `now`.
+    pub fn close_until(&mut self, now: SystemTime) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        for series in self.series.values_mut() {
+            if series
+                .forming
+                .as_ref()
+                .is_some_and(|candle| candle.end() <= now)
+            {
+                closed.extend(series.close());
+            }
+        }
+        closed.sort_by_key(|candle| (candle.start, candle.interval.seconds()));
+        closed
+    }
+
+    /// Bars for `ticker` starting within `[start, end)`, oldest first,
+    /// including the forming bar when it falls in range.
+    pub fn candles(
+        &self,
+        ticker: &str,
+        interval: CandleInterval,
+        start: Option<SystemTime>,
+        end: Option<SystemTime>,
+    ) -> Vec<Candle> {
+        let Some(series) = self.series.get(&(ticker.to_string(), interval)) else {
+            return Vec::new();
+        };
+        series
+            .closed
+            .iter()
+            .chain(series.forming.as_ref())
+            .filter(|candle| start.map_or(true, |start| candle.start >= start))
+            .filter(|candle| end.map_or(true, |end| candle.start < end))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    fn at(secs: f64) -> SystemTime {
+        // An hour boundary, so every interval starts a bar at `at(0.0)`.
+        UNIX_EPOCH + Duration::from_secs_f64(1_699_999_200.0 + secs)
+    }
+
+    #[test]
+    fn test_ticks_build_ohlcv_bars() {
+        let mut candles = CandleAggregator::new();
+        assert!(candles
+            .record("AAPL", price(10.0), Some(5), at(0.1))
+            .is_empty());
+        assert!(candles
+            .record("AAPL", price(12.0), None, at(0.4))
+            .is_empty());
+        assert!(candles
+            .record("AAPL", price(9.0), Some(2), at(0.9))
+            .is_empty());
+
+        let closed = candles.record("AAPL", price(11.0), None, at(1.2));
+        assert_eq!(closed.len(), 1);
+        let bar = &closed[0];
+        assert_eq!(bar.interval, CandleInterval::OneSecond);
+        assert_eq!(
+            (bar.open, bar.high, bar.low, bar.close),
+            (price(10.0), price(12.0), price(9.0), price(9.0))
+        );
+        assert_eq!((bar.volume, bar.tick_count), (7, 3));
+        assert!(bar.complete);
+        assert_eq!(bar.start, at(0.0));
+
+        let minute = candles.candles("AAPL", CandleInterval::OneMinute, None, None);
+        assert_eq!(minute.len(), 1);
+        assert_eq!(
+            (minute[0].open, minute[0].close),
+            (price(10.0), price(11.0))
+        );
+        assert!(!minute[0].complete);
+    }
+
+    #[test]
+    fn test_bars_close_on_the_clock() {
+        let mut candles = CandleAggregator::new();
+        candles.record("AAPL", price(10.0), None, at(0.5));
+        assert!(candles.close_until(at(0.9)).is_empty());
+
+        let closed = candles.close_until(at(1.0));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].interval, CandleInterval::OneSecond);
+        // A bar is only emitted once.
+        assert!(candles.close_until(at(2.0)).is_empty());
+
+        let hour = candles.close_until(at(3600.0));
+        let intervals: Vec<_> = hour.iter().map(|candle| candle.interval).collect();
+        assert_eq!(
+            intervals,
+            vec![
+                CandleInterval::OneMinute,
+                CandleInterval::FiveMinutes,
+                CandleInterval::OneHour
+            ]
+        );
+    }
+
+    #[test]
+    fn test_candles_filters_by_range() {
+        let mut candles = CandleAggregator::new();
+        for secs in 0..5 {
+            candles.record("AAPL", price(secs as f64), None, at(secs as f64));
+        }
+        let bars = candles.candles(
+            "AAPL",
+            CandleInterval::OneSecond,
+            Some(at(1.0)),
+            Some(at(3.0)),
+        );
+        let opens: Vec<Price> = bars.iter().map(|candle| candle.open).collect();
+        assert_eq!(opens, vec![price(1.0), price(2.0)]);
+        assert!(candles
+            .candles("MSFT", CandleInterval::OneSecond, None, None)
+            .is_empty());
+        assert_eq!(
+            CandleInterval::parse("5M"),
+            Some(CandleInterval::FiveMinutes)
+        );
+        assert_eq!(CandleInterval::parse("2m"), None);
+    }
+}
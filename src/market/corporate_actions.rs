@@ -0,0 +1,320 @@
+use crate::config::{CorporateActionConfig, CorporateActionKindConfig};
+use crate::price::Price;
+use chrono::DateTime;
+use std::time::SystemTime;
+
+/// What a corporate action does to the share price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CorporateActionKind {
+    /// `new_shares` shares for every `old_shares`, dividing the price by
+    /// their ratio.
+    Split { new_shares: u32, old_shares: u32 },
+    /// Cash paid per share, taken off the price on the ex-date.
+    Dividend { amount: Price },
+}
+
+impl CorporateActionKind {
+    pub fn from_config(config: &CorporateActionKindConfig) -> Result<Self, String> {
+        let kind = match *config {
+            CorporateActionKindConfig::Split {
+                new_shares,
+                old_shares,
+            } => CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            },
+            CorporateActionKindConfig::Dividend { amount } => CorporateActionKind::Dividend {
+                amount: Price::from_f64(amount)
+                    .ok_or_else(|| format!("Invalid dividend amount: {}", amount))?,
+            },
+        };
+        kind.validate()?;
+        Ok(kind)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            } => {
+                if new_shares == 0 || old_shares == 0 || new_shares == old_shares {
+                    return Err(format!(
+                        "A split needs two different positive share counts, got {} for {}",
+                        new_shares, old_shares
+                    ));
+                }
+            }
+            CorporateActionKind::Dividend { amount } => {
+                if amount <= Price::ZERO {
+                    return Err(format!("Dividend amount must be positive, got {}", amount));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            } => format!("{}-for-{} split", new_shares, old_shares),
+            CorporateActionKind::Dividend { amount } => format!("${} dividend", amount),
+        }
+    }
+
+    /// The price after the action, on the `tick` grid, for a share last
+    /// priced at `price`.
+    pub fn apply(&self, price: Price, tick: Price) -> Result<Adjustment, String> {
+        let (after, volume_factor) = match *self {
+            CorporateActionKind::Split {
+                new_shares,
+                old_shares,
+            } => {
+                let ratio = new_shares as f64 / old_shares as f64;
+                (price.scaled(1.0 / ratio).round_to_tick(tick), ratio)
+            }
+            CorporateActionKind::Dividend { amount } => (
+                price
+                    .checked_sub(amount)
+                    .and_then(|after| after.round_to_tick(tick)),
+                1.0,
+            ),
+        };
+        let after = after.ok_or_else(|| {
+            format!(
+                "A {} would take the price from {} out of range",
+                self.describe(),
+                price
+            )
+        })?;
+        if price <= Price::ZERO || after <= Price::ZERO {
+            return Err(format!(
+                "A {} would take the price from {} to {}",
+                self.describe(),
+                price,
+                after
+            ));
+        }
+        Ok(Adjustment {
+            price_before: price,
+            price_after: after,
+            price_factor: after.to_f64() / price.to_f64(),
+            volume_factor,
+        })
+    }
+}
+
+/// How an applied action changed the price, and the factors that bring
+/// earlier history in line with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    pub price_before: Price,
+    pub price_after: Price,
+    /// Multiplies prices from before the action.
+    pub price_factor: f64,
+    /// Multiplies volumes from before the action.
+    pub volume_factor: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionStatus {
+    Pending,
+    Applied {
+        timestamp: SystemTime,
+        adjustment: Adjustment,
+    },
+    Rejected {
+        timestamp: SystemTime,
+        reason: String,
+    },
+}
+
+/// A split or dividend for one ticker, and what became of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorporateAction {
+    pub id: u64,
+    pub ticker: String,
+    pub kind: CorporateActionKind,
+    pub effective: SystemTime,
+    pub status: ActionStatus,
+}
+
+/// Every corporate action scheduled since startup, applied or not, in the
+/// order they were scheduled.
+#[derive(Debug, Clone, Default)]
+pub struct CorporateActionSchedule {
+    actions: Vec<CorporateAction>,
+}
+
+impl CorporateActionSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The configured actions, for tickers in `listed`.
+    pub fn from_config(
+        configs: &[CorporateActionConfig],
+        listed: &[String],
+    ) -> Result<Self, String> {
+        let mut schedule = Self::new();
+        for config in configs {
+            let ticker = config.ticker.to_uppercase();
+            if !listed.contains(&ticker) {
+                return Err(format!("Unknown ticker in corporate action: {}", ticker));
+            }
+            let effective = DateTime::parse_from_rfc3339(&config.effective).map_err(|e| {
+                format!(
+                    "Invalid effective time {:?} for {} corporate action: {}",
+                    config.effective, ticker, e
+                )
+            })?;
+            let kind = CorporateActionKind::from_config(&config.kind)
+                .map_err(|e| format!("Invalid corporate action for {}: {}", ticker, e))?;
+            schedule.schedule(&ticker, kind, effective.into())?;
+        }
+        Ok(schedule)
+    }
+
+    /// Adds a pending action and returns it.
+    pub fn schedule(
+        &mut self,
+        ticker: &str,
+        kind: CorporateActionKind,
+        effective: SystemTime,
+    ) -> Result<CorporateAction, String> {
+        kind.validate()?;
+        let action = CorporateAction {
+            id: self.actions.len() as u64 + 1,
+            ticker: ticker.to_string(),
+            kind,
+            effective,
+            status: ActionStatus::Pending,
+        };
+        self.actions.push(action.clone());
+        Ok(action)
+    }
+
+    /// Pending actions effective at or before `now`, earliest first.
+    pub fn due(&self, now: SystemTime) -> Vec<CorporateAction> {
+        let mut due: Vec<CorporateAction> = self
+            .actions
+            .iter()
+            .filter(|action| action.status == ActionStatus::Pending && action.effective <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|action| (action.effective, action.id));
+        due
+    }
+
+    /// Records what became of action `id` and returns it.
+    pub fn resolve(&mut self, id: u64, status: ActionStatus) -> Option<CorporateAction> {
+        let action = self.actions.iter_mut().find(|action| action.id == id)?;
+        action.status = status;
+        Some(action.clone())
+    }
+
+    /// `ticker`'s actions, or every ticker's when `None`.
+    pub fn actions(&self, ticker: Option<&str>) -> Vec<CorporateAction> {
+        self.actions
+            .iter()
+            .filter(|action| ticker.map_or(true, |ticker| action.ticker == ticker))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    #[test]
+    fn test_splits_and_dividends_adjust_the_price() {
+        let cent = price(0.01);
+        let split = CorporateActionKind::Split {
+            new_shares: 4,
+            old_shares: 1,
+        };
+        let adjustment = split.apply(price(190.01), cent).unwrap();
+        assert_eq!(adjustment.price_after, price(47.5));
+        assert_eq!(adjustment.volume_factor, 4.0);
+        assert!((adjustment.price_factor - 47.5 / 190.01).abs() < 1e-12);
+
+        let dividend = CorporateActionKind::Dividend {
+            amount: price(0.24),
+        };
+        let adjustment = dividend.apply(price(100.0), cent).unwrap();
+        assert_eq!(adjustment.price_after, price(99.76));
+        assert_eq!(adjustment.volume_factor, 1.0);
+        assert!(dividend.apply(price(0.2), cent).is_err());
+        // A price the grid cannot represent is an error, not a wrapped value.
+        let reverse = CorporateActionKind::Split {
+            new_shares: 1,
+            old_shares: 4,
+        };
+        assert!(reverse
+            .apply(Price::from_nanos(i64::MAX), price(1.0))
+            .is_err());
+
+        assert!(CorporateActionKind::Split {
+            new_shares: 1,
+            old_shares: 1
+        }
+        .validate()
+        .is_err());
+        assert_eq!(split.describe(), "4-for-1 split");
+        assert_eq!(dividend.describe(), "$0.24 dividend");
+    }
+
+    #[test]
+    fn test_schedule_releases_due_actions_in_order() {
+        let listed = vec!["AAPL".to_string(), "MSFT".to_string()];
+        let config = |ticker: &str, effective: &str| CorporateActionConfig {
+            ticker: ticker.to_string(),
+            effective: effective.to_string(),
+            kind: CorporateActionKindConfig::Dividend { amount: 0.5 },
+        };
+        let mut schedule = CorporateActionSchedule::from_config(
+            &[
+                config("msft", "2024-06-03T13:30:00Z"),
+                config("AAPL", "2024-06-01T13:30:00Z"),
+            ],
+            &listed,
+        )
+        .unwrap();
+        assert!(CorporateActionSchedule::from_config(
+            &[config("XYZ", "2024-06-01T13:30:00Z")],
+            &listed
+        )
+        .is_err());
+        assert!(
+            CorporateActionSchedule::from_config(&[config("AAPL", "June 1st")], &listed).is_err()
+        );
+
+        let june = |day: &str| -> SystemTime {
+            DateTime::parse_from_rfc3339(&format!("2024-06-{}T14:00:00Z", day))
+                .unwrap()
+                .into()
+        };
+        assert_eq!(schedule.due(june("01")).len(), 1);
+        let due = schedule.due(june("04"));
+        assert_eq!(
+            due.iter()
+                .map(|action| action.ticker.as_str())
+                .collect::<Vec<_>>(),
+            ["AAPL", "MSFT"]
+        );
+
+        let status = ActionStatus::Rejected {
+            timestamp: june("04"),
+            reason: "no price".to_string(),
+        };
+        let resolved = schedule.resolve(due[0].id, status.clone()).unwrap();
+        assert_eq!(resolved.status, status);
+        assert_eq!(schedule.due(june("04")).len(), 1);
+        assert_eq!(schedule.actions(Some("AAPL")), vec![resolved]);
+        assert_eq!(schedule.actions(None).len(), 2);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::config::CorrelationConfig;
+
+/// Tolerance used when checking symmetry, the unit diagonal and positive
+/// semi-definiteness of a correlation matrix.
+const TOLERANCE: f64 = 1e-9;
+
+/// Lower-triangular Cholesky factor `L` of a correlation matrix `C = L * L^T`.
+///
+/// Multiplying a vector of independent standard normals by `L` yields normals
+/// with correlation `C`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CholeskyFactor {
+    lower: Vec<Vec<f64>>,
+}
+
+impl CholeskyFactor {
+    pub fn identity(n: usize) -> Self {
+        let lower = (0..n)
+            .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+            .collect();
+        CholeskyFactor { lower }
+    }
+
+    /// Factors `matrix`, which must be a symmetric, positive semi-definite
+    /// matrix with a unit diagonal and entries in [-1, 1].
+    ///
+    /// Singular (semi-definite) matrices are accepted: a zero pivot simply
+    /// makes that asset a linear combination of the preceding ones.
+    pub fn new(matrix: &[Vec<f64>]) -> Result<Self, String> {
+        let n = matrix.len();
+        if let Some((i, row)) = matrix.iter().enumerate().find(|(_, row)| row.len() != n) {
+            return Err(format!(
+                "Correlation matrix must be square: row {} has {} entries, expected {}",
+                i,
+                row.len(),
+                n
+            ));
+        }
+        for (i, row) in matrix.iter().enumerate() {
+            if (row[i] - 1.0).abs() > TOLERANCE {
+                return Err(format!(
+                    "Correlation matrix diagonal must be 1, got {} at ({}, {})",
+                    row[i], i, i
+                ));
+            }
+            for (j, &value) in row.iter().enumerate() {
+                if !value.is_finite() || value.abs() > 1.0 + TOLERANCE {
+                    return Err(format!(
+                        "Correlation must be within [-1, 1], got {} at ({}, {})",
+                        value, i, j
+                    ));
+                }
+                if (value - matrix[j][i]).abs() > TOLERANCE {
+                    return Err(format!(
+                        "Correlation matrix must be symmetric: ({}, {}) = {} but ({}, {}) = {}",
+                        i, j, value, j, i, matrix[j][i]
+                    ));
+                }
+            }
+        }
+
+        let mut lower = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..=i {
+                let dot: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+                if i == j {
+                    let pivot = matrix[i][i] - dot;
+                    if pivot < -TOLERANCE {
+                        return Err(format!(
+                            "Correlation matrix is not positive semi-definite (pivot {} at row {})",
+                            pivot, i
+                        ));
+                    }
+                    lower[i][i] = pivot.max(0.0).sqrt();
+                } else if lower[j][j] > TOLERANCE {
+                    lower[i][j] = (matrix[i][j] - dot) / lower[j][j];
+                } else if (matrix[i][j] - dot).abs() > TOLERANCE {
+                    // A zero pivot leaves no freedom to match this entry.
+                    return Err(format!(
+                        "Correlation matrix is not positive semi-definite (row {}, column {})",
+                        i, j
+                    ));
+                }
+            }
+        }
+        Ok(CholeskyFactor { lower })
+    }
+
+    /// Adds an asset uncorrelated with every existing one.
+    pub fn push_independent(&mut self) {
+        for row in &mut self.lower {
+            row.push(0.0);
+        }
+        let n = self.lower.len();
+        let mut row = vec![0.0; n + 1];
+        row[n] = 1.0;
+        self.lower.push(row);
+    }
+
+    /// Transforms independent standard normals into correlated ones.
+    pub fn correlate(&self, independent: &[f64]) -> Vec<f64> {
+        self.lower
+            .iter()
+            .map(|row| row.iter().zip(independent).map(|(l, z)| l * z).sum())
+            .collect()
+    }
+}
+
+/// Expands a configured correlation block over a subset of tickers into a
+/// full matrix ordered like `universe`; unlisted tickers are independent.
+pub fn build_correlation_matrix(
+    universe: &[&str],
+    config: &CorrelationConfig,
+) -> Result<Vec<Vec<f64>>, String> {
+    if config.matrix.len() != config.tickers.len() {
+        return Err(format!(
+            "Correlation matrix has {} rows but {} tickers are listed",
+            config.matrix.len(),
+            config.tickers.len()
+        ));
+    }
+    let mut positions = Vec::with_capacity(config.tickers.len());
+    for ticker in &config.tickers {
+        let position = universe
+            .iter()
+            .position(|&t| t == ticker)
+            .ok_or_else(|| format!("Unknown ticker in correlation config: {}", ticker))?;
+        if positions.contains(&position) {
+            return Err(format!(
+                "Duplicate ticker in correlation config: {}",
+                ticker
+            ));
+        }
+        positions.push(position);
+    }
+
+    let n = universe.len();
+    let mut matrix: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    for (row, &i) in config.matrix.iter().zip(&positions) {
+        if row.len() != positions.len() {
+            return Err(format!(
+                "Correlation matrix row for {} has {} entries, expected {}",
+                universe[i],
+                row.len(),
+                positions.len()
+            ));
+        }
+        for (&value, &j) in row.iter().zip(&positions) {
+            matrix[i][j] = value;
+        }
+    }
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cholesky_reconstructs_matrix() {
+        let matrix = vec![
+            vec![1.0, 0.8, 0.3],
+            vec![0.8, 1.0, 0.5],
+            vec![0.3, 0.5, 1.0],
+        ];
+        let factor = CholeskyFactor::new(&matrix).unwrap();
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, &expected) in row.iter().enumerate() {
+                let value: f64 = (0..3)
+                    .map(|k| factor.lower[i][k] * factor.lower[j][k])
+                    .sum();
+                assert!((value - expected).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_semi_definite_matrix_is_accepted() {
+        let matrix = vec![vec![1.0, 1.0], vec![1.0, 1.0]];
+        let factor = CholeskyFactor::new(&matrix).unwrap();
+        let z = factor.correlate(&[0.7, -2.0]);
+        assert!((z[0] - z[1]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_matrices_are_rejected() {
+        // Pairwise plausible but jointly impossible correlations.
+        let indefinite = vec![
+            vec![1.0, 0.9, -0.9],
+            vec![0.9, 1.0, 0.9],
+            vec![-0.9, 0.9, 1.0],
+        ];
+        assert!(CholeskyFactor::new(&indefinite).is_err());
+        assert!(CholeskyFactor::new(&[vec![1.0, 0.5], vec![0.4, 1.0]]).is_err());
+        assert!(CholeskyFactor::new(&[vec![2.0]]).is_err());
+    }
+
+    #[test]
+    fn test_build_correlation_matrix_embeds_block() {
+        let config = CorrelationConfig {
+            tickers: vec!["C".to_string(), "A".to_string()],
+            matrix: vec![vec![1.0, 0.6], vec![0.6, 1.0]],
+        };
+        let matrix = build_correlation_matrix(&["A", "B", "C"], &config).unwrap();
+        assert_eq!(matrix[0][2], 0.6);
+        assert_eq!(matrix[2][0], 0.6);
+        assert_eq!(matrix[0][1], 0.0);
+        assert_eq!(matrix[1][1], 1.0);
+    }
+}
@@ -0,0 +1,209 @@
+use super::Side;
+use crate::price::Price;
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthAction {
+    /// A price level that was not in the book.
+    Add,
+    /// A new total quantity at an existing level.
+    Modify,
+    /// A level that is no longer in the book.
+    Delete,
+}
+
+/// One price level changing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelUpdate {
+    pub side: Side,
+    pub price: Price,
+    /// Total quantity at the level afterwards; zero for deletes.
+    pub quantity: u64,
+    pub action: DepthAction,
+}
+
+/// Every level one change to a ticker's book touched. Sequence numbers
+/// count the changes to each ticker's book, starting from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthUpdate {
+    pub ticker: String,
+    pub sequence: u64,
+    pub timestamp: SystemTime,
+    pub levels: Vec<LevelUpdate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthLevel {
+    pub price: Price,
+    pub quantity: u64,
+}
+
+/// A ticker's book as of update `sequence`; zero before its first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthSnapshot {
+    pub ticker: String,
+    pub sequence: u64,
+    pub timestamp: SystemTime,
+    /// Best first on both sides.
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+/// Total quantity at each price on both sides of a book, as a client
+/// building it from a snapshot and updates would hold it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DepthBook {
+    bids: BTreeMap<Price, u64>,
+    asks: BTreeMap<Price, u64>,
+}
+
+impl DepthBook {
+    pub fn from_snapshot(snapshot: &DepthSnapshot) -> Self {
+        let levels = |levels: &[DepthLevel]| {
+            levels
+                .iter()
+                .map(|level| (level.price, level.quantity))
+                .collect()
+        };
+        DepthBook {
+            bids: levels(&snapshot.bids),
+            asks: levels(&snapshot.asks),
+        }
+    }
+
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<Price, u64> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// Adds `quantity` at `price`.
+    pub fn add(&mut self, side: Side, price: Price, quantity: u64) {
+        *self.levels_mut(side).entry(price).or_default() += quantity;
+    }
+
+    pub fn apply(&mut self, levels: &[LevelUpdate]) {
+        for level in levels {
+            let book = self.levels_mut(level.side);
+            match level.action {
+                DepthAction::Delete => book.remove(&level.price),
+                DepthAction::Add | DepthAction::Modify => book.insert(level.price, level.quantity),
+            };
+        }
+    }
+
+    /// The best `levels` levels a side, or the whole book when zero.
+    pub fn top(&self, levels: usize) -> DepthBook {
+        let levels = if levels == 0 { usize::MAX } else { levels };
+        DepthBook {
+            bids: self
+                .bids
+                .iter()
+                .rev()
+                .take(levels)
+                .map(|(&price, &quantity)| (price, quantity))
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(levels)
+                .map(|(&price, &quantity)| (price, quantity))
+                .collect(),
+        }
+    }
+
+    /// The updates that turn this book into `next`, bids first.
+    pub fn changes_to(&self, next: &DepthBook) -> Vec<LevelUpdate> {
+        let mut changes = Vec::new();
+        for (side, before, after) in [
+            (Side::Buy, &self.bids, &next.bids),
+            (Side::Sell, &self.asks, &next.asks),
+        ] {
+            for (&price, &quantity) in after {
+                let action = match before.get(&price) {
+                    None => DepthAction::Add,
+                    Some(&old) if old != quantity => DepthAction::Modify,
+                    Some(_) => continue,
+                };
+                changes.push(LevelUpdate {
+                    side,
+                    price,
+                    quantity,
+                    action,
+                });
+            }
+            changes.extend(
+                before
+                    .keys()
+                    .filter(|price| !after.contains_key(price))
+                    .map(|&price| LevelUpdate {
+                        side,
+                        price,
+                        quantity: 0,
+                        action: DepthAction::Delete,
+                    }),
+            );
+        }
+        changes
+    }
+
+    pub fn snapshot(&self, ticker: &str, sequence: u64, timestamp: SystemTime) -> DepthSnapshot {
+        let level = |(&price, &quantity): (&Price, &u64)| DepthLevel { price, quantity };
+        DepthSnapshot {
+            ticker: ticker.to_string(),
+            sequence,
+            timestamp,
+            bids: self.bids.iter().rev().map(level).collect(),
+            asks: self.asks.iter().map(level).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    #[test]
+    fn test_changes_rebuild_the_book() {
+        let mut before = DepthBook::default();
+        before.add(Side::Buy, price(99.0), 100);
+        before.add(Side::Buy, price(98.0), 200);
+        before.add(Side::Sell, price(101.0), 100);
+        let mut after = before.clone();
+        after.add(Side::Buy, price(99.0), 50);
+        after.add(Side::Sell, price(100.5), 10);
+        after.apply(&[LevelUpdate {
+            side: Side::Buy,
+            price: price(98.0),
+            quantity: 0,
+            action: DepthAction::Delete,
+        }]);
+
+        let changes = before.changes_to(&after);
+        let actions: Vec<(Side, DepthAction)> = changes
+            .iter()
+            .map(|change| (change.side, change.action))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (Side::Buy, DepthAction::Modify),
+                (Side::Buy, DepthAction::Delete),
+                (Side::Sell, DepthAction::Add),
+            ]
+        );
+        let mut rebuilt = before.clone();
+        rebuilt.apply(&changes);
+        assert_eq!(rebuilt, after);
+
+        // A level falling out of the top one is deleted from that view.
+        let top = after.top(1);
+        let snapshot = top.snapshot("AAPL", 7, SystemTime::now());
+        assert_eq!(snapshot.asks[0].price, price(100.5));
+        assert_eq!(DepthBook::from_snapshot(&snapshot), top);
+        assert_eq!(after.top(0), after);
+    }
+}
@@ -0,0 +1,1114 @@
+use super::{
+    ActionStatus, Candle, CandleAggregator, CandleInterval, CorporateAction, CorporateActionKind,
+    CorporateActionSchedule, DepthSnapshot, DepthUpdate, ExecutionReport, Halt, HaltChange,
+    HaltCheck, HaltMonitor, Instrument, MarketSession, MarketSimulator, Order, OrderRequest,
+    PaperExchange, Quote, QuoteGenerator, SessionChange, TickerRegistry, Trade, TradeGenerator,
+    TradingCalendar,
+};
+use crate::config::{InstrumentConfig, MarketConfig};
+use crate::price::{decimals_for_tick, Price, MIN_DECIMALS};
+use crate::utils::PriceTracker;
+use std::collections::{HashMap, HashSet};
+use std::sync::{
+    Arc, Mutex as StdMutex, MutexGuard, PoisonError, RwLock as StdRwLock, RwLockReadGuard,
+    RwLockWriteGuard,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+
+/// Number of ticks a slow subscriber may fall behind before it starts
+/// missing updates.
+const BUS_CAPACITY: usize = 1024;
+
+/// A new price from a market data source, before the feed has stamped it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceUpdate {
+    pub ticker: String,
+    pub price: f64,
+    /// Traded volume, when the source records one; otherwise the feed
+    /// draws the size of the trade it prints.
+    pub volume: Option<u64>,
+}
+
+/// A published price, stamped with its event time and per-ticker sequence number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    pub ticker: String,
+    /// On the instrument's tick grid.
+    pub price: Price,
+    /// Shares in the trade printed at `price`.
+    pub volume: u64,
+    pub timestamp: SystemTime,
+    pub sequence: u64,
+    /// Bid/ask around `price` at the same instant.
+    pub quote: Quote,
+    /// The instrument's tick size when the price was published.
+    pub tick_size: Price,
+}
+
+impl Tick {
+    /// Decimal places the tick's prices are shown with.
+    pub fn decimals(&self) -> u32 {
+        decimals_for_tick(self.tick_size)
+    }
+}
+
+/// The single authoritative view of the market.
+///
+/// Every tick is recorded in the shared [`PriceTracker`], stored as the
+/// ticker's latest price and then broadcast to all subscribers, so readers of
+/// any of the three always agree on what the market did. Ticks are also
+/// folded into candles, which are broadcast on their own channel as each bar
+/// closes, and printed as trades on the tape, whose sizes are the volume the
+/// tracker and candles record. Only instruments in the feed's
+/// [`TickerRegistry`] are published.
+///
+/// The feed also follows the [`TradingCalendar`]: it announces each session
+/// change to subscribers and remembers every ticker's close when the regular
+/// session ends. Prices that breach a ticker's limit-up/limit-down band, or
+/// arrive while it is halted, are not published; halts starting and ending
+/// are broadcast on their own channel.
+///
+/// Paper orders trade in the feed's [`PaperExchange`], whose simulated
+/// liquidity follows every published quote; execution reports and changes to
+/// each book's depth are broadcast on their own channels too.
+#[derive(Clone)]
+pub struct MarketFeed {
+    sender: broadcast::Sender<Tick>,
+    latest: Arc<RwLock<HashMap<String, Tick>>>,
+    tracker: Arc<Mutex<PriceTracker>>,
+    quotes: Arc<Mutex<QuoteGenerator>>,
+    trades: Arc<Mutex<TradeGenerator>>,
+    trade_sender: broadcast::Sender<Trade>,
+    candles: Arc<Mutex<CandleAggregator>>,
+    candle_sender: broadcast::Sender<Candle>,
+    /// Only ever held briefly and never across an await, so a blocking lock
+    /// lets synchronous code check listings too.
+    registry: Arc<StdRwLock<TickerRegistry>>,
+    calendar: Arc<TradingCalendar>,
+    session: Arc<StdRwLock<MarketSession>>,
+    session_sender: broadcast::Sender<SessionChange>,
+    /// Each ticker's latest tick as of the last regular session close.
+    closes: Arc<RwLock<HashMap<String, Tick>>>,
+    /// Never held across an await, like the registry.
+    halts: Arc<StdMutex<HaltMonitor>>,
+    halt_sender: broadcast::Sender<HaltChange>,
+    /// Never held across an await, like the registry.
+    corporate_actions: Arc<StdMutex<CorporateActionSchedule>>,
+    /// Never held across an await, like the registry.
+    exchange: Arc<StdMutex<PaperExchange>>,
+    execution_sender: broadcast::Sender<ExecutionReport>,
+    depth_sender: broadcast::Sender<DepthUpdate>,
+}
+
+impl Default for MarketFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MarketFeed {
+    pub fn new() -> Self {
+        Self::with_parts(
+            PriceTracker::new(),
+            QuoteGenerator::default(),
+            TradeGenerator::default(),
+            TickerRegistry::new(),
+            TradingCalendar::always_open(),
+            HaltMonitor::new(),
+            CorporateActionSchedule::new(),
+            PaperExchange::new(),
+        )
+    }
+
+    /// Creates a feed with the configured history tracking, quoting, trading
+    /// calendar, halt rules, corporate actions and order books, drawing quote
+    /// and trade sizes from `seed`.
+    pub fn from_config(config: &MarketConfig, seed: u64) -> Result<Self, String> {
+        let calendar = match &config.calendar {
+            Some(calendar) => TradingCalendar::from_config(calendar)?,
+            None => TradingCalendar::always_open(),
+        };
+        let halts = match &config.halts {
+            Some(halts) => HaltMonitor::from_config(halts)?,
+            None => HaltMonitor::new(),
+        };
+        let registry = TickerRegistry::from_config(config)?;
+        let corporate_actions =
+            CorporateActionSchedule::from_config(&config.corporate_actions, &registry.symbols())?;
+        Ok(Self::with_parts(
+            PriceTracker::from_config(config)?,
+            QuoteGenerator::from_config(config, seed)?,
+            TradeGenerator::new(seed),
+            registry,
+            calendar,
+            halts,
+            corporate_actions,
+            PaperExchange::from_config(&config.order_book)?,
+        ))
+    }
+
+    /// A feed following `calendar`, otherwise like [`new`](Self::new).
+    pub fn with_calendar(calendar: TradingCalendar) -> Self {
+        Self::with_parts(
+            PriceTracker::new(),
+            QuoteGenerator::default(),
+            TradeGenerator::default(),
+            TickerRegistry::new(),
+            calendar,
+            HaltMonitor::new(),
+            CorporateActionSchedule::new(),
+            PaperExchange::new(),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_parts(
+        tracker: PriceTracker,
+        quotes: QuoteGenerator,
+        trades: TradeGenerator,
+        registry: TickerRegistry,
+        calendar: TradingCalendar,
+        halts: HaltMonitor,
+        corporate_actions: CorporateActionSchedule,
+        exchange: PaperExchange,
+    ) -> Self {
+        let (sender, _) = broadcast::channel(BUS_CAPACITY);
+        let (candle_sender, _) = broadcast::channel(BUS_CAPACITY);
+        let (session_sender, _) = broadcast::channel(BUS_CAPACITY);
+        let (halt_sender, _) = broadcast::channel(BUS_CAPACITY);
+        let (execution_sender, _) = broadcast::channel(BUS_CAPACITY);
+        let (depth_sender, _) = broadcast::channel(BUS_CAPACITY);
+        let (trade_sender, _) = broadcast::channel(BUS_CAPACITY);
+        let session = calendar.session_at(SystemTime::now());
+        MarketFeed {
+            sender,
+            latest: Arc::new(RwLock::new(HashMap::new())),
+            tracker: Arc::new(Mutex::new(tracker)),
+            quotes: Arc::new(Mutex::new(quotes)),
+            trades: Arc::new(Mutex::new(trades)),
+            trade_sender,
+            candles: Arc::new(Mutex::new(CandleAggregator::new())),
+            candle_sender,
+            registry: Arc::new(StdRwLock::new(registry)),
+            calendar: Arc::new(calendar),
+            session: Arc::new(StdRwLock::new(session)),
+            session_sender,
+            closes: Arc::new(RwLock::new(HashMap::new())),
+            halts: Arc::new(StdMutex::new(halts)),
+            halt_sender,
+            corporate_actions: Arc::new(StdMutex::new(corporate_actions)),
+            exchange: Arc::new(StdMutex::new(exchange)),
+            execution_sender,
+            depth_sender,
+        }
+    }
+
+    fn registry(&self) -> RwLockReadGuard<'_, TickerRegistry> {
+        self.registry.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn halt_monitor(&self) -> MutexGuard<'_, HaltMonitor> {
+        self.halts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn action_schedule(&self) -> MutexGuard<'_, CorporateActionSchedule> {
+        self.corporate_actions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn paper_exchange(&self) -> MutexGuard<'_, PaperExchange> {
+        self.exchange.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs `change` on the exchange and broadcasts the depth updates it
+    /// caused before letting go of the lock, so every ticker's updates go out
+    /// in sequence order.
+    fn with_exchange<T>(&self, change: impl FnOnce(&mut PaperExchange) -> T) -> T {
+        let mut exchange = self.paper_exchange();
+        let result = change(&mut exchange);
+        for update in exchange.take_depth_updates() {
+            let _ = self.depth_sender.send(update);
+        }
+        result
+    }
+
+    fn registry_mut(&self) -> RwLockWriteGuard<'_, TickerRegistry> {
+        self.registry
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether `ticker` is currently listed.
+    pub fn is_listed(&self, ticker: &str) -> bool {
+        self.registry().contains(ticker)
+    }
+
+    /// Listed symbols, in listing order.
+    pub fn symbols(&self) -> Vec<String> {
+        self.registry().symbols()
+    }
+
+    pub fn instrument(&self, ticker: &str) -> Option<Instrument> {
+        self.registry().get(ticker).cloned()
+    }
+
+    pub fn instruments(&self) -> Vec<Instrument> {
+        self.registry().instruments().to_vec()
+    }
+
+    /// Decimal places `ticker`'s prices are shown with; delisted tickers keep
+    /// the default.
+    pub fn decimals(&self, ticker: &str) -> u32 {
+        self.registry()
+            .get(ticker)
+            .map_or(MIN_DECIMALS, Instrument::decimals)
+    }
+
+    /// Changes with every listing, delisting or modification.
+    pub fn registry_version(&self) -> u64 {
+        self.registry().version()
+    }
+
+    /// Lists `instrument` and publishes its opening `price`. The listing is
+    /// undone if the price cannot be published, e.g. during a halt.
+    pub async fn list(&self, instrument: Instrument, price: f64) -> Result<Tick, String> {
+        let ticker = instrument.symbol.clone();
+        self.registry_mut().add(instrument)?;
+        let mut ticks = self
+            .publish(vec![PriceUpdate {
+                ticker: ticker.clone(),
+                price,
+                volume: None,
+            }])
+            .await;
+        match ticks.pop() {
+            Some(tick) => Ok(tick),
+            None => {
+                self.registry_mut().delist(&ticker)?;
+                Err(match self.halt(&ticker) {
+                    Some(halt) => format!(
+                        "{} cannot open while trading is halted ({:?})",
+                        ticker, halt.reason
+                    ),
+                    None => format!("{} cannot open at {}", ticker, price),
+                })
+            }
+        }
+    }
+
+    pub fn modify_instrument(
+        &self,
+        ticker: &str,
+        changes: &InstrumentConfig,
+    ) -> Result<Instrument, String> {
+        self.registry_mut().modify(ticker, changes)
+    }
+
+    /// Stops publishing `ticker` and cancels its open orders. Its history
+    /// stays available.
+    pub fn delist(&self, ticker: &str) -> Result<Instrument, String> {
+        let instrument = self.registry_mut().delist(ticker)?;
+        let reason = format!("{} was delisted", ticker);
+        let cancelled =
+            self.with_exchange(|exchange| exchange.cancel_all(ticker, &reason, SystemTime::now()));
+        self.send_executions(cancelled);
+        Ok(instrument)
+    }
+
+    pub fn tracker(&self) -> Arc<Mutex<PriceTracker>> {
+        self.tracker.clone()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Tick> {
+        self.sender.subscribe()
+    }
+
+    pub async fn latest(&self, ticker: &str) -> Option<Tick> {
+        self.latest.read().await.get(ticker).cloned()
+    }
+
+    /// Receives every candle as its bar closes.
+    pub fn subscribe_candles(&self) -> broadcast::Receiver<Candle> {
+        self.candle_sender.subscribe()
+    }
+
+    /// `ticker`'s bars starting within `[start, end)`, oldest first; the
+    /// forming bar is included, marked incomplete.
+    pub async fn candles(
+        &self,
+        ticker: &str,
+        interval: CandleInterval,
+        start: Option<SystemTime>,
+        end: Option<SystemTime>,
+    ) -> Vec<Candle> {
+        self.candles
+            .lock()
+            .await
+            .candles(ticker, interval, start, end)
+    }
+
+    pub fn calendar(&self) -> &TradingCalendar {
+        &self.calendar
+    }
+
+    /// The session the market is in, as last announced.
+    pub fn session(&self) -> MarketSession {
+        *self.session.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Receives every session change as it happens.
+    pub fn subscribe_sessions(&self) -> broadcast::Receiver<SessionChange> {
+        self.session_sender.subscribe()
+    }
+
+    /// Moves the market into `current` at `timestamp` and announces it;
+    /// `None` when the market was already in that session. Leaving the
+    /// regular session records every ticker's close.
+    pub async fn change_session(
+        &self,
+        current: MarketSession,
+        timestamp: SystemTime,
+    ) -> Option<SessionChange> {
+        let previous = {
+            let mut session = self.session.write().unwrap_or_else(PoisonError::into_inner);
+            std::mem::replace(&mut *session, current)
+        };
+        if previous == current {
+            return None;
+        }
+        if previous == MarketSession::Regular {
+            let latest = self.latest.read().await.clone();
+            *self.closes.write().await = latest;
+        }
+        if current == MarketSession::Regular {
+            self.halt_monitor().reset_market_reference();
+        }
+        if current == MarketSession::Closed {
+            let expired = self.with_exchange(|exchange| exchange.expire_day_orders(timestamp));
+            self.send_executions(expired);
+        }
+        let change = SessionChange {
+            previous,
+            current,
+            timestamp,
+        };
+        let _ = self.session_sender.send(change);
+        Some(change)
+    }
+
+    /// `ticker`'s last tick of the most recent regular session, if one has
+    /// closed since the feed started.
+    pub async fn last_close(&self, ticker: &str) -> Option<Tick> {
+        self.closes.read().await.get(ticker).cloned()
+    }
+
+    /// The halt stopping trading in `ticker` right now, if any.
+    pub fn halt(&self, ticker: &str) -> Option<Halt> {
+        self.halt_monitor().halt(ticker, SystemTime::now()).cloned()
+    }
+
+    /// Every halt in force, market-wide ones first.
+    pub fn halts(&self) -> Vec<Halt> {
+        self.halt_monitor().halts(SystemTime::now())
+    }
+
+    /// Receives every halt as it starts and ends.
+    pub fn subscribe_halts(&self) -> broadcast::Receiver<HaltChange> {
+        self.halt_sender.subscribe()
+    }
+
+    /// Submits a paper order on listed `ticker`. Orders are rejected while
+    /// the market is closed or the ticker halted; otherwise they match
+    /// against its book straight away. Returns every resulting report, the
+    /// order's own first; a request that is not a valid order is an error.
+    pub fn submit_order(&self, request: OrderRequest) -> Result<Vec<ExecutionReport>, String> {
+        let instrument = self
+            .instrument(&request.ticker)
+            .ok_or_else(|| format!("{} is not listed", request.ticker))?;
+        self.paper_exchange()
+            .validate(&request, instrument.tick_size)?;
+        let now = SystemTime::now();
+        let rejection = if !self.session().is_open() {
+            Some("The market is closed".to_string())
+        } else {
+            self.halt(&request.ticker).map(|halt| {
+                format!(
+                    "Trading in {} is halted ({})",
+                    request.ticker,
+                    halt.reason.label()
+                )
+            })
+        };
+        let reports = self.with_exchange(|exchange| match rejection {
+            Some(reason) => Ok(vec![exchange.reject(request, reason, now)]),
+            None => exchange.submit(request, instrument.tick_size, now),
+        })?;
+        self.send_executions(reports.clone());
+        Ok(reports)
+    }
+
+    /// Cancels what is left of open order `id`.
+    pub fn cancel_order(&self, id: u64) -> Result<ExecutionReport, String> {
+        let report = self.with_exchange(|exchange| exchange.cancel(id, SystemTime::now()))?;
+        self.send_executions(vec![report.clone()]);
+        Ok(report)
+    }
+
+    /// Changes open order `id`'s quantity or limit price; see
+    /// [`PaperExchange::replace`].
+    pub fn replace_order(
+        &self,
+        id: u64,
+        quantity: Option<u64>,
+        limit_price: Option<Price>,
+    ) -> Result<Vec<ExecutionReport>, String> {
+        let ticker = self
+            .order(id)
+            .map(|order| order.ticker)
+            .ok_or_else(|| format!("Unknown order {}", id))?;
+        // A delisted ticker's orders are already cancelled.
+        let tick_size = self
+            .instrument(&ticker)
+            .map(|instrument| instrument.tick_size)
+            .ok_or_else(|| format!("Order {} is already cancelled", id))?;
+        let reports = self.with_exchange(|exchange| {
+            exchange.replace(id, quantity, limit_price, tick_size, SystemTime::now())
+        })?;
+        self.send_executions(reports.clone());
+        Ok(reports)
+    }
+
+    pub fn order(&self, id: u64) -> Option<Order> {
+        self.paper_exchange().order(id).cloned()
+    }
+
+    /// Receives every trade printed on the tape.
+    pub fn subscribe_trades(&self) -> broadcast::Receiver<Trade> {
+        self.trade_sender.subscribe()
+    }
+
+    /// Receives a report of every order event as it happens.
+    pub fn subscribe_executions(&self) -> broadcast::Receiver<ExecutionReport> {
+        self.execution_sender.subscribe()
+    }
+
+    /// `ticker`'s book aggregated by price, `levels` deep on each side or
+    /// whole when zero, as of its latest depth update.
+    pub fn depth_snapshot(&self, ticker: &str, levels: usize) -> DepthSnapshot {
+        self.paper_exchange().depth(ticker, levels)
+    }
+
+    /// Receives every change to every ticker's book, in sequence order per
+    /// ticker.
+    pub fn subscribe_depth(&self) -> broadcast::Receiver<DepthUpdate> {
+        self.depth_sender.subscribe()
+    }
+
+    fn send_executions(&self, reports: Vec<ExecutionReport>) {
+        for report in reports {
+            let _ = self.execution_sender.send(report);
+        }
+    }
+
+    /// Schedules a corporate action on listed `ticker`.
+    pub fn schedule_corporate_action(
+        &self,
+        ticker: &str,
+        kind: CorporateActionKind,
+        effective: SystemTime,
+    ) -> Result<CorporateAction, String> {
+        if !self.is_listed(ticker) {
+            return Err(format!("{} is not listed", ticker));
+        }
+        self.action_schedule().schedule(ticker, kind, effective)
+    }
+
+    /// `ticker`'s corporate actions, or every ticker's when `None`, in the
+    /// order they were scheduled.
+    pub fn corporate_actions(&self, ticker: Option<&str>) -> Vec<CorporateAction> {
+        self.action_schedule().actions(ticker)
+    }
+
+    /// Applies every pending corporate action effective by `now` and returns
+    /// them resolved. Each moves its ticker's price from the latest tick,
+    /// publishes the new price and notes the adjustment in the tracker.
+    /// Actions on tickers without a price yet, or whose new price cannot be
+    /// published, e.g. while the ticker is halted, wait for a later call.
+    pub async fn apply_corporate_actions(&self, now: SystemTime) -> Vec<CorporateAction> {
+        let due = self.action_schedule().due(now);
+        let mut resolved = Vec::with_capacity(due.len());
+        for action in due {
+            let Some(tick) = self.latest(&action.ticker).await else {
+                continue;
+            };
+            if self.halt(&action.ticker).is_some() {
+                continue;
+            }
+            let status = match action.kind.apply(tick.price, tick.tick_size) {
+                Ok(adjustment) => {
+                    // Resting limits are priced for the old shares.
+                    let reason = format!("Cancelled for the {}", action.kind.describe());
+                    let cancelled = self.with_exchange(|exchange| {
+                        exchange.cancel_all(&action.ticker, &reason, now)
+                    });
+                    self.send_executions(cancelled);
+                    self.quotes.lock().await.reset(&action.ticker);
+                    self.trades.lock().await.reset(&action.ticker);
+                    self.halt_monitor()
+                        .rebase(&action.ticker, adjustment.price_factor);
+                    let published = self
+                        .publish(vec![PriceUpdate {
+                            ticker: action.ticker.clone(),
+                            price: adjustment.price_after.to_f64(),
+                            volume: None,
+                        }])
+                        .await;
+                    if published.is_empty() {
+                        self.halt_monitor()
+                            .rebase(&action.ticker, 1.0 / adjustment.price_factor);
+                        continue;
+                    }
+                    self.tracker.lock().await.record_adjustment(
+                        &action.ticker,
+                        now,
+                        adjustment.price_factor,
+                        adjustment.volume_factor,
+                    );
+                    println!(
+                        "Applied {} to {}: {} -> {}",
+                        action.kind.describe(),
+                        action.ticker,
+                        adjustment.price_before,
+                        adjustment.price_after
+                    );
+                    ActionStatus::Applied {
+                        timestamp: now,
+                        adjustment,
+                    }
+                }
+                Err(reason) => {
+                    eprintln!("Rejected corporate action on {}: {}", action.ticker, reason);
+                    ActionStatus::Rejected {
+                        timestamp: now,
+                        reason,
+                    }
+                }
+            };
+            resolved.extend(self.action_schedule().resolve(action.id, status));
+        }
+        resolved
+    }
+
+    /// Closes and broadcasts every bar that ended at or before `now`.
+    pub async fn close_candles(&self, now: SystemTime) -> Vec<Candle> {
+        let closed = self.candles.lock().await.close_until(now);
+        for candle in closed.iter().cloned() {
+            let _ = self.candle_sender.send(candle);
+        }
+        closed
+    }
+
+    /// Records and fans out one time step's worth of updates. Every update in
+    /// the batch shares the same event time, and the tracker assigns each its
+    /// ticker's next sequence number and a quote around its new price.
+    ///
+    /// Prices are rounded to the instrument's tick size first, so everything
+    /// downstream sees the same on-tick value. Halts that are over are lifted
+    /// before the batch is checked against the halt rules.
+    pub async fn publish(&self, updates: Vec<PriceUpdate>) -> Vec<Tick> {
+        let updates: Vec<(PriceUpdate, Price, Price)> = {
+            let registry = self.registry();
+            updates
+                .into_iter()
+                .filter_map(|update| {
+                    let instrument = registry.get(&update.ticker)?;
+                    let Some(price) = instrument.round(update.price) else {
+                        eprintln!(
+                            "Dropping unrepresentable price {} for {}",
+                            update.price, update.ticker
+                        );
+                        return None;
+                    };
+                    Some((update, price, instrument.tick_size))
+                })
+                .collect()
+        };
+        let timestamp = SystemTime::now();
+        let mut halt_changes = Vec::new();
+        let updates: Vec<(PriceUpdate, Price, Price)> = {
+            let mut halts = self.halt_monitor();
+            let ended = halts.lift_expired(timestamp);
+            halt_changes.extend(
+                ended
+                    .into_iter()
+                    .map(|halt| HaltChange { halt, ended: true }),
+            );
+            let accepted = updates
+                .into_iter()
+                .filter(
+                    |(update, price, _)| match halts.check(&update.ticker, *price, timestamp) {
+                        HaltCheck::Accepted => true,
+                        HaltCheck::Halted => false,
+                        HaltCheck::Triggered(halt) => {
+                            halt_changes.push(HaltChange { halt, ended: false });
+                            false
+                        }
+                    },
+                )
+                .collect();
+            halt_changes.extend(
+                halts
+                    .check_market(timestamp)
+                    .map(|halt| HaltChange { halt, ended: false }),
+            );
+            accepted
+        };
+        for change in halt_changes {
+            let halt = &change.halt;
+            println!(
+                "Trading {} in {} ({}, {:+.2}%)",
+                if change.ended { "resumed" } else { "halted" },
+                halt.ticker.as_deref().unwrap_or("all tickers"),
+                halt.reason.label(),
+                halt.move_pct
+            );
+            let _ = self.halt_sender.send(change);
+        }
+        let mut closed = Vec::new();
+        let mut printed = Vec::new();
+        let ticks: Vec<Tick> = {
+            let mut tracker = self.tracker.lock().await;
+            let mut quotes = self.quotes.lock().await;
+            let mut trades = self.trades.lock().await;
+            let mut candles = self.candles.lock().await;
+            let mut latest = self.latest.write().await;
+            updates
+                .into_iter()
+                .map(|(update, price, tick_size)| {
+                    let quote = quotes.quote(&update.ticker, price, tick_size);
+                    let trade =
+                        trades.print(&update.ticker, price, &quote, update.volume, timestamp);
+                    let volume = trade.size;
+                    printed.push(trade);
+                    let sequence = tracker.record_trade(&update.ticker, price, volume, timestamp);
+                    closed.extend(candles.record(&update.ticker, price, Some(volume), timestamp));
+                    let tick = Tick {
+                        ticker: update.ticker,
+                        price,
+                        volume,
+                        timestamp,
+                        sequence,
+                        quote,
+                        tick_size,
+                    };
+                    latest.insert(tick.ticker.clone(), tick.clone());
+                    tick
+                })
+                .collect()
+        };
+        let executions: Vec<ExecutionReport> = self.with_exchange(|exchange| {
+            ticks
+                .iter()
+                .flat_map(|tick| {
+                    exchange.reseed(&tick.ticker, &tick.quote, tick.tick_size, timestamp)
+                })
+                .collect()
+        });
+        // Bars closed by this batch go out before the ticks that closed them.
+        for candle in closed {
+            let _ = self.candle_sender.send(candle);
+        }
+        for tick in ticks.iter().cloned() {
+            // An error only means nobody is subscribed right now.
+            let _ = self.sender.send(tick);
+        }
+        for trade in printed {
+            let _ = self.trade_sender.send(trade);
+        }
+        self.send_executions(executions);
+        ticks
+    }
+}
+
+/// Spawns the candle clock: on every whole second it closes the bars that
+/// have ended, so a bar is emitted on time even when no later tick arrives
+/// to close it.
+pub fn spawn_candle_clock(feed: MarketFeed) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let into_second = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.subsec_nanos())
+                .unwrap_or(0);
+            sleep(Duration::from_nanos(1_000_000_000 - into_second as u64)).await;
+            feed.close_candles(SystemTime::now()).await;
+        }
+    })
+}
+
+/// Spawns the session clock: sleeps until the calendar's next session change
+/// and announces it on the feed, for as long as the market has hours.
+pub fn spawn_session_clock(feed: MarketFeed) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some((at, session)) = feed.calendar().next_change(SystemTime::now()) {
+            sleep(at.duration_since(SystemTime::now()).unwrap_or_default()).await;
+            if let Some(change) = feed.change_session(session, at).await {
+                println!(
+                    "Market session changed from {} to {}",
+                    change.previous.label(),
+                    change.current.label()
+                );
+            }
+        }
+    })
+}
+
+/// Spawns the market clock: publishes the simulator's starting prices, then
+/// advances the whole universe once per `tick_interval` while the market is
+/// open. Instruments listed on the feed while it runs join the simulation
+/// from their opening price, and corporate actions fall due on the clock,
+/// moving the simulated price with them.
+pub fn spawn_market_clock(
+    mut simulator: MarketSimulator,
+    feed: MarketFeed,
+    tick_interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let to_updates = |prices: Vec<(String, f64)>| {
+            prices
+                .into_iter()
+                .map(|(ticker, price)| PriceUpdate {
+                    ticker,
+                    price,
+                    volume: None,
+                })
+                .collect::<Vec<_>>()
+        };
+        feed.publish(to_updates(simulator.prices())).await;
+
+        let mut registry_version = feed.registry_version();
+        let mut listed: HashSet<String> = feed.symbols().into_iter().collect();
+        let mut clock = interval(tick_interval);
+        clock.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately; the starting prices cover it.
+        clock.tick().await;
+        loop {
+            clock.tick().await;
+            if feed.registry_version() != registry_version {
+                registry_version = feed.registry_version();
+                let now_listed: HashSet<String> = feed.symbols().into_iter().collect();
+                for ticker in now_listed.difference(&listed) {
+                    if let Some(tick) = feed.latest(ticker).await {
+                        simulator.list(ticker, tick.price.to_f64());
+                    }
+                }
+                listed = now_listed;
+            }
+            // Prices stand still outside trading hours.
+            if !feed.session().is_open() {
+                continue;
+            }
+            for action in feed.apply_corporate_actions(SystemTime::now()).await {
+                if let ActionStatus::Applied { adjustment, .. } = action.status {
+                    simulator.list(&action.ticker, adjustment.price_after.to_f64());
+                }
+            }
+            // Delisted tickers keep evolving but are no longer published.
+            feed.publish(to_updates(simulator.step())).await;
+        }
+    })
+}
+
+/// An untraded update of `ticker` to `price`, for tests.
+#[cfg(test)]
+pub(crate) fn update(ticker: &str, price: f64) -> PriceUpdate {
+    PriceUpdate {
+        ticker: ticker.to_string(),
+        price,
+        volume: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CircuitBreakerLevel, HaltConfig};
+    use crate::market::{ExecutionType, HaltReason, OrderStatus, OrderType, Side, TimeInForce};
+    use crate::utils::TICKERS;
+
+    #[tokio::test]
+    async fn test_publish_updates_every_view() {
+        let feed = MarketFeed::new();
+        let mut receiver = feed.subscribe();
+        let first = feed.publish(vec![update("AAPL", 150.0)]).await;
+        assert_eq!(first[0].sequence, 1);
+        let second = feed.publish(vec![update("AAPL", 151.006)]).await;
+        assert_eq!(second[0].sequence, 2);
+        assert!(second[0].timestamp >= first[0].timestamp);
+
+        assert_eq!(receiver.recv().await.unwrap(), first[0]);
+        assert_eq!(receiver.recv().await.unwrap(), second[0]);
+        assert_eq!(feed.latest("AAPL").await.as_ref(), second.first());
+        assert_eq!(
+            feed.tracker().lock().await.get_prices("AAPL"),
+            Some(vec![first[0].price, second[0].price])
+        );
+        // Published prices sit on AAPL's one-cent tick.
+        assert_eq!(second[0].price.to_f64(), 151.01);
+    }
+
+    #[tokio::test]
+    async fn test_candles_close_on_the_clock() {
+        let feed = MarketFeed::new();
+        let mut candles = feed.subscribe_candles();
+        feed.publish(vec![PriceUpdate {
+            volume: Some(10),
+            ..update("AAPL", 150.0)
+        }])
+        .await;
+
+        let forming = feed
+            .candles("AAPL", CandleInterval::OneSecond, None, None)
+            .await;
+        assert_eq!(forming.len(), 1);
+        assert!(!forming[0].complete);
+
+        // Longer bars may end at the same instant, so look for the 1s bar.
+        let closed = feed.close_candles(forming[0].end()).await;
+        assert_eq!(candles.recv().await.unwrap(), closed[0]);
+        let bar = closed
+            .iter()
+            .find(|candle| candle.interval == CandleInterval::OneSecond)
+            .unwrap();
+        assert_eq!((bar.close.to_f64(), bar.volume), (150.0, 10));
+        assert!(bar.complete);
+    }
+
+    #[tokio::test]
+    async fn test_leaving_the_regular_session_records_closes() {
+        let feed = MarketFeed::new();
+        let mut sessions = feed.subscribe_sessions();
+        assert_eq!(feed.session(), MarketSession::Regular);
+        let closing = feed.publish(vec![update("AAPL", 150.0)]).await;
+        assert_eq!(feed.last_close("AAPL").await, None);
+
+        let now = SystemTime::now();
+        let change = feed
+            .change_session(MarketSession::AfterHours, now)
+            .await
+            .unwrap();
+        assert_eq!(sessions.recv().await.unwrap(), change);
+        assert_eq!(change.previous, MarketSession::Regular);
+        assert_eq!(feed.session(), MarketSession::AfterHours);
+        assert_eq!(
+            feed.change_session(MarketSession::AfterHours, now).await,
+            None
+        );
+
+        // After-hours trading moves the price but not the close.
+        feed.publish(vec![update("AAPL", 151.0)]).await;
+        feed.change_session(MarketSession::Closed, now).await;
+        assert_eq!(feed.last_close("AAPL").await.as_ref(), closing.first());
+    }
+
+    #[tokio::test]
+    async fn test_band_breaches_halt_publishing() {
+        let config = MarketConfig {
+            halts: Some(HaltConfig {
+                band_pct: 5.0,
+                bands: HashMap::new(),
+                reference_secs: 300.0,
+                halt_secs: 300.0,
+                circuit_breakers: Vec::new(),
+            }),
+            ..MarketConfig::default()
+        };
+        let feed = MarketFeed::from_config(&config, 1).unwrap();
+        let mut halts = feed.subscribe_halts();
+        assert_eq!(feed.publish(vec![update("AAPL", 100.0)]).await.len(), 1);
+        assert!(feed.publish(vec![update("AAPL", 90.0)]).await.is_empty());
+
+        let change = halts.recv().await.unwrap();
+        assert!(!change.ended);
+        assert_eq!(change.halt.reason, HaltReason::LimitDown);
+        assert_eq!(feed.halt("AAPL"), Some(change.halt.clone()));
+        assert_eq!(feed.halts(), vec![change.halt]);
+        assert_eq!(feed.halt("MSFT"), None);
+        // Halted tickers stay at their last published price.
+        assert!(feed.publish(vec![update("AAPL", 100.0)]).await.is_empty());
+        assert_eq!(feed.latest("AAPL").await.unwrap().price.to_f64(), 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_listing_is_undone_when_the_open_cannot_publish() {
+        let config = MarketConfig {
+            halts: Some(HaltConfig {
+                band_pct: 5.0,
+                bands: HashMap::new(),
+                reference_secs: 300.0,
+                halt_secs: 300.0,
+                circuit_breakers: vec![CircuitBreakerLevel {
+                    decline_pct: 3.0,
+                    halt_secs: 900.0,
+                }],
+            }),
+            ..MarketConfig::default()
+        };
+        let feed = MarketFeed::from_config(&config, 1).unwrap();
+        feed.publish(vec![update("AAPL", 100.0)]).await;
+        feed.publish(vec![update("AAPL", 96.0)]).await;
+        assert!(feed.halt("AAPL").is_some_and(|halt| halt.ticker.is_none()));
+
+        let version = feed.registry_version();
+        let error = feed
+            .list(Instrument::builtin("ACME"), 10.0)
+            .await
+            .unwrap_err();
+        assert!(error.contains("halted"), "{}", error);
+        assert!(!feed.is_listed("ACME"));
+        assert!(feed.latest("ACME").await.is_none());
+        assert_ne!(feed.registry_version(), version);
+    }
+
+    #[tokio::test]
+    async fn test_corporate_actions_move_the_price_and_adjust_history() {
+        let feed = MarketFeed::new();
+        let before = feed.publish(vec![update("AAPL", 200.0)]).await;
+        let now = SystemTime::now();
+        let split = CorporateActionKind::Split {
+            new_shares: 4,
+            old_shares: 1,
+        };
+        let scheduled = feed.schedule_corporate_action("AAPL", split, now).unwrap();
+        assert_eq!(scheduled.status, ActionStatus::Pending);
+        assert!(feed.schedule_corporate_action("XYZ", split, now).is_err());
+
+        let applied = feed.apply_corporate_actions(now).await;
+        assert_eq!(applied.len(), 1);
+        let ActionStatus::Applied { adjustment, .. } = applied[0].status else {
+            panic!("the split should apply");
+        };
+        assert_eq!(adjustment.price_after.to_f64(), 50.0);
+        assert_eq!(feed.latest("AAPL").await.unwrap().price.to_f64(), 50.0);
+        assert!(feed.apply_corporate_actions(now).await.is_empty());
+        assert_eq!(feed.corporate_actions(Some("AAPL")), applied);
+
+        let tracker = feed.tracker();
+        let tracker = tracker.lock().await;
+        let first = tracker.points_since("AAPL", None)[0];
+        assert_eq!(first.price, before[0].price);
+        assert_eq!(tracker.adjusted("AAPL", first).price.to_f64(), 50.0);
+    }
+
+    #[tokio::test]
+    async fn test_corporate_actions_wait_out_a_halt() {
+        let config = MarketConfig {
+            halts: Some(HaltConfig {
+                band_pct: 5.0,
+                bands: HashMap::new(),
+                reference_secs: 300.0,
+                halt_secs: 0.2,
+                circuit_breakers: Vec::new(),
+            }),
+            ..MarketConfig::default()
+        };
+        let feed = MarketFeed::from_config(&config, 1).unwrap();
+        feed.publish(vec![update("AAPL", 200.0)]).await;
+        assert!(feed.publish(vec![update("AAPL", 180.0)]).await.is_empty());
+        assert!(feed.halt("AAPL").is_some());
+        let split = CorporateActionKind::Split {
+            new_shares: 4,
+            old_shares: 1,
+        };
+        let now = SystemTime::now();
+        feed.schedule_corporate_action("AAPL", split, now).unwrap();
+
+        // Nothing moves while the ticker is halted.
+        assert!(feed.apply_corporate_actions(now).await.is_empty());
+        assert_eq!(
+            feed.corporate_actions(Some("AAPL"))[0].status,
+            ActionStatus::Pending
+        );
+        assert_eq!(feed.latest("AAPL").await.unwrap().price.to_f64(), 200.0);
+        {
+            let tracker = feed.tracker();
+            let tracker = tracker.lock().await;
+            let first = tracker.points_since("AAPL", None)[0];
+            assert_eq!(tracker.adjusted("AAPL", first).price.to_f64(), 200.0);
+        }
+
+        sleep(Duration::from_millis(300)).await;
+        let applied = feed.apply_corporate_actions(SystemTime::now()).await;
+        assert_eq!(applied.len(), 1);
+        assert!(matches!(applied[0].status, ActionStatus::Applied { .. }));
+        assert_eq!(feed.latest("AAPL").await.unwrap().price.to_f64(), 50.0);
+        assert_eq!(feed.halt("AAPL"), None);
+    }
+
+    #[tokio::test]
+    async fn test_day_orders_expire_and_closed_markets_reject() {
+        let feed = MarketFeed::new();
+        let mut executions = feed.subscribe_executions();
+        feed.publish(vec![update("AAPL", 100.0)]).await;
+        let bid = || OrderRequest {
+            ticker: "AAPL".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: 10,
+            limit_price: Price::from_f64(90.0),
+            time_in_force: TimeInForce::Day,
+            client_order_id: String::new(),
+        };
+        let id = feed.submit_order(bid()).unwrap()[0].order.id;
+        assert_eq!(
+            executions.recv().await.unwrap().exec_type,
+            ExecutionType::New
+        );
+
+        feed.change_session(MarketSession::Closed, SystemTime::now())
+            .await
+            .unwrap();
+        let expired = executions.recv().await.unwrap();
+        assert_eq!(
+            (expired.order.id, expired.exec_type),
+            (id, ExecutionType::Expired)
+        );
+        let rejected = feed.submit_order(bid()).unwrap();
+        assert_eq!(rejected[0].order.status, OrderStatus::Rejected);
+        assert!(feed
+            .submit_order(OrderRequest {
+                ticker: "XYZ".to_string(),
+                ..bid()
+            })
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_see_the_same_market() {
+        let feed = MarketFeed::new();
+        let mut first = feed.subscribe();
+        let mut second = feed.subscribe();
+        let clock = spawn_market_clock(
+            MarketSimulator::default(),
+            feed.clone(),
+            Duration::from_millis(5),
+        );
+
+        for _ in 0..(TICKERS.len() * 3) {
+            assert_eq!(first.recv().await.unwrap(), second.recv().await.unwrap());
+        }
+        clock.abort();
+
+        let latest = feed.latest("AAPL").await.unwrap();
+        let tracker = feed.tracker();
+        let tracker = tracker.lock().await;
+        assert_eq!(
+            tracker.get_prices("AAPL").unwrap().last(),
+            Some(&latest.price)
+        );
+    }
+}
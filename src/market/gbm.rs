@@ -0,0 +1,57 @@
+use super::PriceModel;
+use rand::RngCore;
+
+/// Geometric Brownian Motion: dS = mu * S * dt + sigma * S * dW.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeometricBrownianMotion {
+    pub mu: f64,
+    pub sigma: f64,
+}
+
+impl GeometricBrownianMotion {
+    pub fn new(mu: f64, sigma: f64) -> Self {
+        GeometricBrownianMotion { mu, sigma }
+    }
+}
+
+impl PriceModel for GeometricBrownianMotion {
+    /// Uses the exact log-normal solution, so prices stay strictly positive
+    /// regardless of the step size.
+    fn step(&mut self, price: f64, dt: f64, z: f64, _rng: &mut dyn RngCore) -> f64 {
+        let drift = (self.mu - 0.5 * self.sigma * self.sigma) * dt;
+        let diffusion = self.sigma * dt.sqrt() * z;
+        price * (drift + diffusion).exp()
+    }
+
+    fn name(&self) -> &'static str {
+        "gbm"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use rand_distr::StandardNormal;
+
+    #[test]
+    fn test_zero_volatility_is_deterministic_growth() {
+        let mut model = GeometricBrownianMotion::new(0.05, 0.0);
+        let mut rng = StdRng::seed_from_u64(1);
+        let next = model.step(100.0, 1.0, 0.7, &mut rng);
+        assert!((next - 100.0 * 0.05f64.exp()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_prices_stay_positive() {
+        let mut model = GeometricBrownianMotion::new(-0.5, 2.0);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut price = 10.0;
+        for _ in 0..10_000 {
+            let z = rng.sample(StandardNormal);
+            price = model.step(price, 1.0 / 252.0, z, &mut rng);
+            assert!(price > 0.0);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::config::{CircuitBreakerLevel, HaltConfig};
+use crate::price::Price;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Why trading stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HaltReason {
+    /// The ticker rose through the top of its band.
+    LimitUp,
+    /// The ticker fell through the bottom of its band.
+    LimitDown,
+    /// The whole market fell far enough to trip a circuit breaker; levels
+    /// count from 1.
+    CircuitBreaker { level: u32 },
+}
+
+impl HaltReason {
+    pub fn label(self) -> String {
+        match self {
+            HaltReason::LimitUp => "limit up".to_string(),
+            HaltReason::LimitDown => "limit down".to_string(),
+            HaltReason::CircuitBreaker { level } => {
+                format!("market-wide circuit breaker level {}", level)
+            }
+        }
+    }
+}
+
+/// A pause in trading of one ticker, or of the whole market.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Halt {
+    /// `None` for a market-wide halt.
+    pub ticker: Option<String>,
+    pub reason: HaltReason,
+    /// The move from the reference price that caused the halt, in percent.
+    pub move_pct: f64,
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+impl Halt {
+    /// Whether the halt stops trading in `ticker`.
+    pub fn applies_to(&self, ticker: &str) -> bool {
+        self.ticker
+            .as_deref()
+            .map_or(true, |halted| halted == ticker)
+    }
+}
+
+/// A halt starting or ending.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HaltChange {
+    pub halt: Halt,
+    /// False when the halt started, true when trading resumed.
+    pub ended: bool,
+}
+
+/// What [`HaltMonitor::check`] decided about a price.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HaltCheck {
+    /// The price is within its band and may be published.
+    Accepted,
+    /// The ticker is already halted.
+    Halted,
+    /// The price breached its band and halted the ticker.
+    Triggered(Halt),
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    band_pct: f64,
+    bands: HashMap<String, f64>,
+    reference_period: Duration,
+    halt_period: Duration,
+    /// Sorted by increasing decline.
+    circuit_breakers: Vec<(f64, Duration)>,
+}
+
+/// Enforces limit-up/limit-down bands around each ticker's reference price
+/// and market-wide circuit breakers on the average decline since the market
+/// reference was taken.
+///
+/// A ticker's reference is its price at most `reference_secs` ago, and is
+/// retaken from the first price after a halt. The market reference is every
+/// ticker's first price since the last
+/// [`reset_market_reference`](Self::reset_market_reference). A monitor
+/// without rules never halts.
+#[derive(Debug, Clone, Default)]
+pub struct HaltMonitor {
+    rules: Option<Rules>,
+    references: HashMap<String, (Price, SystemTime)>,
+    market_references: HashMap<String, Price>,
+    last: HashMap<String, Price>,
+    /// Circuit breaker levels tripped since the market reference was taken.
+    tripped: usize,
+    halts: HashMap<Option<String>, Halt>,
+}
+
+impl HaltMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(config: &HaltConfig) -> Result<Self, String> {
+        let positive = |value: f64, name: &str| {
+            if value.is_finite() && value > 0.0 {
+                Ok(value)
+            } else {
+                Err(format!(
+                    "market.halts.{} must be positive, got {}",
+                    name, value
+                ))
+            }
+        };
+        positive(config.band_pct, "band_pct")?;
+        for (ticker, &band) in &config.bands {
+            positive(band, &format!("bands.{}", ticker))?;
+        }
+        let reference_period =
+            Duration::from_secs_f64(positive(config.reference_secs, "reference_secs")?);
+        let halt_period = Duration::from_secs_f64(positive(config.halt_secs, "halt_secs")?);
+        let mut circuit_breakers = config
+            .circuit_breakers
+            .iter()
+            .map(|level: &CircuitBreakerLevel| {
+                let decline = positive(level.decline_pct, "circuit_breakers.decline_pct")?;
+                let halt = positive(level.halt_secs, "circuit_breakers.halt_secs")?;
+                Ok((decline, Duration::from_secs_f64(halt)))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        circuit_breakers.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Ok(HaltMonitor {
+            rules: Some(Rules {
+                band_pct: config.band_pct,
+                bands: config.bands.clone(),
+                reference_period,
+                halt_period,
+                circuit_breakers,
+            }),
+            ..Self::default()
+        })
+    }
+
+    /// The halt stopping trading in `ticker` at `now`, the market-wide one
+    /// first.
+    pub fn halt(&self, ticker: &str, now: SystemTime) -> Option<&Halt> {
+        [None, Some(ticker.to_string())]
+            .iter()
+            .filter_map(|key| self.halts.get(key))
+            .find(|halt| halt.end > now)
+    }
+
+    /// Every halt in force at `now`, market-wide ones first.
+    pub fn halts(&self, now: SystemTime) -> Vec<Halt> {
+        let mut halts: Vec<Halt> = self
+            .halts
+            .values()
+            .filter(|halt| halt.end > now)
+            .cloned()
+            .collect();
+        halts.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+        halts
+    }
+
+    /// Ends every halt that is over by `now` and returns them.
+    pub fn lift_expired(&mut self, now: SystemTime) -> Vec<Halt> {
+        let expired: Vec<Option<String>> = self
+            .halts
+            .iter()
+            .filter(|(_, halt)| halt.end <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut lifted = Vec::with_capacity(expired.len());
+        for key in expired {
+            // Trading reopens around the first price after the halt.
+            match &key {
+                Some(ticker) => {
+                    self.references.remove(ticker);
+                }
+                None => self.references.clear(),
+            }
+            lifted.extend(self.halts.remove(&key));
+        }
+        lifted.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+        lifted
+    }
+
+    /// Checks `ticker`'s new `price` against its band, halting the ticker on
+    /// a breach. Accepted prices count towards the circuit breakers.
+    pub fn check(&mut self, ticker: &str, price: Price, now: SystemTime) -> HaltCheck {
+        let Some(rules) = &self.rules else {
+            return HaltCheck::Accepted;
+        };
+        if self.halt(ticker, now).is_some() {
+            return HaltCheck::Halted;
+        }
+        let reference = match self.references.get(ticker) {
+            Some(&(reference, taken))
+                if now.duration_since(taken).unwrap_or_default() < rules.reference_period =>
+            {
+                reference
+            }
+            _ => {
+                self.references.insert(ticker.to_string(), (price, now));
+                price
+            }
+        };
+        // Mean-reverting series may sit at or below zero, where a percentage
+        // band means nothing.
+        if reference > Price::ZERO {
+            let move_pct = (price.to_f64() / reference.to_f64() - 1.0) * 100.0;
+            let band = rules.bands.get(ticker).copied().unwrap_or(rules.band_pct);
+            if move_pct.abs() > band {
+                let halt = Halt {
+                    ticker: Some(ticker.to_string()),
+                    reason: if move_pct > 0.0 {
+                        HaltReason::LimitUp
+                    } else {
+                        HaltReason::LimitDown
+                    },
+                    move_pct,
+                    start: now,
+                    end: now + rules.halt_period,
+                };
+                self.halts.insert(halt.ticker.clone(), halt.clone());
+                return HaltCheck::Triggered(halt);
+            }
+        }
+        self.market_references
+            .entry(ticker.to_string())
+            .or_insert(price);
+        self.last.insert(ticker.to_string(), price);
+        HaltCheck::Accepted
+    }
+
+    /// Halts the whole market when the average move since the market
+    /// reference has fallen through a circuit breaker level not yet tripped.
+    /// Only the highest level reached applies.
+    pub fn check_market(&mut self, now: SystemTime) -> Option<Halt> {
+        let rules = self.rules.as_ref()?;
+        let moves: Vec<f64> = self
+            .last
+            .iter()
+            .filter_map(|(ticker, price)| {
+                let reference = *self.market_references.get(ticker)?;
+                (reference > Price::ZERO)
+                    .then(|| (price.to_f64() / reference.to_f64() - 1.0) * 100.0)
+            })
+            .collect();
+        if moves.is_empty() {
+            return None;
+        }
+        let move_pct = moves.iter().sum::<f64>() / moves.len() as f64;
+        let reached = rules
+            .circuit_breakers
+            .iter()
+            .take_while(|(decline, _)| -move_pct >= *decline)
+            .count();
+        if reached <= self.tripped {
+            return None;
+        }
+        self.tripped = reached;
+        let halt = Halt {
+            ticker: None,
+            reason: HaltReason::CircuitBreaker {
+                level: reached as u32,
+            },
+            move_pct,
+            start: now,
+            end: now + rules.circuit_breakers[reached - 1].1,
+        };
+        self.halts.insert(None, halt.clone());
+        Some(halt)
+    }
+
+    /// Scales `ticker`'s reference prices by `factor`, so a corporate action
+    /// moving its price is not mistaken for a market move.
+    pub fn rebase(&mut self, ticker: &str, factor: f64) {
+        if let Some((reference, _)) = self.references.get_mut(ticker) {
+            *reference = reference.scaled(factor);
+        }
+        if let Some(reference) = self.market_references.get_mut(ticker) {
+            *reference = reference.scaled(factor);
+        }
+        if let Some(last) = self.last.get_mut(ticker) {
+            *last = last.scaled(factor);
+        }
+    }
+
+    /// Starts measuring market-wide declines afresh, as at a session open.
+    pub fn reset_market_reference(&mut self) {
+        self.market_references.clear();
+        self.last.clear();
+        self.tripped = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    fn monitor() -> HaltMonitor {
+        HaltMonitor::from_config(&HaltConfig {
+            band_pct: 5.0,
+            bands: HashMap::from([("TSLA".to_string(), 10.0)]),
+            reference_secs: 300.0,
+            halt_secs: 60.0,
+            circuit_breakers: vec![
+                CircuitBreakerLevel {
+                    decline_pct: 13.0,
+                    halt_secs: 900.0,
+                },
+                CircuitBreakerLevel {
+                    decline_pct: 7.0,
+                    halt_secs: 600.0,
+                },
+            ],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_band_breach_halts_the_ticker() {
+        let mut halts = monitor();
+        let start = SystemTime::UNIX_EPOCH;
+        let at = |secs| start + Duration::from_secs(secs);
+        assert_eq!(
+            halts.check("AAPL", price(100.0), at(0)),
+            HaltCheck::Accepted
+        );
+        assert_eq!(
+            halts.check("AAPL", price(104.9), at(1)),
+            HaltCheck::Accepted
+        );
+        // TSLA's own band is wider.
+        assert_eq!(
+            halts.check("TSLA", price(100.0), at(1)),
+            HaltCheck::Accepted
+        );
+        assert_eq!(halts.check("TSLA", price(92.0), at(2)), HaltCheck::Accepted);
+
+        let HaltCheck::Triggered(halt) = halts.check("AAPL", price(94.0), at(2)) else {
+            panic!("a 6% drop should halt AAPL");
+        };
+        assert_eq!(halt.reason, HaltReason::LimitDown);
+        assert_eq!(halt.end, at(62));
+        assert_eq!(halts.check("AAPL", price(100.0), at(3)), HaltCheck::Halted);
+        assert_eq!(halts.halt("AAPL", at(3)), Some(&halt));
+        assert_eq!(halts.halt("MSFT", at(3)), None);
+
+        assert!(halts.lift_expired(at(61)).is_empty());
+        assert_eq!(halts.lift_expired(at(62)), vec![halt]);
+        // Trading reopens around the first price after the halt.
+        assert_eq!(
+            halts.check("AAPL", price(90.0), at(63)),
+            HaltCheck::Accepted
+        );
+        assert_eq!(
+            halts.check("AAPL", price(93.0), at(64)),
+            HaltCheck::Accepted
+        );
+        // The reference rolls forward after `reference_secs`.
+        assert_eq!(
+            halts.check("AAPL", price(99.0), at(363)),
+            HaltCheck::Accepted
+        );
+        assert_eq!(
+            halts.check("AAPL", price(103.0), at(364)),
+            HaltCheck::Accepted
+        );
+
+        assert_eq!(
+            HaltMonitor::new().check("AAPL", price(1.0), at(0)),
+            HaltCheck::Accepted
+        );
+    }
+
+    #[test]
+    fn test_market_decline_trips_circuit_breakers_once() {
+        let mut halts = monitor();
+        let start = SystemTime::UNIX_EPOCH;
+        let at = |secs| start + Duration::from_secs(secs);
+        // Two tickers, each falling 4% per reference period, average out at
+        // the decline of either.
+        let mut level = 100.0;
+        let mut tripped = Vec::new();
+        for step in 0..5 {
+            let now = at(step * 300);
+            for ticker in ["AAPL", "MSFT"] {
+                assert_eq!(halts.check(ticker, price(level), now), HaltCheck::Accepted);
+            }
+            if let Some(halt) = halts.check_market(now) {
+                tripped.push(halt.reason);
+                halts.lift_expired(halt.end);
+            }
+            level *= 0.96;
+        }
+        assert_eq!(
+            tripped,
+            vec![
+                HaltReason::CircuitBreaker { level: 1 },
+                HaltReason::CircuitBreaker { level: 2 }
+            ]
+        );
+
+        halts.reset_market_reference();
+        assert_eq!(halts.check_market(at(2000)), None);
+    }
+}
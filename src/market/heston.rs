@@ -0,0 +1,74 @@
+use super::PriceModel;
+use rand::{Rng, RngCore};
+use rand_distr::StandardNormal;
+
+/// Heston stochastic volatility:
+///
+/// dS = mu * S * dt + sqrt(v) * S * dW1
+/// dv = kappa * (theta - v) * dt + xi * sqrt(v) * dW2, with corr(dW1, dW2) = rho.
+///
+/// The variance is advanced with a full-truncation Euler scheme, so it may dip
+/// below zero internally but only its positive part ever drives the price.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heston {
+    pub mu: f64,
+    pub kappa: f64,
+    pub theta: f64,
+    pub xi: f64,
+    pub rho: f64,
+    variance: f64,
+}
+
+impl Heston {
+    pub fn new(mu: f64, kappa: f64, theta: f64, xi: f64, rho: f64, initial_variance: f64) -> Self {
+        Heston {
+            mu,
+            kappa,
+            theta,
+            xi,
+            rho,
+            variance: initial_variance,
+        }
+    }
+
+    /// Current instantaneous variance, floored at zero.
+    pub fn variance(&self) -> f64 {
+        self.variance.max(0.0)
+    }
+}
+
+impl PriceModel for Heston {
+    fn step(&mut self, price: f64, dt: f64, z: f64, rng: &mut dyn RngCore) -> f64 {
+        let v = self.variance();
+        let independent: f64 = rng.sample(StandardNormal);
+        let z_variance = self.rho * z + (1.0 - self.rho * self.rho).sqrt() * independent;
+
+        let next = price * ((self.mu - 0.5 * v) * dt + (v * dt).sqrt() * z).exp();
+        self.variance +=
+            self.kappa * (self.theta - v) * dt + self.xi * (v * dt).sqrt() * z_variance;
+        next
+    }
+
+    fn name(&self) -> &'static str {
+        "heston"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_variance_reverts_to_long_run_level() {
+        let mut model = Heston::new(0.0, 5.0, 0.04, 0.0, -0.7, 0.25);
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut price = 100.0;
+        for _ in 0..1_000 {
+            price = model.step(price, 0.01, 0.0, &mut rng);
+            assert!(price > 0.0);
+        }
+        assert!((model.variance() - 0.04).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,92 @@
+use super::PriceModel;
+use rand::{Rng, RngCore};
+use rand_distr::{Poisson, StandardNormal};
+
+/// Merton jump-diffusion: GBM with Poisson-arriving jumps whose log sizes are
+/// normally distributed with mean `jump_mean` and deviation `jump_std`.
+///
+/// The drift is compensated for the expected jump so `mu` remains the
+/// expected rate of return.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MertonJumpDiffusion {
+    pub mu: f64,
+    pub sigma: f64,
+    pub jump_intensity: f64,
+    pub jump_mean: f64,
+    pub jump_std: f64,
+}
+
+impl MertonJumpDiffusion {
+    pub fn new(mu: f64, sigma: f64, jump_intensity: f64, jump_mean: f64, jump_std: f64) -> Self {
+        MertonJumpDiffusion {
+            mu,
+            sigma,
+            jump_intensity,
+            jump_mean,
+            jump_std,
+        }
+    }
+
+    fn sample_jumps(&self, dt: f64, rng: &mut dyn RngCore) -> f64 {
+        let rate = self.jump_intensity * dt;
+        if rate <= 0.0 {
+            return 0.0;
+        }
+        let count = match Poisson::new(rate) {
+            Ok(poisson) => rng.sample::<f64, _>(poisson) as u64,
+            Err(_) => 0,
+        };
+        (0..count)
+            .map(|_| self.jump_mean + self.jump_std * rng.sample::<f64, _>(StandardNormal))
+            .sum()
+    }
+}
+
+impl PriceModel for MertonJumpDiffusion {
+    fn step(&mut self, price: f64, dt: f64, z: f64, rng: &mut dyn RngCore) -> f64 {
+        let expected_jump = (self.jump_mean + 0.5 * self.jump_std * self.jump_std).exp() - 1.0;
+        let drift =
+            (self.mu - self.jump_intensity * expected_jump - 0.5 * self.sigma * self.sigma) * dt;
+        let diffusion = self.sigma * dt.sqrt() * z;
+        price * (drift + diffusion + self.sample_jumps(dt, rng)).exp()
+    }
+
+    fn name(&self) -> &'static str {
+        "jump_diffusion"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_without_jumps_matches_gbm() {
+        let mut model = MertonJumpDiffusion::new(0.05, 0.2, 0.0, -0.1, 0.05);
+        let mut rng = StdRng::seed_from_u64(3);
+        let next = model.step(100.0, 0.5, 0.3, &mut rng);
+        let expected = 100.0 * ((0.05 - 0.02) * 0.5 + 0.2 * 0.5f64.sqrt() * 0.3).exp();
+        assert!((next - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_jumps_move_price_beyond_diffusion() {
+        // With no diffusion, any deviation from the compensated drift comes
+        // from the -10% jumps.
+        let mut model = MertonJumpDiffusion::new(0.0, 0.0, 50.0, -0.1, 0.0);
+        let compensated_drift = -50.0 * ((-0.1f64).exp() - 1.0);
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut price = 100.0;
+        for _ in 0..20 {
+            let next = model.step(price, 1.0, 0.0, &mut rng);
+            assert!(next > 0.0);
+            let jumps = (next / price).ln() - compensated_drift;
+            // Every realized move is a whole number of -10% log jumps.
+            assert!(jumps <= 1e-9);
+            assert!(((jumps / -0.1).round() * -0.1 - jumps).abs() < 1e-6);
+            price = next;
+        }
+    }
+}
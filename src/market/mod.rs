@@ -0,0 +1,464 @@
+use crate::config::{MarketConfig, ModelConfig, TickerConfig};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+use std::collections::HashMap;
+
+mod calendar;
+mod candles;
+mod corporate_actions;
+mod correlation;
+mod depth;
+mod feed;
+mod gbm;
+mod halts;
+mod heston;
+mod jump_diffusion;
+mod model;
+mod orders;
+mod ornstein_uhlenbeck;
+mod quote;
+mod registry;
+mod replay;
+mod trades;
+
+pub use calendar::{MarketSession, SessionChange, TradingCalendar};
+pub use candles::{Candle, CandleAggregator, CandleInterval, MAX_CANDLES_PER_SERIES};
+pub use corporate_actions::{
+    ActionStatus, Adjustment, CorporateAction, CorporateActionKind, CorporateActionSchedule,
+};
+pub use correlation::{build_correlation_matrix, CholeskyFactor};
+pub use depth::{DepthAction, DepthBook, DepthLevel, DepthSnapshot, DepthUpdate, LevelUpdate};
+#[cfg(test)]
+pub(crate) use feed::update;
+pub use feed::{
+    spawn_candle_clock, spawn_market_clock, spawn_session_clock, MarketFeed, PriceUpdate, Tick,
+};
+pub use gbm::GeometricBrownianMotion;
+pub use halts::{Halt, HaltChange, HaltCheck, HaltMonitor, HaltReason};
+pub use heston::Heston;
+pub use jump_diffusion::MertonJumpDiffusion;
+pub use model::{build_model, PriceModel};
+pub use orders::{
+    ExecutionReport, ExecutionType, Order, OrderRequest, OrderStatus, OrderType, PaperExchange,
+    Side, TimeInForce,
+};
+pub use ornstein_uhlenbeck::OrnsteinUhlenbeck;
+pub use quote::{Quote, QuoteGenerator};
+pub use registry::{Instrument, TickerRegistry};
+pub use replay::{load_ticks, spawn_replay, start_replay, RecordedTick};
+pub use trades::{Trade, TradeGenerator};
+
+/// Seconds in a trading year (252 sessions of 6.5 hours). Model parameters are
+/// annualized, so the configured time step is converted with this constant.
+pub const TRADING_SECONDS_PER_YEAR: f64 = 252.0 * 6.5 * 3600.0;
+
+/// Built-in starting parameters used for tickers without a `[market.tickers]` entry.
+pub fn default_ticker_config(ticker: &str) -> TickerConfig {
+    let (initial_price, mu, sigma) = match ticker {
+        "AAPL" => (190.0, 0.08, 0.25),
+        "MSFT" => (410.0, 0.08, 0.22),
+        "GOOG" => (140.0, 0.07, 0.28),
+        "AMZN" => (175.0, 0.09, 0.32),
+        "META" => (480.0, 0.10, 0.38),
+        "NFLX" => (600.0, 0.08, 0.40),
+        "TSLA" => (180.0, 0.05, 0.60),
+        "NVDA" => (880.0, 0.15, 0.50),
+        "AMD" => (170.0, 0.10, 0.48),
+        "INTC" => (40.0, 0.02, 0.35),
+        _ => (100.0, 0.05, 0.30),
+    };
+    TickerConfig {
+        initial_price,
+        model: ModelConfig::Gbm { mu, sigma },
+    }
+}
+
+/// Parameters of the running simulation reported by `GetServerInfo`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationInfo {
+    /// `"simulation"` or `"replay"`.
+    pub data_source: &'static str,
+    pub seed: u64,
+    pub seed_configured: bool,
+    pub tick_interval_ms: u64,
+    pub time_step_secs: f64,
+}
+
+impl SimulationInfo {
+    pub fn new(config: &MarketConfig, simulator: &MarketSimulator) -> Self {
+        SimulationInfo {
+            data_source: if config.replay.is_some() {
+                "replay"
+            } else {
+                "simulation"
+            },
+            seed: simulator.seed(),
+            seed_configured: config.seed.is_some(),
+            tick_interval_ms: config.tick_interval_ms,
+            time_step_secs: config.time_step_secs,
+        }
+    }
+}
+
+struct TickerPath {
+    ticker: String,
+    model: Box<dyn PriceModel>,
+    last_price: f64,
+}
+
+/// Evolves one price path per ticker. Every [`step`](Self::step) advances the
+/// whole universe by one time step, drawing the tickers' Brownian shocks
+/// jointly so they follow the configured correlation matrix.
+///
+/// All randomness comes from a single ChaCha8 generator, whose output is
+/// portable across platforms and crate versions, so a given seed reproduces
+/// the same market bit for bit.
+pub struct MarketSimulator {
+    dt: f64,
+    seed: u64,
+    rng: ChaCha8Rng,
+    paths: Vec<TickerPath>,
+    index: HashMap<String, usize>,
+    cholesky: CholeskyFactor,
+}
+
+impl MarketSimulator {
+    pub fn from_config(config: &MarketConfig) -> Result<Self, String> {
+        if !(config.time_step_secs.is_finite() && config.time_step_secs > 0.0) {
+            return Err(format!(
+                "market.time_step_secs must be positive, got {}",
+                config.time_step_secs
+            ));
+        }
+        let universe = TickerRegistry::from_config(config)?.symbols();
+        if let Some(unknown) = config
+            .tickers
+            .keys()
+            .find(|ticker| !universe.contains(ticker))
+        {
+            return Err(format!("Unknown ticker in market config: {}", unknown));
+        }
+
+        let symbols: Vec<&str> = universe.iter().map(String::as_str).collect();
+        let cholesky = match &config.correlation {
+            Some(correlation) => {
+                let matrix = build_correlation_matrix(&symbols, correlation)?;
+                CholeskyFactor::new(&matrix)?
+            }
+            None => CholeskyFactor::identity(symbols.len()),
+        };
+
+        let mut paths = Vec::with_capacity(symbols.len());
+        for &ticker in &symbols {
+            let params = config
+                .tickers
+                .get(ticker)
+                .cloned()
+                .unwrap_or_else(|| default_ticker_config(ticker));
+            // Mean-reverting series may legitimately start at or below zero.
+            let allows_non_positive = matches!(params.model, ModelConfig::OrnsteinUhlenbeck { .. });
+            if !params.initial_price.is_finite()
+                || (params.initial_price <= 0.0 && !allows_non_positive)
+            {
+                return Err(format!(
+                    "Invalid initial_price for {}: {}",
+                    ticker, params.initial_price
+                ));
+            }
+            let model = build_model(&params.model).map_err(|e| {
+                format!(
+                    "Invalid {} model for {}: {}",
+                    params.model_name(),
+                    ticker,
+                    e
+                )
+            })?;
+            paths.push(TickerPath {
+                ticker: ticker.to_string(),
+                model,
+                last_price: params.initial_price,
+            });
+        }
+        let index = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (path.ticker.clone(), i))
+            .collect();
+
+        let seed = config.seed.unwrap_or_else(rand::random);
+        Ok(MarketSimulator {
+            dt: config.time_step_secs / TRADING_SECONDS_PER_YEAR,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            paths,
+            index,
+            cholesky,
+        })
+    }
+
+    /// Starts simulating `ticker` from `price`, for an instrument listed
+    /// while the market runs. A new ticker follows its built-in model,
+    /// independent of the rest of the universe; a ticker that is already
+    /// simulated, e.g. one being relisted, continues from `price`.
+    pub fn list(&mut self, ticker: &str, price: f64) {
+        if let Some(&i) = self.index.get(ticker) {
+            self.paths[i].last_price = price;
+            return;
+        }
+        let params = default_ticker_config(ticker);
+        let model = build_model(&params.model).expect("built-in models are valid");
+        self.index.insert(ticker.to_string(), self.paths.len());
+        self.paths.push(TickerPath {
+            ticker: ticker.to_string(),
+            model,
+            last_price: price,
+        });
+        self.cholesky.push_independent();
+    }
+
+    /// Advances every ticker by one time step and returns the new prices.
+    pub fn step(&mut self) -> Vec<(String, f64)> {
+        let independent: Vec<f64> = (0..self.paths.len())
+            .map(|_| self.rng.sample(StandardNormal))
+            .collect();
+        let shocks = self.cholesky.correlate(&independent);
+        for (path, z) in self.paths.iter_mut().zip(shocks) {
+            path.last_price = path.model.step(path.last_price, self.dt, z, &mut self.rng);
+        }
+        self.prices()
+    }
+
+    /// Advances the market by one time step and returns the new price of `ticker`.
+    pub fn next_price(&mut self, ticker: &str) -> Option<f64> {
+        let i = *self.index.get(ticker)?;
+        self.step();
+        Some(self.paths[i].last_price)
+    }
+
+    pub fn last_price(&self, ticker: &str) -> Option<f64> {
+        self.path(ticker).map(|path| path.last_price)
+    }
+
+    /// Latest price of every ticker, in universe order.
+    pub fn prices(&self) -> Vec<(String, f64)> {
+        self.paths
+            .iter()
+            .map(|path| (path.ticker.clone(), path.last_price))
+            .collect()
+    }
+
+    /// Seed the simulation was started with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Name of the model driving `ticker`, as used in `config.toml`.
+    pub fn model_name(&self, ticker: &str) -> Option<&'static str> {
+        self.path(ticker).map(|path| path.model.name())
+    }
+
+    fn path(&self, ticker: &str) -> Option<&TickerPath> {
+        self.index.get(ticker).map(|&i| &self.paths[i])
+    }
+}
+
+impl Default for MarketSimulator {
+    fn default() -> Self {
+        Self::from_config(&MarketConfig::default()).expect("default market config is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CorrelationConfig;
+    use crate::utils::TICKERS;
+
+    fn sample_correlation(a: &[f64], b: &[f64]) -> f64 {
+        let n = a.len() as f64;
+        let mean_a = a.iter().sum::<f64>() / n;
+        let mean_b = b.iter().sum::<f64>() / n;
+        let cov: f64 = a
+            .iter()
+            .zip(b)
+            .map(|(x, y)| (x - mean_a) * (y - mean_b))
+            .sum();
+        let var_a: f64 = a.iter().map(|x| (x - mean_a).powi(2)).sum();
+        let var_b: f64 = b.iter().map(|y| (y - mean_b).powi(2)).sum();
+        cov / (var_a * var_b).sqrt()
+    }
+
+    #[test]
+    fn test_simulator_covers_all_tickers() {
+        let simulator = MarketSimulator::default();
+        for &ticker in TICKERS {
+            assert_eq!(
+                simulator.last_price(ticker),
+                Some(default_ticker_config(ticker).initial_price)
+            );
+        }
+        assert_eq!(simulator.last_price("XYZ"), None);
+    }
+
+    #[test]
+    fn test_listing_adds_or_resets_a_path() {
+        let mut simulator = MarketSimulator::default();
+        simulator.list("SPY", 500.0);
+        assert_eq!(simulator.last_price("SPY"), Some(500.0));
+        let prices = simulator.step();
+        assert_eq!(prices.len(), TICKERS.len() + 1);
+        assert_eq!(prices.last().unwrap().0, "SPY");
+
+        simulator.list("AAPL", 50.0);
+        assert_eq!(simulator.last_price("AAPL"), Some(50.0));
+    }
+
+    #[test]
+    fn test_next_price_evolves_from_last_price() {
+        let mut config = MarketConfig::default();
+        config.tickers.insert(
+            "AAPL".to_string(),
+            TickerConfig {
+                initial_price: 100.0,
+                model: ModelConfig::Gbm {
+                    mu: 0.0,
+                    sigma: 0.2,
+                },
+            },
+        );
+        let mut simulator = MarketSimulator::from_config(&config).unwrap();
+        for _ in 0..100 {
+            let previous = simulator.last_price("AAPL").unwrap();
+            let next = simulator.next_price("AAPL").unwrap();
+            // One second of 20% annual vol should never move the price by 5%.
+            assert!((next / previous - 1.0).abs() < 0.05);
+            assert_eq!(simulator.last_price("AAPL"), Some(next));
+        }
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let mut config = MarketConfig::default();
+        config.tickers.insert(
+            "XYZ".to_string(),
+            TickerConfig {
+                initial_price: 100.0,
+                model: ModelConfig::Gbm {
+                    mu: 0.0,
+                    sigma: 0.2,
+                },
+            },
+        );
+        assert!(MarketSimulator::from_config(&config).is_err());
+
+        let mut config = MarketConfig::default();
+        config.tickers.insert(
+            "AAPL".to_string(),
+            TickerConfig {
+                initial_price: -1.0,
+                model: ModelConfig::Gbm {
+                    mu: 0.0,
+                    sigma: 0.2,
+                },
+            },
+        );
+        assert!(MarketSimulator::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_model_selected_per_ticker() {
+        let mut config = MarketConfig::default();
+        config.tickers.insert(
+            "INTC".to_string(),
+            TickerConfig {
+                initial_price: 0.0,
+                model: ModelConfig::OrnsteinUhlenbeck {
+                    theta: 3.0,
+                    mean: 0.0,
+                    sigma: 0.5,
+                },
+            },
+        );
+        let mut simulator = MarketSimulator::from_config(&config).unwrap();
+        assert_eq!(simulator.model_name("INTC"), Some("ornstein_uhlenbeck"));
+        assert_eq!(simulator.model_name("AAPL"), Some("gbm"));
+        assert!(simulator.next_price("INTC").unwrap().is_finite());
+    }
+
+    #[test]
+    fn test_correlated_tickers_move_together() {
+        let config = MarketConfig {
+            correlation: Some(CorrelationConfig {
+                tickers: vec!["NVDA".to_string(), "AMD".to_string()],
+                matrix: vec![vec![1.0, 0.9], vec![0.9, 1.0]],
+            }),
+            ..MarketConfig::default()
+        };
+        let mut simulator = MarketSimulator::from_config(&config).unwrap();
+
+        let mut returns: HashMap<&str, Vec<f64>> = HashMap::new();
+        for _ in 0..5_000 {
+            let before = simulator.prices();
+            let after = simulator.step();
+            for ((ticker, old), (_, new)) in before.iter().zip(&after) {
+                if let Some(&t) = ["NVDA", "AMD", "INTC"].iter().find(|&&t| t == ticker) {
+                    returns.entry(t).or_default().push((new / old).ln());
+                }
+            }
+        }
+        assert!(sample_correlation(&returns["NVDA"], &returns["AMD"]) > 0.85);
+        assert!(sample_correlation(&returns["NVDA"], &returns["INTC"]).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_non_psd_correlation_is_rejected() {
+        let config = MarketConfig {
+            correlation: Some(CorrelationConfig {
+                tickers: vec!["AAPL".to_string(), "MSFT".to_string(), "GOOG".to_string()],
+                matrix: vec![
+                    vec![1.0, 0.9, -0.9],
+                    vec![0.9, 1.0, 0.9],
+                    vec![-0.9, 0.9, 1.0],
+                ],
+            }),
+            ..MarketConfig::default()
+        };
+        assert!(MarketSimulator::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_same_seed_reproduces_market() {
+        let mut config = MarketConfig {
+            seed: Some(1234),
+            correlation: Some(CorrelationConfig {
+                tickers: vec!["NVDA".to_string(), "AMD".to_string()],
+                matrix: vec![vec![1.0, 0.7], vec![0.7, 1.0]],
+            }),
+            ..MarketConfig::default()
+        };
+        config.tickers.insert(
+            "TSLA".to_string(),
+            TickerConfig {
+                initial_price: 180.0,
+                model: ModelConfig::JumpDiffusion {
+                    mu: 0.05,
+                    sigma: 0.5,
+                    jump_intensity: 1_000_000.0,
+                    jump_mean: 0.0,
+                    jump_std: 0.01,
+                },
+            },
+        );
+        let mut first = MarketSimulator::from_config(&config).unwrap();
+        let mut second = MarketSimulator::from_config(&config).unwrap();
+        assert_eq!(first.seed(), 1234);
+        for _ in 0..100 {
+            assert_eq!(first.step(), second.step());
+        }
+
+        config.seed = Some(4321);
+        let mut other = MarketSimulator::from_config(&config).unwrap();
+        assert_ne!(first.step(), other.step());
+    }
+}
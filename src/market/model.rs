@@ -0,0 +1,124 @@
+use super::{GeometricBrownianMotion, Heston, MertonJumpDiffusion, OrnsteinUhlenbeck};
+use crate::config::ModelConfig;
+use rand::RngCore;
+
+/// A stochastic process that advances a price by one time step.
+///
+/// The driving Brownian shock `z` is supplied by the caller so the simulator
+/// controls how shocks are drawn; any additional randomness a model needs
+/// (jumps, variance shocks) is taken from `rng`.
+pub trait PriceModel: Send {
+    /// Returns the price after `dt` years, given a standard normal shock `z`.
+    fn step(&mut self, price: f64, dt: f64, z: f64, rng: &mut dyn RngCore) -> f64;
+
+    /// Short identifier matching the `model` key in `config.toml`.
+    fn name(&self) -> &'static str;
+}
+
+/// Builds the model described by `config`, rejecting out-of-range parameters.
+pub fn build_model(config: &ModelConfig) -> Result<Box<dyn PriceModel>, String> {
+    let non_negative = |name: &str, value: f64| {
+        if value.is_finite() && value >= 0.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} must be finite and non-negative, got {}",
+                name, value
+            ))
+        }
+    };
+    let finite = |name: &str, value: f64| {
+        if value.is_finite() {
+            Ok(())
+        } else {
+            Err(format!("{} must be finite, got {}", name, value))
+        }
+    };
+
+    let model: Box<dyn PriceModel> = match *config {
+        ModelConfig::Gbm { mu, sigma } => {
+            finite("mu", mu)?;
+            non_negative("sigma", sigma)?;
+            Box::new(GeometricBrownianMotion::new(mu, sigma))
+        }
+        ModelConfig::JumpDiffusion {
+            mu,
+            sigma,
+            jump_intensity,
+            jump_mean,
+            jump_std,
+        } => {
+            finite("mu", mu)?;
+            non_negative("sigma", sigma)?;
+            non_negative("jump_intensity", jump_intensity)?;
+            finite("jump_mean", jump_mean)?;
+            non_negative("jump_std", jump_std)?;
+            Box::new(MertonJumpDiffusion::new(
+                mu,
+                sigma,
+                jump_intensity,
+                jump_mean,
+                jump_std,
+            ))
+        }
+        ModelConfig::OrnsteinUhlenbeck { theta, mean, sigma } => {
+            non_negative("theta", theta)?;
+            finite("mean", mean)?;
+            non_negative("sigma", sigma)?;
+            Box::new(OrnsteinUhlenbeck::new(theta, mean, sigma))
+        }
+        ModelConfig::Heston {
+            mu,
+            kappa,
+            theta,
+            xi,
+            rho,
+            initial_variance,
+        } => {
+            finite("mu", mu)?;
+            non_negative("kappa", kappa)?;
+            non_negative("theta", theta)?;
+            non_negative("xi", xi)?;
+            non_negative("initial_variance", initial_variance)?;
+            if !(-1.0..=1.0).contains(&rho) {
+                return Err(format!("rho must be within [-1, 1], got {}", rho));
+            }
+            Box::new(Heston::new(mu, kappa, theta, xi, rho, initial_variance))
+        }
+    };
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_model_selects_implementation() {
+        let model = build_model(&ModelConfig::OrnsteinUhlenbeck {
+            theta: 2.0,
+            mean: 1.5,
+            sigma: 0.1,
+        })
+        .unwrap();
+        assert_eq!(model.name(), "ornstein_uhlenbeck");
+    }
+
+    #[test]
+    fn test_build_model_rejects_invalid_parameters() {
+        assert!(build_model(&ModelConfig::Gbm {
+            mu: 0.05,
+            sigma: -0.1,
+        })
+        .is_err());
+        assert!(build_model(&ModelConfig::Heston {
+            mu: 0.05,
+            kappa: 2.0,
+            theta: 0.04,
+            xi: 0.3,
+            rho: -1.5,
+            initial_variance: 0.04,
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,977 @@
+use super::{DepthBook, DepthSnapshot, DepthUpdate, Quote};
+use crate::config::OrderBookConfig;
+use crate::price::Price;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::SystemTime;
+
+/// Filled, cancelled, rejected and expired orders kept for lookup.
+const RETAINED_FINISHED_ORDERS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn label(self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    /// Takes whatever liquidity is there; the rest is cancelled.
+    Market,
+    /// Trades at the limit price or better; the rest rests in the book
+    /// unless its time in force says otherwise.
+    Limit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeInForce {
+    /// Rests until filled, cancelled or the market closes.
+    Day,
+    /// Rests until filled or cancelled.
+    GoodTillCancel,
+    /// Fills what it can straight away and cancels the rest.
+    ImmediateOrCancel,
+    /// Fills in full straight away or not at all.
+    FillOrKill,
+}
+
+impl TimeInForce {
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeInForce::Day => "DAY",
+            TimeInForce::GoodTillCancel => "GTC",
+            TimeInForce::ImmediateOrCancel => "IOC",
+            TimeInForce::FillOrKill => "FOK",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    /// Whether the order is still working in the book.
+    pub fn is_open(self) -> bool {
+        matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OrderStatus::New => "new",
+            OrderStatus::PartiallyFilled => "partially filled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Rejected => "rejected",
+            OrderStatus::Expired => "expired",
+        }
+    }
+}
+
+/// What happened to an order in an [`ExecutionReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionType {
+    New,
+    Trade,
+    Cancelled,
+    Replaced,
+    Rejected,
+    Expired,
+}
+
+/// An order as a client submits it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub ticker: String,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub quantity: u64,
+    /// Required for limit orders, and not allowed for market orders.
+    pub limit_price: Option<Price>,
+    pub time_in_force: TimeInForce,
+    /// The client's own reference, echoed back unchanged.
+    pub client_order_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub id: u64,
+    pub client_order_id: String,
+    pub ticker: String,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    pub quantity: u64,
+    pub limit_price: Option<Price>,
+    pub filled_quantity: u64,
+    /// Sum of price times quantity over every fill, in billionths.
+    filled_value: i128,
+    pub status: OrderStatus,
+    pub created: SystemTime,
+    pub updated: SystemTime,
+}
+
+impl Order {
+    /// Quantity still to be filled.
+    pub fn leaves_quantity(&self) -> u64 {
+        self.quantity - self.filled_quantity
+    }
+
+    /// Average fill price, to the nearest billionth; `None` before the
+    /// first fill.
+    pub fn average_price(&self) -> Option<Price> {
+        (self.filled_quantity > 0).then(|| {
+            let quantity = self.filled_quantity as i128;
+            Price::from_nanos(((self.filled_value + quantity / 2) / quantity) as i64)
+        })
+    }
+
+    fn fill(&mut self, price: Price, quantity: u64, now: SystemTime) {
+        self.filled_quantity += quantity;
+        self.filled_value += price.as_nanos() as i128 * quantity as i128;
+        self.status = if self.leaves_quantity() == 0 {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        self.updated = now;
+    }
+}
+
+/// One event in an order's life, with the order as it stood afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    pub id: u64,
+    pub exec_type: ExecutionType,
+    pub order: Order,
+    /// Price and quantity of the fill, for trades.
+    pub last_price: Option<Price>,
+    pub last_quantity: u64,
+    pub timestamp: SystemTime,
+    /// Why the order was rejected, cancelled or expired.
+    pub reason: Option<String>,
+}
+
+/// Quantity waiting at a price level: a client's order, or simulated
+/// liquidity when `order` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Resting {
+    order: Option<u64>,
+    quantity: u64,
+}
+
+/// Price levels on both sides of one ticker's book, each a queue in time
+/// priority.
+#[derive(Debug, Clone)]
+struct OrderBook {
+    bids: BTreeMap<Price, VecDeque<Resting>>,
+    asks: BTreeMap<Price, VecDeque<Resting>>,
+    /// The depth as of the last update published.
+    published: DepthBook,
+    sequence: u64,
+    updated: SystemTime,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        OrderBook {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            published: DepthBook::default(),
+            sequence: 0,
+            updated: SystemTime::now(),
+        }
+    }
+}
+
+impl OrderBook {
+    /// Total quantity at each price.
+    fn depth(&self) -> DepthBook {
+        let mut depth = DepthBook::default();
+        for (side, levels) in [(Side::Buy, &self.bids), (Side::Sell, &self.asks)] {
+            for (&price, queue) in levels {
+                depth.add(
+                    side,
+                    price,
+                    queue.iter().map(|resting| resting.quantity).sum(),
+                );
+            }
+        }
+        depth
+    }
+
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<Price, VecDeque<Resting>> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// The best price a `taker` could trade at.
+    fn best_opposite(&self, taker: Side) -> Option<Price> {
+        match taker {
+            Side::Buy => self.asks.keys().next().copied(),
+            Side::Sell => self.bids.keys().next_back().copied(),
+        }
+    }
+
+    fn insert(&mut self, side: Side, price: Price, resting: Resting) {
+        self.levels_mut(side)
+            .entry(price)
+            .or_default()
+            .push_back(resting);
+    }
+
+    /// Removes order `id` from the level at `price`.
+    fn remove(&mut self, side: Side, price: Price, id: u64) {
+        let levels = self.levels_mut(side);
+        if let Some(queue) = levels.get_mut(&price) {
+            queue.retain(|resting| resting.order != Some(id));
+            if queue.is_empty() {
+                levels.remove(&price);
+            }
+        }
+    }
+
+    /// Changes the quantity of order `id` without moving it in its queue.
+    fn resize(&mut self, side: Side, price: Price, id: u64, quantity: u64) {
+        if let Some(resting) = self
+            .levels_mut(side)
+            .get_mut(&price)
+            .and_then(|queue| queue.iter_mut().find(|resting| resting.order == Some(id)))
+        {
+            resting.quantity = quantity;
+        }
+    }
+
+    fn clear_simulated(&mut self) {
+        for levels in [&mut self.bids, &mut self.asks] {
+            levels.retain(|_, queue| {
+                queue.retain(|resting| resting.order.is_some());
+                !queue.is_empty()
+            });
+        }
+    }
+
+    /// Quantity a `taker` limited to `limit` could fill right now.
+    fn available(&self, taker: Side, limit: Option<Price>) -> u64 {
+        let levels: Box<dyn Iterator<Item = (&Price, &VecDeque<Resting>)>> = match taker {
+            Side::Buy => Box::new(self.asks.iter()),
+            Side::Sell => Box::new(self.bids.iter().rev()),
+        };
+        levels
+            .take_while(|(price, _)| crosses(taker, **price, limit))
+            .flat_map(|(_, queue)| queue.iter().map(|resting| resting.quantity))
+            .sum()
+    }
+
+    /// Takes up to `quantity` for a `taker` limited to `limit`, best price
+    /// first and in time priority within a level. Returns each maker filled,
+    /// with the quantity it gave, at its level's price.
+    fn take(
+        &mut self,
+        taker: Side,
+        limit: Option<Price>,
+        mut quantity: u64,
+    ) -> Vec<(Price, Resting)> {
+        let mut fills = Vec::new();
+        while quantity > 0 {
+            let Some(price) = self
+                .best_opposite(taker)
+                .filter(|price| crosses(taker, *price, limit))
+            else {
+                break;
+            };
+            let levels = self.levels_mut(match taker {
+                Side::Buy => Side::Sell,
+                Side::Sell => Side::Buy,
+            });
+            let queue = levels.get_mut(&price).expect("best level exists");
+            let maker = queue.front_mut().expect("levels are never empty");
+            let filled = maker.quantity.min(quantity);
+            maker.quantity -= filled;
+            quantity -= filled;
+            fills.push((
+                price,
+                Resting {
+                    order: maker.order,
+                    quantity: filled,
+                },
+            ));
+            if maker.quantity == 0 {
+                queue.pop_front();
+                if queue.is_empty() {
+                    levels.remove(&price);
+                }
+            }
+        }
+        fills
+    }
+}
+
+/// Whether a `taker` limited to `limit` may trade at `price`.
+fn crosses(taker: Side, price: Price, limit: Option<Price>) -> bool {
+    limit.map_or(true, |limit| match taker {
+        Side::Buy => price <= limit,
+        Side::Sell => price >= limit,
+    })
+}
+
+/// A paper-trading venue: one limit order book per ticker, matching client
+/// orders against each other and against simulated liquidity.
+///
+/// Every published quote replaces the ticker's simulated liquidity with
+/// `levels` price levels a side, starting at the quote's bid and ask and
+/// `level_spacing_ticks` ticks apart, each deeper level showing more size.
+/// New liquidity that crosses a resting client order trades with it at the
+/// order's price, as an arriving order would.
+///
+/// Finished orders are kept for lookup until
+/// `RETAINED_FINISHED_ORDERS` newer ones have finished.
+///
+/// Each change to a book's depth is queued as a [`DepthUpdate`] for
+/// [`take_depth_updates`](Self::take_depth_updates).
+#[derive(Debug, Clone)]
+pub struct PaperExchange {
+    levels: u32,
+    level_spacing_ticks: u32,
+    books: HashMap<String, OrderBook>,
+    orders: HashMap<u64, Order>,
+    /// Ids of filled, cancelled, rejected and expired orders, oldest first.
+    finished: VecDeque<u64>,
+    finished_retention: usize,
+    next_order_id: u64,
+    next_execution_id: u64,
+    depth_updates: Vec<DepthUpdate>,
+}
+
+impl Default for PaperExchange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaperExchange {
+    pub fn new() -> Self {
+        Self::from_config(&OrderBookConfig::default()).expect("default order book config is valid")
+    }
+
+    pub fn from_config(config: &OrderBookConfig) -> Result<Self, String> {
+        if config.levels == 0 || config.level_spacing_ticks == 0 {
+            return Err(format!(
+                "Order book levels and level_spacing_ticks must be positive, got {} and {}",
+                config.levels, config.level_spacing_ticks
+            ));
+        }
+        Ok(PaperExchange {
+            levels: config.levels,
+            level_spacing_ticks: config.level_spacing_ticks,
+            books: HashMap::new(),
+            orders: HashMap::new(),
+            finished: VecDeque::new(),
+            finished_retention: RETAINED_FINISHED_ORDERS,
+            next_order_id: 1,
+            next_execution_id: 1,
+            depth_updates: Vec::new(),
+        })
+    }
+
+    pub fn order(&self, id: u64) -> Option<&Order> {
+        self.orders.get(&id)
+    }
+
+    /// The best `levels` levels a side of `ticker`'s book, or all of them
+    /// when zero.
+    pub fn depth(&self, ticker: &str, levels: usize) -> DepthSnapshot {
+        match self.books.get(ticker) {
+            Some(book) => book
+                .published
+                .top(levels)
+                .snapshot(ticker, book.sequence, book.updated),
+            None => DepthBook::default().snapshot(ticker, 0, SystemTime::now()),
+        }
+    }
+
+    /// The depth updates since the last call, oldest first.
+    pub fn take_depth_updates(&mut self) -> Vec<DepthUpdate> {
+        std::mem::take(&mut self.depth_updates)
+    }
+
+    /// Checks `request` against the instrument's `tick_size`.
+    pub fn validate(&self, request: &OrderRequest, tick_size: Price) -> Result<(), String> {
+        if request.quantity == 0 {
+            return Err("Order quantity must be positive".to_string());
+        }
+        match (request.order_type, request.limit_price) {
+            (OrderType::Market, Some(_)) => {
+                Err("A market order cannot have a limit price".to_string())
+            }
+            (OrderType::Market, None) => Ok(()),
+            (OrderType::Limit, None) => Err("A limit order needs a limit price".to_string()),
+            (OrderType::Limit, Some(price)) => validate_limit(price, tick_size),
+        }
+    }
+
+    /// Records `request` as rejected for `reason`, without it reaching the
+    /// book.
+    pub fn reject(
+        &mut self,
+        request: OrderRequest,
+        reason: String,
+        now: SystemTime,
+    ) -> ExecutionReport {
+        let mut order = self.new_order(request, now);
+        order.status = OrderStatus::Rejected;
+        self.orders.insert(order.id, order.clone());
+        self.retire_if_finished(order.id);
+        self.report(ExecutionType::Rejected, &order, None, now, Some(reason))
+    }
+
+    /// Accepts `request` and matches it against the book straight away.
+    /// Returns every resulting report, the new order's first.
+    pub fn submit(
+        &mut self,
+        request: OrderRequest,
+        tick_size: Price,
+        now: SystemTime,
+    ) -> Result<Vec<ExecutionReport>, String> {
+        self.validate(&request, tick_size)?;
+        let mut order = self.new_order(request, now);
+        let mut reports = vec![self.report(ExecutionType::New, &order, None, now, None)];
+
+        let book = self.books.entry(order.ticker.clone()).or_default();
+        if order.time_in_force == TimeInForce::FillOrKill
+            && book.available(order.side, order.limit_price) < order.quantity
+        {
+            order.status = OrderStatus::Cancelled;
+            order.updated = now;
+            let reason = "Fill-or-kill order could not be filled in full".to_string();
+            reports.push(self.report(ExecutionType::Cancelled, &order, None, now, Some(reason)));
+        } else {
+            reports.extend(self.execute(&mut order, now));
+            let rests = order.order_type == OrderType::Limit
+                && matches!(
+                    order.time_in_force,
+                    TimeInForce::Day | TimeInForce::GoodTillCancel
+                );
+            if order.leaves_quantity() > 0 {
+                if rests {
+                    self.rest(&order);
+                } else {
+                    let reason = format!("{} unfilled shares cancelled", order.leaves_quantity());
+                    order.status = OrderStatus::Cancelled;
+                    order.updated = now;
+                    reports.push(self.report(
+                        ExecutionType::Cancelled,
+                        &order,
+                        None,
+                        now,
+                        Some(reason),
+                    ));
+                }
+            }
+        }
+        self.publish_depth(&order.ticker, now);
+        let id = order.id;
+        self.orders.insert(id, order);
+        self.retire_if_finished(id);
+        Ok(reports)
+    }
+
+    /// Cancels what is left of open order `id`.
+    pub fn cancel(&mut self, id: u64, now: SystemTime) -> Result<ExecutionReport, String> {
+        self.open_order(id)?;
+        Ok(self.close(id, OrderStatus::Cancelled, None, now))
+    }
+
+    /// Changes open order `id`'s total quantity and limit price, keeping
+    /// those left as `None`. The order keeps its place in the queue only
+    /// when its price is unchanged and its quantity does not grow; a new
+    /// price that crosses the book trades straight away.
+    pub fn replace(
+        &mut self,
+        id: u64,
+        quantity: Option<u64>,
+        limit_price: Option<Price>,
+        tick_size: Price,
+        now: SystemTime,
+    ) -> Result<Vec<ExecutionReport>, String> {
+        let mut order = self.open_order(id)?.clone();
+        let old_price = order.limit_price.expect("open orders are limit orders");
+        let quantity = quantity.unwrap_or(order.quantity);
+        if quantity <= order.filled_quantity {
+            return Err(format!(
+                "New quantity {} must exceed the {} shares already filled",
+                quantity, order.filled_quantity
+            ));
+        }
+        let price = limit_price.unwrap_or(old_price);
+        validate_limit(price, tick_size)?;
+
+        let keeps_priority = price == old_price && quantity <= order.quantity;
+        order.quantity = quantity;
+        order.limit_price = Some(price);
+        order.updated = now;
+        let mut reports = vec![self.report(ExecutionType::Replaced, &order, None, now, None)];
+        let book = self.books.entry(order.ticker.clone()).or_default();
+        if keeps_priority {
+            book.resize(order.side, price, id, order.leaves_quantity());
+        } else {
+            book.remove(order.side, old_price, id);
+            reports.extend(self.execute(&mut order, now));
+            if order.leaves_quantity() > 0 {
+                self.rest(&order);
+            }
+        }
+        self.publish_depth(&order.ticker, now);
+        self.orders.insert(id, order);
+        self.retire_if_finished(id);
+        Ok(reports)
+    }
+
+    /// Replaces `ticker`'s simulated liquidity with fresh levels around
+    /// `quote`, and returns the fills of client orders it traded with.
+    pub fn reseed(
+        &mut self,
+        ticker: &str,
+        quote: &Quote,
+        tick_size: Price,
+        now: SystemTime,
+    ) -> Vec<ExecutionReport> {
+        let spacing = tick_size
+            .as_nanos()
+            .checked_mul(self.level_spacing_ticks as i64);
+        let book = self.books.entry(ticker.to_string()).or_default();
+        book.clear_simulated();
+        let mut fills = Vec::new();
+        for level in 0..self.levels {
+            let offset = spacing
+                .and_then(|spacing| spacing.checked_mul(level as i64))
+                .map(Price::from_nanos);
+            let depth = level as u64 + 1;
+            for (side, price, size) in [
+                (
+                    Side::Buy,
+                    offset.and_then(|offset| quote.bid.checked_sub(offset)),
+                    quote.bid_size * depth,
+                ),
+                (
+                    Side::Sell,
+                    offset.and_then(|offset| quote.ask.checked_add(offset)),
+                    quote.ask_size * depth,
+                ),
+            ] {
+                let Some(price) = price.filter(|price| *price > Price::ZERO) else {
+                    continue;
+                };
+                let taken = book.take(side, Some(price), size);
+                let remaining = size - taken.iter().map(|(_, maker)| maker.quantity).sum::<u64>();
+                fills.extend(taken);
+                if remaining > 0 {
+                    book.insert(
+                        side,
+                        price,
+                        Resting {
+                            order: None,
+                            quantity: remaining,
+                        },
+                    );
+                }
+            }
+        }
+        self.publish_depth(ticker, now);
+        self.fill_makers(fills, now)
+    }
+
+    /// Expires every open day order, as at the market close.
+    pub fn expire_day_orders(&mut self, now: SystemTime) -> Vec<ExecutionReport> {
+        let ids = self.open_ids(|order| order.time_in_force == TimeInForce::Day);
+        ids.into_iter()
+            .map(|id| {
+                let reason = "Day order expired at the close".to_string();
+                self.close(id, OrderStatus::Expired, Some(reason), now)
+            })
+            .collect()
+    }
+
+    /// Cancels every open order on `ticker` for `reason`.
+    pub fn cancel_all(
+        &mut self,
+        ticker: &str,
+        reason: &str,
+        now: SystemTime,
+    ) -> Vec<ExecutionReport> {
+        let ids = self.open_ids(|order| order.ticker == ticker);
+        ids.into_iter()
+            .map(|id| self.close(id, OrderStatus::Cancelled, Some(reason.to_string()), now))
+            .collect()
+    }
+
+    fn new_order(&mut self, request: OrderRequest, now: SystemTime) -> Order {
+        let id = self.next_order_id;
+        self.next_order_id += 1;
+        Order {
+            id,
+            client_order_id: request.client_order_id,
+            ticker: request.ticker,
+            side: request.side,
+            order_type: request.order_type,
+            time_in_force: request.time_in_force,
+            quantity: request.quantity,
+            limit_price: request.limit_price,
+            filled_quantity: 0,
+            filled_value: 0,
+            status: OrderStatus::New,
+            created: now,
+            updated: now,
+        }
+    }
+
+    fn open_order(&self, id: u64) -> Result<&Order, String> {
+        let order = self
+            .orders
+            .get(&id)
+            .ok_or_else(|| format!("Unknown order {}", id))?;
+        if !order.status.is_open() {
+            return Err(format!("Order {} is already {}", id, order.status.label()));
+        }
+        Ok(order)
+    }
+
+    /// Open orders matching `filter`, oldest first.
+    fn open_ids(&self, filter: impl Fn(&Order) -> bool) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .orders
+            .values()
+            .filter(|order| order.status.is_open() && filter(order))
+            .map(|order| order.id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Matches `order` as a taker and fills the makers it trades with.
+    fn execute(&mut self, order: &mut Order, now: SystemTime) -> Vec<ExecutionReport> {
+        let fills = self.books.entry(order.ticker.clone()).or_default().take(
+            order.side,
+            order.limit_price,
+            order.leaves_quantity(),
+        );
+        let mut reports = Vec::new();
+        for &(price, maker) in &fills {
+            order.fill(price, maker.quantity, now);
+            reports.push(self.report(
+                ExecutionType::Trade,
+                order,
+                Some((price, maker.quantity)),
+                now,
+                None,
+            ));
+        }
+        reports.extend(self.fill_makers(fills, now));
+        reports
+    }
+
+    /// Fills the client orders among `fills`' makers.
+    fn fill_makers(
+        &mut self,
+        fills: Vec<(Price, Resting)>,
+        now: SystemTime,
+    ) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        for (price, maker) in fills {
+            let Some(order) = maker.order.and_then(|id| self.orders.get_mut(&id)) else {
+                continue;
+            };
+            order.fill(price, maker.quantity, now);
+            let order = order.clone();
+            self.retire_if_finished(order.id);
+            reports.push(self.report(
+                ExecutionType::Trade,
+                &order,
+                Some((price, maker.quantity)),
+                now,
+                None,
+            ));
+        }
+        reports
+    }
+
+    fn rest(&mut self, order: &Order) {
+        let price = order.limit_price.expect("only limit orders rest");
+        self.books.entry(order.ticker.clone()).or_default().insert(
+            order.side,
+            price,
+            Resting {
+                order: Some(order.id),
+                quantity: order.leaves_quantity(),
+            },
+        );
+    }
+
+    /// Takes open order `id` out of the book with final `status`.
+    fn close(
+        &mut self,
+        id: u64,
+        status: OrderStatus,
+        reason: Option<String>,
+        now: SystemTime,
+    ) -> ExecutionReport {
+        let order = self.orders.get_mut(&id).expect("closed orders exist");
+        if let (Some(book), Some(price)) = (self.books.get_mut(&order.ticker), order.limit_price) {
+            book.remove(order.side, price, id);
+        }
+        order.status = status;
+        order.updated = now;
+        let order = order.clone();
+        self.retire_if_finished(id);
+        self.publish_depth(&order.ticker, now);
+        let exec_type = match status {
+            OrderStatus::Expired => ExecutionType::Expired,
+            _ => ExecutionType::Cancelled,
+        };
+        self.report(exec_type, &order, None, now, reason)
+    }
+
+    /// Notes order `id` as finished once it is no longer open, forgetting
+    /// the oldest finished orders beyond the retention.
+    fn retire_if_finished(&mut self, id: u64) {
+        if self
+            .orders
+            .get(&id)
+            .map_or(true, |order| order.status.is_open())
+        {
+            return;
+        }
+        self.finished.push_back(id);
+        while self.finished.len() > self.finished_retention {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.orders.remove(&oldest);
+            }
+        }
+    }
+
+    /// Queues the change in `ticker`'s depth since its last update, if any.
+    fn publish_depth(&mut self, ticker: &str, now: SystemTime) {
+        let Some(book) = self.books.get_mut(ticker) else {
+            return;
+        };
+        let depth = book.depth();
+        let levels = book.published.changes_to(&depth);
+        if levels.is_empty() {
+            return;
+        }
+        book.published = depth;
+        book.sequence += 1;
+        book.updated = now;
+        self.depth_updates.push(DepthUpdate {
+            ticker: ticker.to_string(),
+            sequence: book.sequence,
+            timestamp: now,
+            levels,
+        });
+    }
+
+    fn report(
+        &mut self,
+        exec_type: ExecutionType,
+        order: &Order,
+        fill: Option<(Price, u64)>,
+        timestamp: SystemTime,
+        reason: Option<String>,
+    ) -> ExecutionReport {
+        let id = self.next_execution_id;
+        self.next_execution_id += 1;
+        ExecutionReport {
+            id,
+            exec_type,
+            order: order.clone(),
+            last_price: fill.map(|(price, _)| price),
+            last_quantity: fill.map_or(0, |(_, quantity)| quantity),
+            timestamp,
+            reason,
+        }
+    }
+}
+
+fn validate_limit(price: Price, tick_size: Price) -> Result<(), String> {
+    if price <= Price::ZERO {
+        return Err(format!("Limit price must be positive, got {}", price));
+    }
+    if price.round_to_tick(tick_size) != Some(price) {
+        return Err(format!(
+            "Limit price {} is not a multiple of the tick size {}",
+            price, tick_size
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    fn quote(bid: f64, ask: f64) -> Quote {
+        Quote {
+            bid: price(bid),
+            ask: price(ask),
+            bid_size: 100,
+            ask_size: 100,
+            last: price((bid + ask) / 2.0),
+        }
+    }
+
+    fn request(side: Side, quantity: u64, limit: Option<f64>, tif: TimeInForce) -> OrderRequest {
+        OrderRequest {
+            ticker: "AAPL".to_string(),
+            side,
+            order_type: if limit.is_some() {
+                OrderType::Limit
+            } else {
+                OrderType::Market
+            },
+            quantity,
+            limit_price: limit.map(price),
+            time_in_force: tif,
+            client_order_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_orders_sweep_simulated_liquidity() {
+        let cent = price(0.01);
+        let now = SystemTime::now();
+        let mut exchange = PaperExchange::new();
+        // Five levels a side: 100 at 100.01, 200 at 100.02, ...
+        exchange.reseed("AAPL", &quote(99.99, 100.01), cent, now);
+
+        let reports = exchange
+            .submit(request(Side::Buy, 250, None, TimeInForce::Day), cent, now)
+            .unwrap();
+        let types: Vec<ExecutionType> = reports.iter().map(|r| r.exec_type).collect();
+        assert_eq!(
+            types,
+            [
+                ExecutionType::New,
+                ExecutionType::Trade,
+                ExecutionType::Trade
+            ]
+        );
+        let order = &reports.last().unwrap().order;
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(reports[2].last_price, Some(price(100.02)));
+        // (100 * 100.01 + 150 * 100.02) / 250
+        assert_eq!(order.average_price(), Some(price(100.016)));
+
+        // A fill-or-kill beyond the visible size is killed untouched.
+        let reports = exchange
+            .submit(
+                request(Side::Sell, 10_000, Some(99.0), TimeInForce::FillOrKill),
+                cent,
+                now,
+            )
+            .unwrap();
+        assert_eq!(reports.last().unwrap().order.status, OrderStatus::Cancelled);
+        assert_eq!(reports.last().unwrap().order.filled_quantity, 0);
+
+        // An IOC limit takes what crosses and cancels the rest.
+        let reports = exchange
+            .submit(
+                request(Side::Sell, 150, Some(99.99), TimeInForce::ImmediateOrCancel),
+                cent,
+                now,
+            )
+            .unwrap();
+        let order = &reports.last().unwrap().order;
+        assert_eq!(
+            (order.filled_quantity, order.status),
+            (100, OrderStatus::Cancelled)
+        );
+
+        assert!(exchange
+            .submit(request(Side::Buy, 0, None, TimeInForce::Day), cent, now)
+            .is_err());
+        assert!(exchange
+            .submit(
+                request(Side::Buy, 1, Some(100.005), TimeInForce::Day),
+                cent,
+                now
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_finished_orders_are_forgotten_beyond_the_retention() {
+        let mut exchange = PaperExchange::new();
+        exchange.finished_retention = 2;
+        let now = SystemTime::now();
+        let ids: Vec<u64> = (0..3)
+            .map(|_| {
+                let request = request(Side::Buy, 10, None, TimeInForce::Day);
+                exchange.reject(request, "closed".to_string(), now).order.id
+            })
+            .collect();
+        assert!(exchange.order(ids[0]).is_none());
+        assert!(exchange.order(ids[1]).is_some() && exchange.order(ids[2]).is_some());
+    }
+
+    #[test]
+    fn test_resting_orders_trade_with_new_liquidity() {
+        let cent = price(0.01);
+        let now = SystemTime::now();
+        let mut exchange = PaperExchange::new();
+        exchange.reseed("AAPL", &quote(99.99, 100.01), cent, now);
+
+        let reports = exchange
+            .submit(
+                request(Side::Buy, 150, Some(99.95), TimeInForce::GoodTillCancel),
+                cent,
+                now,
+            )
+            .unwrap();
+        let id = reports[0].order.id;
+        assert_eq!(exchange.order(id).unwrap().status, OrderStatus::New);
+
+        // Shrinking keeps priority; the market then falls through the bid.
+        exchange.replace(id, Some(120), None, cent, now).unwrap();
+        let fills = exchange.reseed("AAPL", &quote(99.90, 99.92), cent, now);
+        assert_eq!(fills.len(), 2);
+        assert!(fills
+            .iter()
+            .all(|fill| fill.last_price == Some(price(99.95))));
+        assert_eq!(fills[1].order.status, OrderStatus::Filled);
+        assert!(exchange.cancel(id, now).is_err());
+
+        let day = exchange
+            .submit(
+                request(Side::Sell, 10, Some(101.0), TimeInForce::Day),
+                cent,
+                now,
+            )
+            .unwrap()[0]
+            .order
+            .id;
+        assert!(exchange.replace(day, Some(0), None, cent, now).is_err());
+        let expired = exchange.expire_day_orders(now);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(exchange.order(day).unwrap().status, OrderStatus::Expired);
+        assert!(exchange
+            .reseed("AAPL", &quote(101.5, 101.6), cent, now)
+            .is_empty());
+    }
+}
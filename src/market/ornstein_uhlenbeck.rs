@@ -0,0 +1,50 @@
+use super::PriceModel;
+use rand::RngCore;
+
+/// Ornstein-Uhlenbeck: dX = theta * (mean - X) * dt + sigma * dW.
+///
+/// Unlike the multiplicative models the process is additive and may cross
+/// zero, which is what spreads and rates are expected to do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrnsteinUhlenbeck {
+    pub theta: f64,
+    pub mean: f64,
+    pub sigma: f64,
+}
+
+impl OrnsteinUhlenbeck {
+    pub fn new(theta: f64, mean: f64, sigma: f64) -> Self {
+        OrnsteinUhlenbeck { theta, mean, sigma }
+    }
+}
+
+impl PriceModel for OrnsteinUhlenbeck {
+    /// Samples the exact transition density, which stays accurate for any `dt`.
+    fn step(&mut self, price: f64, dt: f64, z: f64, _rng: &mut dyn RngCore) -> f64 {
+        if self.theta == 0.0 {
+            return price + self.sigma * dt.sqrt() * z;
+        }
+        let decay = (-self.theta * dt).exp();
+        let std_dev = self.sigma * ((1.0 - decay * decay) / (2.0 * self.theta)).sqrt();
+        self.mean + (price - self.mean) * decay + std_dev * z
+    }
+
+    fn name(&self) -> &'static str {
+        "ornstein_uhlenbeck"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_reverts_towards_mean() {
+        let mut model = OrnsteinUhlenbeck::new(5.0, 2.0, 0.0);
+        let mut rng = StdRng::seed_from_u64(5);
+        let next = model.step(10.0, 1.0, 0.0, &mut rng);
+        assert!((next - (2.0 + 8.0 * (-5.0f64).exp())).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,219 @@
+use super::TickerRegistry;
+use crate::config::{MarketConfig, QuoteConfig};
+use crate::price::Price;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+/// The inside market for a ticker: best bid and ask around the last price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub bid: Price,
+    pub ask: Price,
+    pub bid_size: u64,
+    pub ask_size: u64,
+    pub last: Price,
+}
+
+impl Quote {
+    pub fn spread(&self) -> Option<Price> {
+        self.ask.checked_sub(self.bid)
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.bid.to_f64() + self.ask.to_f64()) / 2.0
+    }
+}
+
+/// Largest `mean_size` a quote config may set, leaving room to total the
+/// traded shares.
+const MAX_MEAN_SIZE: u64 = 1_000_000_000;
+
+/// Builds a quote for every published price, centred on that price so bid,
+/// ask and last always agree with the underlying model.
+///
+/// The spread is the configured basis points of the price, widened in
+/// proportion to the size of the latest move and never narrower than
+/// `min_spread`. The bid rounds down and the ask up to the instrument's tick,
+/// so both sit on a tick at least one away from the last price. Sizes on
+/// each side are drawn uniformly around `mean_size`.
+pub struct QuoteGenerator {
+    configs: HashMap<String, QuoteConfig>,
+    rng: ChaCha8Rng,
+    last_prices: HashMap<String, Price>,
+}
+
+impl Default for QuoteGenerator {
+    fn default() -> Self {
+        Self::new(HashMap::new(), rand::random())
+    }
+}
+
+impl QuoteGenerator {
+    /// Sizes are drawn from their own ChaCha8 stream, so quoting never
+    /// perturbs the price paths produced from the same seed.
+    pub fn new(configs: HashMap<String, QuoteConfig>, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(1);
+        QuoteGenerator {
+            configs,
+            rng,
+            last_prices: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &MarketConfig, seed: u64) -> Result<Self, String> {
+        let registry = TickerRegistry::from_config(config)?;
+        for (ticker, quote) in &config.quotes {
+            if !registry.contains(ticker) {
+                return Err(format!("Unknown ticker in quote config: {}", ticker));
+            }
+            let non_negative = |value: f64| value.is_finite() && value >= 0.0;
+            if !non_negative(quote.spread_bps)
+                || !non_negative(quote.min_spread)
+                || !non_negative(quote.volatility_widening)
+            {
+                return Err(format!(
+                    "Quote spread settings for {} must be non-negative",
+                    ticker
+                ));
+            }
+            if !(1..=MAX_MEAN_SIZE).contains(&quote.mean_size) {
+                return Err(format!(
+                    "Quote mean_size for {} must be between 1 and {}, got {}",
+                    ticker, MAX_MEAN_SIZE, quote.mean_size
+                ));
+            }
+        }
+        Ok(Self::new(config.quotes.clone(), seed))
+    }
+
+    /// Forgets `ticker`'s last price, so its next quote does not widen for a
+    /// jump that was not a market move.
+    pub fn reset(&mut self, ticker: &str) {
+        self.last_prices.remove(ticker);
+    }
+
+    /// Quotes `ticker` at `price`, its newest last price, on a grid of `tick`.
+    pub fn quote(&mut self, ticker: &str, price: Price, tick: Price) -> Quote {
+        let config = self.configs.get(ticker).cloned().unwrap_or_default();
+        let previous = self.last_prices.insert(ticker.to_string(), price);
+        let last = price.to_f64();
+        let abs_return = match previous.map(Price::to_f64) {
+            Some(previous) if previous > 0.0 && last > 0.0 => (last / previous).ln().abs(),
+            _ => 0.0,
+        };
+
+        let relative = config.spread_bps / 10_000.0 + config.volatility_widening * abs_return;
+        let half_spread = (last.abs() * relative).max(config.min_spread) / 2.0;
+        let half_spread = Price::from_f64(half_spread).unwrap_or(Price::ZERO);
+        let mut size = || {
+            self.rng
+                .gen_range(1..config.mean_size.saturating_mul(2).max(2))
+        };
+        let bid = price
+            .checked_sub(half_spread)
+            .and_then(|bid| bid.floor_to_tick(tick));
+        let ask = price
+            .checked_add(half_spread)
+            .and_then(|ask| ask.ceil_to_tick(tick));
+        // At the ends of the range, a side keeps whichever bound it can
+        // represent, or the last price itself.
+        Quote {
+            bid: bid
+                .into_iter()
+                .chain(price.checked_sub(tick))
+                .min()
+                .unwrap_or(price),
+            ask: ask
+                .into_iter()
+                .chain(price.checked_add(tick))
+                .max()
+                .unwrap_or(price),
+            bid_size: size(),
+            ask_size: size(),
+            last: price,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    #[test]
+    fn test_quote_brackets_last_price() {
+        let mut generator = QuoteGenerator::new(HashMap::new(), 7);
+        let cent = price(0.01);
+        let quote = generator.quote("AAPL", price(100.0), cent);
+        assert_eq!(quote.last, price(100.0));
+        assert!(quote.bid < quote.last && quote.ask > quote.last);
+        assert!((quote.mid() - 100.0).abs() < 1e-12);
+        // 2bp of 100 is 0.02.
+        assert_eq!(quote.spread(), Some(price(0.02)));
+        assert!(quote.bid_size > 0 && quote.ask_size > 0);
+
+        // Both sides stay on the tick grid, a tick or more from the last price.
+        let coarse = generator.quote("MSFT", price(10.0), price(0.05));
+        assert_eq!((coarse.bid, coarse.ask), (price(9.95), price(10.05)));
+
+        // With no tick left above it, the top of the range is its own ask.
+        let top = Price::from_nanos(i64::MAX);
+        let edge = generator.quote("NVDA", top, cent);
+        assert_eq!(edge.ask, top);
+        assert!(edge.bid < top);
+    }
+
+    #[test]
+    fn test_spread_widens_on_large_moves_and_respects_minimum() {
+        let config = QuoteConfig {
+            spread_bps: 1.0,
+            min_spread: 0.05,
+            volatility_widening: 1.0,
+            mean_size: 100,
+        };
+        let mut generator = QuoteGenerator::new(HashMap::from([("AAPL".to_string(), config)]), 7);
+        let cent = price(0.01);
+        // 1bp of 100 is below the 0.05 floor, which each side rounds out to a tick.
+        assert_eq!(
+            generator.quote("AAPL", price(100.0), cent).spread(),
+            Some(price(0.06))
+        );
+        let calm = generator.quote("AAPL", price(100.0), cent).spread();
+        let jumpy = generator.quote("AAPL", price(110.0), cent).spread();
+        assert!(jumpy > calm);
+    }
+
+    #[test]
+    fn test_invalid_quote_config_is_rejected() {
+        let mut config = MarketConfig::default();
+        config.quotes.insert(
+            "AAPL".to_string(),
+            QuoteConfig {
+                spread_bps: -1.0,
+                ..QuoteConfig::default()
+            },
+        );
+        assert!(QuoteGenerator::from_config(&config, 1).is_err());
+
+        let mut config = MarketConfig::default();
+        config.quotes.insert(
+            "AAPL".to_string(),
+            QuoteConfig {
+                mean_size: u64::MAX,
+                ..QuoteConfig::default()
+            },
+        );
+        assert!(QuoteGenerator::from_config(&config, 1).is_err());
+        // Sizes stay in range even when the check is bypassed.
+        let mut generator = QuoteGenerator::new(config.quotes, 1);
+        assert!(generator.quote("AAPL", price(100.0), price(0.01)).bid_size > 0);
+
+        let mut config = MarketConfig::default();
+        config
+            .quotes
+            .insert("XYZ".to_string(), QuoteConfig::default());
+        assert!(QuoteGenerator::from_config(&config, 1).is_err());
+    }
+}
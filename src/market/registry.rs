@@ -0,0 +1,313 @@
+use crate::config::{InstrumentConfig, MarketConfig};
+use crate::price::{decimals_for_tick, Price};
+use crate::utils::TICKERS;
+
+/// A listed symbol and its reference data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub symbol: String,
+    pub name: String,
+    pub exchange: String,
+    pub currency: String,
+    pub sector: String,
+    /// Smallest price increment; every published price is a multiple of it.
+    pub tick_size: Price,
+    /// Number of shares in one round lot.
+    pub lot_size: u64,
+}
+
+impl Instrument {
+    /// Built-in metadata for `symbol`; symbols outside the built-in universe
+    /// get their symbol as name and generic values for the rest.
+    pub fn builtin(symbol: &str) -> Self {
+        let (name, exchange, sector) = match symbol {
+            "AAPL" => ("Apple Inc.", "NASDAQ", "Technology"),
+            "MSFT" => ("Microsoft Corporation", "NASDAQ", "Technology"),
+            "GOOG" => ("Alphabet Inc.", "NASDAQ", "Communication Services"),
+            "AMZN" => ("Amazon.com, Inc.", "NASDAQ", "Consumer Discretionary"),
+            "META" => ("Meta Platforms, Inc.", "NASDAQ", "Communication Services"),
+            "NFLX" => ("Netflix, Inc.", "NASDAQ", "Communication Services"),
+            "TSLA" => ("Tesla, Inc.", "NASDAQ", "Consumer Discretionary"),
+            "NVDA" => ("NVIDIA Corporation", "NASDAQ", "Technology"),
+            "AMD" => ("Advanced Micro Devices, Inc.", "NASDAQ", "Technology"),
+            "INTC" => ("Intel Corporation", "NASDAQ", "Technology"),
+            _ => (symbol, "SIM", ""),
+        };
+        Instrument {
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            exchange: exchange.to_string(),
+            currency: "USD".to_string(),
+            sector: sector.to_string(),
+            tick_size: Price::from_nanos(10_000_000),
+            lot_size: 100,
+        }
+    }
+
+    /// Decimal places prices of this instrument are shown with.
+    pub fn decimals(&self) -> u32 {
+        decimals_for_tick(self.tick_size)
+    }
+
+    /// `price` rounded to the nearest tick, or `None` when it is not finite
+    /// or out of range.
+    pub fn round(&self, price: f64) -> Option<Price> {
+        Price::from_f64(price).and_then(|price| price.round_to_tick(self.tick_size))
+    }
+
+    /// Overrides the fields `config` sets.
+    pub fn apply(&mut self, config: &InstrumentConfig) {
+        let InstrumentConfig {
+            name,
+            exchange,
+            currency,
+            sector,
+            tick_size,
+            lot_size,
+        } = config.clone();
+        self.name = name.unwrap_or(std::mem::take(&mut self.name));
+        self.exchange = exchange.unwrap_or(std::mem::take(&mut self.exchange));
+        self.currency = currency.unwrap_or(std::mem::take(&mut self.currency));
+        self.sector = sector.unwrap_or(std::mem::take(&mut self.sector));
+        // Sizes that are not finite or round to zero nanos fail validation.
+        self.tick_size = tick_size.map_or(self.tick_size, |tick_size| {
+            Price::from_f64(tick_size).unwrap_or(Price::ZERO)
+        });
+        self.lot_size = lot_size.unwrap_or(self.lot_size);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_symbol = !self.symbol.is_empty()
+            && self.symbol.len() <= 12
+            && self
+                .symbol
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '.' || c == '-');
+        if !valid_symbol {
+            return Err(format!(
+                "Invalid symbol {:?}: use 1 to 12 upper-case letters, digits, '.' or '-'",
+                self.symbol
+            ));
+        }
+        if self.tick_size <= Price::ZERO {
+            return Err(format!(
+                "Tick size for {} must be a positive multiple of 0.000000001",
+                self.symbol
+            ));
+        }
+        if self.lot_size == 0 {
+            return Err(format!("Lot size for {} must be positive", self.symbol));
+        }
+        if self.currency.len() != 3 || !self.currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!(
+                "Currency for {} must be a three-letter code, got {:?}",
+                self.symbol, self.currency
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The instruments the server currently lists, in listing order.
+///
+/// Starts from the built-in universe plus any configured additions and can
+/// change while the server runs; `version` increases with every change so
+/// followers such as the market clock can notice cheaply.
+#[derive(Debug, Clone)]
+pub struct TickerRegistry {
+    instruments: Vec<Instrument>,
+    version: u64,
+}
+
+impl Default for TickerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TickerRegistry {
+    /// The built-in universe with its built-in metadata.
+    pub fn new() -> Self {
+        TickerRegistry {
+            instruments: TICKERS.iter().map(|s| Instrument::builtin(s)).collect(),
+            version: 0,
+        }
+    }
+
+    /// The built-in universe with `[market.instruments]` applied. Configured
+    /// symbols outside it are listed after it in alphabetical order.
+    pub fn from_config(config: &MarketConfig) -> Result<Self, String> {
+        let mut registry = Self::new();
+        let mut symbols: Vec<&String> = config.instruments.keys().collect();
+        symbols.sort();
+        for symbol in symbols {
+            let overrides = &config.instruments[symbol];
+            match registry
+                .instruments
+                .iter_mut()
+                .find(|instrument| &instrument.symbol == symbol)
+            {
+                Some(instrument) => {
+                    instrument.apply(overrides);
+                    instrument.validate()?;
+                }
+                None => {
+                    let mut instrument = Instrument::builtin(symbol);
+                    instrument.apply(overrides);
+                    registry.add(instrument)?;
+                }
+            }
+        }
+        registry.version = 0;
+        Ok(registry)
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.get(symbol).is_some()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Instrument> {
+        self.instruments
+            .iter()
+            .find(|instrument| instrument.symbol == symbol)
+    }
+
+    pub fn instruments(&self) -> &[Instrument] {
+        &self.instruments
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.instruments
+            .iter()
+            .map(|instrument| instrument.symbol.clone())
+            .collect()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Lists a new instrument.
+    pub fn add(&mut self, instrument: Instrument) -> Result<(), String> {
+        instrument.validate()?;
+        if self.contains(&instrument.symbol) {
+            return Err(format!("{} is already listed", instrument.symbol));
+        }
+        self.instruments.push(instrument);
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Applies `changes` to a listed instrument and returns the result.
+    pub fn modify(
+        &mut self,
+        symbol: &str,
+        changes: &InstrumentConfig,
+    ) -> Result<Instrument, String> {
+        let instrument = self
+            .instruments
+            .iter_mut()
+            .find(|instrument| instrument.symbol == symbol)
+            .ok_or_else(|| format!("{} is not listed", symbol))?;
+        let mut updated = instrument.clone();
+        updated.apply(changes);
+        updated.validate()?;
+        *instrument = updated.clone();
+        self.version += 1;
+        Ok(updated)
+    }
+
+    /// Removes a listed instrument and returns it.
+    pub fn delist(&mut self, symbol: &str) -> Result<Instrument, String> {
+        let position = self
+            .instruments
+            .iter()
+            .position(|instrument| instrument.symbol == symbol)
+            .ok_or_else(|| format!("{} is not listed", symbol))?;
+        self.version += 1;
+        Ok(self.instruments.remove(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_registry_from_config() {
+        let mut config = MarketConfig {
+            instruments: HashMap::from([
+                (
+                    "AAPL".to_string(),
+                    InstrumentConfig {
+                        lot_size: Some(1),
+                        ..InstrumentConfig::default()
+                    },
+                ),
+                (
+                    "SPY".to_string(),
+                    InstrumentConfig {
+                        name: Some("SPDR S&P 500 ETF".to_string()),
+                        exchange: Some("NYSE".to_string()),
+                        ..InstrumentConfig::default()
+                    },
+                ),
+            ]),
+            ..MarketConfig::default()
+        };
+        let registry = TickerRegistry::from_config(&config).unwrap();
+        assert_eq!(registry.symbols().len(), TICKERS.len() + 1);
+        assert_eq!(registry.symbols().last().unwrap(), "SPY");
+        let apple = registry.get("AAPL").unwrap();
+        assert_eq!((apple.name.as_str(), apple.lot_size), ("Apple Inc.", 1));
+        let spy = registry.get("SPY").unwrap();
+        assert_eq!(spy.exchange, "NYSE");
+        assert_eq!((spy.tick_size.to_f64(), spy.decimals()), (0.01, 2));
+
+        config.instruments.insert(
+            "BAD".to_string(),
+            InstrumentConfig {
+                tick_size: Some(0.0),
+                ..InstrumentConfig::default()
+            },
+        );
+        assert!(TickerRegistry::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_add_modify_and_delist() {
+        let mut registry = TickerRegistry::new();
+        assert!(registry.add(Instrument::builtin("AAPL")).is_err());
+        assert!(registry.add(Instrument::builtin("lower")).is_err());
+        registry.add(Instrument::builtin("SPY")).unwrap();
+        assert!(registry.contains("SPY"));
+
+        let modified = registry
+            .modify(
+                "SPY",
+                &InstrumentConfig {
+                    tick_size: Some(0.05),
+                    ..InstrumentConfig::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(modified.tick_size.to_f64(), 0.05);
+        assert_eq!(modified.round(10.07).unwrap().to_f64(), 10.05);
+        assert!(registry
+            .modify(
+                "SPY",
+                &InstrumentConfig {
+                    lot_size: Some(0),
+                    ..InstrumentConfig::default()
+                },
+            )
+            .is_err());
+        assert_eq!(registry.get("SPY").unwrap().lot_size, 100);
+
+        assert_eq!(registry.delist("SPY").unwrap().symbol, "SPY");
+        assert!(!registry.contains("SPY"));
+        assert!(registry.delist("SPY").is_err());
+        assert_eq!(registry.version(), 3);
+    }
+}
@@ -0,0 +1,367 @@
+use super::{MarketFeed, PriceUpdate, SessionChange};
+use crate::config::{ReplayConfig, ReplayEnd};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+/// Shortest pause before a looping file starts again, so a file replayed at
+/// a very high speed cannot republish itself in a busy loop.
+const MIN_LOOP_GAP: Duration = Duration::from_millis(10);
+
+/// One row of a recorded tick file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RecordedTick {
+    /// Unix time in seconds.
+    pub timestamp: f64,
+    pub price: f64,
+    #[serde(default)]
+    pub volume: Option<u64>,
+}
+
+/// Loads a tick file, choosing JSONL or CSV by extension, and checks that it
+/// is non-empty, time-ordered and holds finite prices.
+pub fn load_ticks(path: &Path) -> Result<Vec<RecordedTick>, String> {
+    let is_jsonl = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("jsonl"))
+        .unwrap_or(false);
+    let ticks = if is_jsonl {
+        load_jsonl(path)?
+    } else {
+        load_csv(path)?
+    };
+
+    if ticks.is_empty() {
+        return Err(format!("No ticks found in {}", path.display()));
+    }
+    for (i, tick) in ticks.iter().enumerate() {
+        if !tick.timestamp.is_finite() || !tick.price.is_finite() {
+            return Err(format!(
+                "Non-finite value in {} at tick {}",
+                path.display(),
+                i + 1
+            ));
+        }
+        if i > 0 && tick.timestamp < ticks[i - 1].timestamp {
+            return Err(format!(
+                "Timestamps in {} go backwards at tick {}",
+                path.display(),
+                i + 1
+            ));
+        }
+    }
+    Ok(ticks)
+}
+
+fn load_csv(path: &Path) -> Result<Vec<RecordedTick>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    reader
+        .deserialize()
+        .enumerate()
+        .map(|(i, row)| {
+            row.map_err(|e| format!("Invalid row {} in {}: {}", i + 1, path.display(), e))
+        })
+        .collect()
+}
+
+fn load_jsonl(path: &Path) -> Result<Vec<RecordedTick>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut ticks = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let tick = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid line {} in {}: {}", i + 1, path.display(), e))?;
+        ticks.push(tick);
+    }
+    Ok(ticks)
+}
+
+/// Loads every configured tick file and starts replaying them into `feed`.
+///
+/// All files are loaded before any replay starts, so a bad file fails
+/// startup instead of leaving a partially replayed market.
+pub fn start_replay(
+    config: &ReplayConfig,
+    feed: &MarketFeed,
+) -> Result<Vec<JoinHandle<()>>, String> {
+    if !(config.speed.is_finite() && config.speed > 0.0) {
+        return Err(format!(
+            "market.replay.speed must be positive, got {}",
+            config.speed
+        ));
+    }
+    if config.files.is_empty() {
+        return Err("market.replay.files must list at least one ticker".to_string());
+    }
+    if !feed.corporate_actions(None).is_empty() {
+        return Err("market.corporate_actions cannot be applied to replayed prices".to_string());
+    }
+
+    let mut loaded = Vec::with_capacity(config.files.len());
+    for (ticker, path) in &config.files {
+        if !feed.is_listed(ticker) {
+            return Err(format!("Unknown ticker in replay config: {}", ticker));
+        }
+        let ticks = load_ticks(Path::new(path))?;
+        // Every tick of such a file would go out at once on every pass.
+        if config.on_end == ReplayEnd::Loop
+            && ticks.len() > 1
+            && ticks[ticks.len() - 1].timestamp == ticks[0].timestamp
+        {
+            return Err(format!(
+                "{} cannot loop: every tick in {} has the same timestamp",
+                ticker, path
+            ));
+        }
+        loaded.push((ticker.clone(), ticks));
+    }
+
+    Ok(loaded
+        .into_iter()
+        .map(|(ticker, ticks)| {
+            spawn_replay(ticker, ticks, feed.clone(), config.speed, config.on_end)
+        })
+        .collect())
+}
+
+/// Spawns a task that publishes `ticks` for `ticker` to `feed`, sleeping the
+/// recorded gap between consecutive ticks divided by `speed`.
+///
+/// With [`ReplayEnd::Loop`] the file restarts after a pause equal to its
+/// average tick spacing, so the wrap-around looks like any other gap.
+///
+/// Like the simulated market, a replay stands still outside trading hours
+/// and picks up where it left off once the market opens.
+pub fn spawn_replay(
+    ticker: String,
+    ticks: Vec<RecordedTick>,
+    feed: MarketFeed,
+    speed: f64,
+    on_end: ReplayEnd,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let scaled = |seconds: f64| Duration::from_secs_f64((seconds / speed).max(0.0));
+        let span = ticks[ticks.len() - 1].timestamp - ticks[0].timestamp;
+        let loop_gap = if ticks.len() > 1 {
+            scaled(span / (ticks.len() - 1) as f64)
+        } else {
+            scaled(1.0)
+        }
+        .max(MIN_LOOP_GAP);
+        let mut sessions = feed.subscribe_sessions();
+
+        println!(
+            "Replaying {} ticks for {} at {}x speed",
+            ticks.len(),
+            ticker,
+            speed
+        );
+        loop {
+            for (i, recorded) in ticks.iter().enumerate() {
+                if i > 0 {
+                    sleep(scaled(recorded.timestamp - ticks[i - 1].timestamp)).await;
+                }
+                if !wait_for_open(&feed, &mut sessions).await {
+                    return;
+                }
+                feed.publish(vec![PriceUpdate {
+                    ticker: ticker.clone(),
+                    price: recorded.price,
+                    volume: recorded.volume,
+                }])
+                .await;
+            }
+
+            match on_end {
+                ReplayEnd::Loop => sleep(loop_gap).await,
+                ReplayEnd::Stop => {
+                    println!("Replay for {} reached end of data", ticker);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Waits until the market is open; `false` when the feed has shut down.
+async fn wait_for_open(
+    feed: &MarketFeed,
+    sessions: &mut broadcast::Receiver<SessionChange>,
+) -> bool {
+    while !feed.session().is_open() {
+        if let Err(broadcast::error::RecvError::Closed) = sessions.recv().await {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::MarketSession;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::time::SystemTime;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_csv_and_jsonl() {
+        let dir = tempdir().unwrap();
+        let csv_path = dir.path().join("aapl.csv");
+        fs::write(
+            &csv_path,
+            "timestamp,price,volume\n1700000000,190.5,100\n1700000000.5,190.75,\n",
+        )
+        .unwrap();
+        let jsonl_path = dir.path().join("aapl.jsonl");
+        fs::write(
+            &jsonl_path,
+            "{\"timestamp\": 1700000000, \"price\": 190.5, \"volume\": 100}\n\n{\"timestamp\": 1700000000.5, \"price\": 190.75}\n",
+        )
+        .unwrap();
+
+        let expected = vec![
+            RecordedTick {
+                timestamp: 1_700_000_000.0,
+                price: 190.5,
+                volume: Some(100),
+            },
+            RecordedTick {
+                timestamp: 1_700_000_000.5,
+                price: 190.75,
+                volume: None,
+            },
+        ];
+        assert_eq!(load_ticks(&csv_path).unwrap(), expected);
+        assert_eq!(load_ticks(&jsonl_path).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_load_rejects_unordered_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bad.csv");
+        fs::write(&path, "timestamp,price\n2,10.0\n1,11.0\n").unwrap();
+        assert!(load_ticks(&path).is_err());
+        assert!(load_ticks(&dir.path().join("missing.csv")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_respects_gaps_and_stops() {
+        let feed = MarketFeed::new();
+        let mut receiver = feed.subscribe();
+        let ticks = vec![
+            RecordedTick {
+                timestamp: 0.0,
+                price: 10.0,
+                volume: None,
+            },
+            RecordedTick {
+                timestamp: 2.0,
+                price: 11.0,
+                volume: Some(5),
+            },
+        ];
+        let started = tokio::time::Instant::now();
+        let replay = spawn_replay(
+            "AAPL".to_string(),
+            ticks,
+            feed.clone(),
+            100.0,
+            ReplayEnd::Stop,
+        );
+
+        assert_eq!(receiver.recv().await.unwrap().price.to_f64(), 10.0);
+        let second = receiver.recv().await.unwrap();
+        assert_eq!((second.price.to_f64(), second.volume), (11.0, 5));
+        // Two recorded seconds at 100x speed take about 20ms.
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        replay.await.unwrap();
+        assert_eq!(feed.latest("AAPL").await.unwrap().price.to_f64(), 11.0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_loops() {
+        let feed = MarketFeed::new();
+        let mut receiver = feed.subscribe();
+        let ticks = vec![
+            RecordedTick {
+                timestamp: 0.0,
+                price: 10.0,
+                volume: None,
+            },
+            RecordedTick {
+                timestamp: 0.001,
+                price: 11.0,
+                volume: None,
+            },
+        ];
+        let replay = spawn_replay("AAPL".to_string(), ticks, feed, 1.0, ReplayEnd::Loop);
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(receiver.recv().await.unwrap().price.to_f64());
+        }
+        replay.abort();
+        assert_eq!(seen, vec![10.0, 11.0, 10.0, 11.0]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_cannot_loop_without_a_time_span() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("aapl.csv");
+        fs::write(&path, "timestamp,price\n5,10.0\n5,11.0\n").unwrap();
+        let mut config = ReplayConfig {
+            speed: 1.0,
+            on_end: ReplayEnd::Loop,
+            files: HashMap::from([("AAPL".to_string(), path.display().to_string())]),
+        };
+        let feed = MarketFeed::new();
+        assert!(start_replay(&config, &feed)
+            .unwrap_err()
+            .contains("same timestamp"));
+
+        config.on_end = ReplayEnd::Stop;
+        let mut receiver = feed.subscribe();
+        start_replay(&config, &feed).unwrap();
+        assert_eq!(receiver.recv().await.unwrap().price.to_f64(), 10.0);
+        assert_eq!(receiver.recv().await.unwrap().price.to_f64(), 11.0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_waits_for_the_market_to_open() {
+        let feed = MarketFeed::new();
+        feed.change_session(MarketSession::Closed, SystemTime::now())
+            .await;
+        let mut receiver = feed.subscribe();
+        let ticks = vec![RecordedTick {
+            timestamp: 0.0,
+            price: 10.0,
+            volume: None,
+        }];
+        let replay = spawn_replay(
+            "AAPL".to_string(),
+            ticks,
+            feed.clone(),
+            1.0,
+            ReplayEnd::Stop,
+        );
+
+        sleep(Duration::from_millis(50)).await;
+        assert!(receiver.try_recv().is_err());
+        feed.change_session(MarketSession::Regular, SystemTime::now())
+            .await;
+        assert_eq!(receiver.recv().await.unwrap().price.to_f64(), 10.0);
+        replay.await.unwrap();
+    }
+}
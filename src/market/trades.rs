@@ -0,0 +1,137 @@
+use super::{Quote, Side};
+use crate::price::Price;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// One print on the time-and-sales tape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    /// Unique across tickers, starting from 1.
+    pub id: u64,
+    pub ticker: String,
+    pub price: Price,
+    pub size: u64,
+    /// The side that crossed the spread to trade.
+    pub aggressor: Side,
+    pub timestamp: SystemTime,
+}
+
+/// Prints a trade for every published price, so the tape and the traded
+/// volume follow the price model.
+///
+/// The aggressor comes from the tick test: a price above the ticker's last
+/// trade was a buyer lifting the offer, one below a seller hitting the bid,
+/// and an unchanged price keeps the last trade's aggressor. Unless the source
+/// recorded a volume, the size is drawn from what the quote shows on the side
+/// the aggressor took.
+pub struct TradeGenerator {
+    rng: ChaCha8Rng,
+    last_trades: HashMap<String, (Price, Side)>,
+    next_id: u64,
+}
+
+impl Default for TradeGenerator {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl TradeGenerator {
+    /// Sizes are drawn from their own ChaCha8 stream, apart from both the
+    /// price paths and the quotes produced from the same seed.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(2);
+        TradeGenerator {
+            rng,
+            last_trades: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Forgets `ticker`'s last trade, so a jump that was not a market move
+    /// does not decide the next aggressor.
+    pub fn reset(&mut self, ticker: &str) {
+        self.last_trades.remove(ticker);
+    }
+
+    /// Prints `ticker` trading at `price`, inside `quote`, at `timestamp`;
+    /// `volume` is the size when the source recorded one.
+    pub fn print(
+        &mut self,
+        ticker: &str,
+        price: Price,
+        quote: &Quote,
+        volume: Option<u64>,
+        timestamp: SystemTime,
+    ) -> Trade {
+        let aggressor = match self.last_trades.get(ticker) {
+            Some(&(last, _)) if price > last => Side::Buy,
+            Some(&(last, _)) if price < last => Side::Sell,
+            Some(&(_, side)) => side,
+            None if self.rng.gen_bool(0.5) => Side::Buy,
+            None => Side::Sell,
+        };
+        let shown = match aggressor {
+            Side::Buy => quote.ask_size,
+            Side::Sell => quote.bid_size,
+        };
+        let size = volume.unwrap_or_else(|| self.rng.gen_range(1..=shown.max(1)));
+        self.last_trades
+            .insert(ticker.to_string(), (price, aggressor));
+        let id = self.next_id;
+        self.next_id += 1;
+        Trade {
+            id,
+            ticker: ticker.to_string(),
+            price,
+            size,
+            aggressor,
+            timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+
+    #[test]
+    fn test_tick_test_aggressor_and_sizes() {
+        let quote = Quote {
+            bid: price(99.99),
+            ask: price(100.01),
+            bid_size: 40,
+            ask_size: 60,
+            last: price(100.0),
+        };
+        let now = SystemTime::now();
+        let mut generator = TradeGenerator::new(7);
+        let first = generator.print("AAPL", price(100.0), &quote, None, now);
+        assert_eq!(first.id, 1);
+
+        let up = generator.print("AAPL", price(100.5), &quote, None, now);
+        assert_eq!(up.aggressor, Side::Buy);
+        assert!((1..=60).contains(&up.size));
+        let unchanged = generator.print("AAPL", price(100.5), &quote, Some(7), now);
+        assert_eq!((unchanged.aggressor, unchanged.size), (Side::Buy, 7));
+        let down = generator.print("AAPL", price(99.0), &quote, None, now);
+        assert_eq!(down.aggressor, Side::Sell);
+        assert!((1..=40).contains(&down.size));
+        assert_eq!(down.id, 4);
+
+        // Every ticker keeps its own last trade.
+        let other = generator.print("MSFT", price(10.0), &quote, Some(1), now);
+        generator.print("MSFT", price(9.0), &quote, Some(1), now);
+        assert_eq!(other.id, 5);
+        assert_eq!(
+            generator
+                .print("AAPL", price(99.0), &quote, Some(1), now)
+                .aggressor,
+            Side::Sell
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Fixed-point prices.
+
+use std::fmt;
+
+/// Billionths of a currency unit in one unit.
+pub const NANOS_PER_UNIT: i64 = 1_000_000_000;
+
+/// Decimal places every price is shown with at least, as for cents.
+pub const MIN_DECIMALS: u32 = 2;
+
+/// A price as a whole number of billionths of a currency unit.
+///
+/// Sums, differences and comparisons are exact, and rounding to an
+/// instrument's tick size with [`round_to_tick`](Self::round_to_tick) gives a
+/// value with no stray digits, however it was computed. Arithmetic is
+/// checked: a result outside the representable range is `None`, as for
+/// [`from_f64`](Self::from_f64).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Price(i64);
+
+impl Price {
+    pub const ZERO: Price = Price(0);
+
+    pub const fn from_nanos(nanos: i64) -> Self {
+        Price(nanos)
+    }
+
+    pub const fn as_nanos(self) -> i64 {
+        self.0
+    }
+
+    /// The nearest price to `value`, or `None` when it is not finite or too
+    /// large to represent.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        let nanos = (value * NANOS_PER_UNIT as f64).round();
+        (nanos.is_finite() && nanos.abs() < i64::MAX as f64).then_some(Price(nanos as i64))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / NANOS_PER_UNIT as f64
+    }
+
+    /// Builds a price from whole units and billionths, which must not have
+    /// opposite signs, as in `google.type.Money`.
+    pub fn from_units_and_nanos(units: i64, nanos: i32) -> Result<Self, String> {
+        if nanos.unsigned_abs() >= NANOS_PER_UNIT as u32 {
+            return Err(format!("nanos must be within ±999999999, got {}", nanos));
+        }
+        if (units > 0 && nanos < 0) || (units < 0 && nanos > 0) {
+            return Err(format!(
+                "units and nanos must have the same sign, got {} and {}",
+                units, nanos
+            ));
+        }
+        units
+            .checked_mul(NANOS_PER_UNIT)
+            .and_then(|whole| whole.checked_add(nanos as i64))
+            .map(Price)
+            .ok_or_else(|| format!("{} units is out of range", units))
+    }
+
+    /// Whole units, truncated toward zero.
+    pub fn units(self) -> i64 {
+        self.0 / NANOS_PER_UNIT
+    }
+
+    /// The fractional part in billionths, with the same sign as the price.
+    pub fn subunit_nanos(self) -> i32 {
+        (self.0 % NANOS_PER_UNIT) as i32
+    }
+
+    /// `self + other`, or `None` when the sum is out of range.
+    pub fn checked_add(self, other: Price) -> Option<Price> {
+        self.0.checked_add(other.0).map(Price)
+    }
+
+    /// `self - other`, or `None` when the difference is out of range.
+    pub fn checked_sub(self, other: Price) -> Option<Price> {
+        self.0.checked_sub(other.0).map(Price)
+    }
+
+    /// The nearest multiple of `tick`, rounding halves away from zero, or
+    /// `None` when that is out of range. A non-positive tick leaves the price
+    /// unchanged.
+    pub fn round_to_tick(self, tick: Price) -> Option<Price> {
+        if tick.0 <= 0 {
+            return Some(self);
+        }
+        let remainder = self.0 % tick.0;
+        let down = Price(self.0 - remainder);
+        if remainder.unsigned_abs() * 2 >= tick.0 as u64 {
+            down.checked_add(Price(remainder.signum() * tick.0))
+        } else {
+            Some(down)
+        }
+    }
+
+    /// The largest multiple of `tick` at or below the price, or `None` when
+    /// that is out of range.
+    pub fn floor_to_tick(self, tick: Price) -> Option<Price> {
+        if tick.0 <= 0 {
+            return Some(self);
+        }
+        self.0.div_euclid(tick.0).checked_mul(tick.0).map(Price)
+    }
+
+    /// The smallest multiple of `tick` at or above the price, or `None` when
+    /// that is out of range.
+    pub fn ceil_to_tick(self, tick: Price) -> Option<Price> {
+        let floor = self.floor_to_tick(tick)?;
+        if floor == self {
+            Some(floor)
+        } else {
+            floor.checked_add(tick)
+        }
+    }
+
+    /// The price times `factor`, to the nearest billionth, or unchanged when
+    /// the product cannot be represented.
+    pub fn scaled(self, factor: f64) -> Price {
+        Price::from_f64(self.to_f64() * factor).unwrap_or(self)
+    }
+
+    /// The price with exactly `decimals` decimal places, rounding halves away
+    /// from zero.
+    pub fn format(self, decimals: u32) -> String {
+        let decimals = decimals.min(9);
+        // Only a price within a step of the range's end cannot be rounded.
+        let rounded = self
+            .round_to_tick(Price(10_i64.pow(9 - decimals)))
+            .unwrap_or(self);
+        let sign = if rounded.0 < 0 { "-" } else { "" };
+        let units = rounded.units().unsigned_abs();
+        if decimals == 0 {
+            return format!("{}{}", sign, units);
+        }
+        let fraction = rounded.subunit_nanos().unsigned_abs() / 10_u32.pow(9 - decimals);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            units,
+            fraction,
+            width = decimals as usize
+        )
+    }
+}
+
+/// Decimal places needed to show every multiple of `tick` exactly, and never
+/// fewer than two.
+pub fn decimals_for_tick(tick: Price) -> u32 {
+    let mut nanos = tick.as_nanos();
+    if nanos == 0 {
+        return MIN_DECIMALS;
+    }
+    let mut decimals = 9;
+    while decimals > MIN_DECIMALS && nanos % 10 == 0 {
+        nanos /= 10;
+        decimals -= 1;
+    }
+    decimals.max(MIN_DECIMALS)
+}
+
+impl fmt::Display for Price {
+    /// As many decimals as the price needs, and at least two.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(decimals_for_tick(*self)))
+    }
+}
+
+/// `value` as a price, for tests.
+#[cfg(test)]
+pub(crate) fn price(value: f64) -> Price {
+    Price::from_f64(value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_to_ticks() {
+        let cent = price(0.01);
+        let nickel = price(0.05);
+        assert_eq!(price(123.456789).round_to_tick(cent), Some(price(123.46)));
+        assert_eq!(price(123.455).round_to_tick(cent), Some(price(123.46)));
+        assert_eq!(price(-1.005).round_to_tick(cent), Some(price(-1.01)));
+        assert_eq!(price(10.07).round_to_tick(nickel), Some(price(10.05)));
+        assert_eq!(price(10.01).floor_to_tick(nickel), Some(price(10.0)));
+        assert_eq!(price(10.01).ceil_to_tick(nickel), Some(price(10.05)));
+        assert_eq!(price(10.05).ceil_to_tick(nickel), Some(price(10.05)));
+        // Ten thousand additions of a cent land exactly on 100.
+        let total = (0..10_000).try_fold(Price::ZERO, |sum, _| sum.checked_add(cent));
+        assert_eq!(total, Some(price(100.0)));
+
+        // Nothing near the end of the range wraps around.
+        let top = Price::from_nanos(i64::MAX - 1);
+        assert_eq!(top.checked_add(cent), None);
+        assert_eq!(Price::from_nanos(i64::MIN).checked_sub(cent), None);
+        assert_eq!(top.round_to_tick(price(1.0)), None);
+        assert_eq!(top.ceil_to_tick(cent), None);
+        assert_eq!(top.floor_to_tick(cent).map(|floor| floor < top), Some(true));
+        assert!(!top.format(2).starts_with('-'));
+    }
+
+    #[test]
+    fn test_units_nanos_and_formatting() {
+        let p = Price::from_units_and_nanos(-12, -500_000_000).unwrap();
+        assert_eq!((p.units(), p.subunit_nanos()), (-12, -500_000_000));
+        assert_eq!(p.to_f64(), -12.5);
+        assert!(Price::from_units_and_nanos(1, -1).is_err());
+        assert!(Price::from_units_and_nanos(0, 1_000_000_000).is_err());
+        assert!(Price::from_f64(f64::NAN).is_none());
+
+        assert_eq!(price(150.5).format(2), "150.50");
+        assert_eq!(price(0.12345).format(4), "0.1235");
+        assert_eq!(price(-0.004).format(2), "0.00");
+        assert_eq!(price(99.999).format(0), "100");
+        assert_eq!(price(150.5).to_string(), "150.50");
+        assert_eq!(price(0.0001).to_string(), "0.0001");
+        assert_eq!(decimals_for_tick(price(0.05)), 2);
+        assert_eq!(decimals_for_tick(price(0.0001)), 4);
+        assert_eq!(decimals_for_tick(price(1.0)), 2);
+        assert_eq!(Price::ZERO.to_string(), "0.00");
+    }
+}
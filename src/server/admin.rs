@@ -0,0 +1,337 @@
+use super::handlers::{corporate_action_message, instrument_message, parse_decimal};
+use super::service::StockServiceImpl;
+use crate::config::InstrumentConfig;
+use crate::finance::{
+    schedule_corporate_action_request, AddInstrumentRequest, CorporateAction,
+    DelistInstrumentRequest, Instrument, ModifyInstrumentRequest, ScheduleCorporateActionRequest,
+};
+use crate::market::{self, default_ticker_config};
+use std::time::SystemTime;
+use tonic::{Request, Response, Status};
+
+/// Keeps only the fields a client actually filled in.
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+impl StockServiceImpl {
+    pub(crate) async fn handle_add_instrument(
+        &self,
+        request: Request<AddInstrumentRequest>,
+    ) -> Result<Response<Instrument>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let requested = req
+            .instrument
+            .ok_or_else(|| Status::invalid_argument("instrument is required"))?;
+        let symbol = requested.symbol.to_uppercase();
+        println!("Received request to list {} from {}", symbol, remote_addr);
+
+        let mut instrument = market::Instrument::builtin(&symbol);
+        instrument.apply(&InstrumentConfig {
+            name: non_empty(requested.name),
+            exchange: non_empty(requested.exchange),
+            currency: non_empty(requested.currency),
+            sector: non_empty(requested.sector),
+            tick_size: (requested.tick_size != 0.0).then_some(requested.tick_size),
+            lot_size: (requested.lot_size != 0).then_some(requested.lot_size),
+        });
+        if let Some(exact) = &requested.exact_tick_size {
+            instrument.tick_size = parse_decimal(exact)
+                .map_err(|e| Status::invalid_argument(format!("Invalid exact_tick_size: {}", e)))?;
+        }
+        let price = if req.initial_price == 0.0 {
+            default_ticker_config(&symbol).initial_price
+        } else {
+            req.initial_price
+        };
+        if !(price.is_finite() && price > 0.0) {
+            return Err(Status::invalid_argument(format!(
+                "initial_price must be positive, got {}",
+                price
+            )));
+        }
+        // Anything the price type cannot hold would never be published.
+        if instrument.round(price).is_none() {
+            return Err(Status::invalid_argument(format!(
+                "initial_price {} is out of range",
+                price
+            )));
+        }
+
+        let listed = instrument_message(&instrument);
+        self.feed.list(instrument, price).await.map_err(|e| {
+            if self.feed.is_listed(&symbol) {
+                Status::already_exists(e)
+            } else if self.feed.halt(&symbol).is_some() {
+                Status::failed_precondition(e)
+            } else {
+                Status::invalid_argument(e)
+            }
+        })?;
+        println!("Listed {} at ${:.2}", symbol, price);
+        Ok(Response::new(listed))
+    }
+
+    pub(crate) async fn handle_modify_instrument(
+        &self,
+        request: Request<ModifyInstrumentRequest>,
+    ) -> Result<Response<Instrument>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let symbol = req.symbol.to_uppercase();
+        println!("Received request to modify {} from {}", symbol, remote_addr);
+
+        if !self.feed.is_listed(&symbol) {
+            return Err(Status::not_found(format!("{} is not listed", symbol)));
+        }
+        let instrument = self
+            .feed
+            .modify_instrument(
+                &symbol,
+                &InstrumentConfig {
+                    name: req.name,
+                    exchange: req.exchange,
+                    currency: req.currency,
+                    sector: req.sector,
+                    tick_size: req.tick_size,
+                    lot_size: req.lot_size,
+                },
+            )
+            .map_err(Status::invalid_argument)?;
+        println!("Modified {}", symbol);
+        Ok(Response::new(instrument_message(&instrument)))
+    }
+
+    pub(crate) async fn handle_delist_instrument(
+        &self,
+        request: Request<DelistInstrumentRequest>,
+    ) -> Result<Response<Instrument>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let symbol = request.into_inner().symbol.to_uppercase();
+        println!("Received request to delist {} from {}", symbol, remote_addr);
+
+        let instrument = self.feed.delist(&symbol).map_err(Status::not_found)?;
+        println!("Delisted {}", symbol);
+        Ok(Response::new(instrument_message(&instrument)))
+    }
+
+    pub(crate) async fn handle_schedule_corporate_action(
+        &self,
+        request: Request<ScheduleCorporateActionRequest>,
+    ) -> Result<Response<CorporateAction>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
+        println!(
+            "Received request to schedule a corporate action on {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            return Err(Status::not_found(format!("{} is not listed", ticker)));
+        }
+        // Only the simulator's clock applies actions; replayed prices would
+        // also undo them on the next recorded tick.
+        if self.simulation.data_source == "replay" {
+            return Err(Status::failed_precondition(
+                "Corporate actions cannot be scheduled while replaying recorded prices",
+            ));
+        }
+        let kind = match req.kind {
+            Some(schedule_corporate_action_request::Kind::Split(split)) => {
+                market::CorporateActionKind::Split {
+                    new_shares: split.new_shares,
+                    old_shares: split.old_shares,
+                }
+            }
+            Some(schedule_corporate_action_request::Kind::Dividend(dividend)) => {
+                let amount = dividend
+                    .amount
+                    .as_ref()
+                    .ok_or_else(|| Status::invalid_argument("Dividend amount is required"))?;
+                market::CorporateActionKind::Dividend {
+                    amount: parse_decimal(amount).map_err(|e| {
+                        Status::invalid_argument(format!("Invalid dividend amount: {}", e))
+                    })?,
+                }
+            }
+            None => return Err(Status::invalid_argument("A split or dividend is required")),
+        };
+        let effective = match req.effective {
+            Some(effective) => SystemTime::try_from(effective)
+                .map_err(|e| Status::invalid_argument(format!("Invalid effective time: {}", e)))?,
+            None => SystemTime::now(),
+        };
+
+        let action = self
+            .feed
+            .schedule_corporate_action(&ticker, kind, effective)
+            .map_err(Status::invalid_argument)?;
+        println!("Scheduled {} on {}", kind.describe(), ticker);
+        Ok(Response::new(corporate_action_message(&action)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finance::{PriceRequest, TickerListRequest};
+
+    #[tokio::test]
+    async fn test_instruments_can_be_listed_modified_and_delisted() {
+        let service = StockServiceImpl::new();
+        let listed = service
+            .handle_add_instrument(Request::new(AddInstrumentRequest {
+                instrument: Some(Instrument {
+                    symbol: "spy".to_string(),
+                    name: "SPDR S&P 500 ETF".to_string(),
+                    exchange: "NYSE".to_string(),
+                    exact_tick_size: Some(crate::finance::Decimal {
+                        units: 0,
+                        nanos: 5_000_000,
+                    }),
+                    ..Default::default()
+                }),
+                initial_price: 500.004,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            (listed.symbol.as_str(), listed.currency.as_str()),
+            ("SPY", "USD")
+        );
+
+        let price = service
+            .handle_get_price(Request::new(PriceRequest {
+                ticker: "SPY".to_string(),
+                interval: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // Opened on the half-cent grid.
+        assert_eq!(price.price, 500.005);
+        assert_eq!(
+            price.exact_price,
+            Some(crate::finance::Decimal {
+                units: 500,
+                nanos: 5_000_000
+            })
+        );
+        assert_eq!(price.formatted_message, "Current price for SPY: $500.005\n");
+        let list = service
+            .handle_get_ticker_list(Request::new(TickerListRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(list.tickers.last().unwrap(), "SPY");
+        let spy = list.instruments.last().unwrap();
+        assert_eq!((spy.exchange.as_str(), spy.price_decimals), ("NYSE", 3));
+
+        let status = service
+            .handle_add_instrument(Request::new(AddInstrumentRequest {
+                instrument: Some(Instrument {
+                    symbol: "SPY".to_string(),
+                    ..Default::default()
+                }),
+                initial_price: 0.0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        let status = service
+            .handle_add_instrument(Request::new(AddInstrumentRequest {
+                instrument: Some(Instrument {
+                    symbol: "HUGE".to_string(),
+                    ..Default::default()
+                }),
+                initial_price: 1e300,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(!service.feed.is_listed("HUGE"));
+
+        let modified = service
+            .handle_modify_instrument(Request::new(ModifyInstrumentRequest {
+                symbol: "SPY".to_string(),
+                tick_size: Some(0.05),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            (modified.tick_size, modified.name.as_str()),
+            (0.05, "SPDR S&P 500 ETF")
+        );
+
+        service
+            .handle_delist_instrument(Request::new(DelistInstrumentRequest {
+                symbol: "SPY".to_string(),
+            }))
+            .await
+            .unwrap();
+        let status = service
+            .handle_get_price(Request::new(PriceRequest {
+                ticker: "SPY".to_string(),
+                interval: None,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let status = service
+            .handle_modify_instrument(Request::new(ModifyInstrumentRequest {
+                symbol: "SPY".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_corporate_actions_need_the_simulator() {
+        let mut service = StockServiceImpl::new();
+        let split = || {
+            Request::new(ScheduleCorporateActionRequest {
+                ticker: "aapl".to_string(),
+                kind: Some(schedule_corporate_action_request::Kind::Split(
+                    crate::finance::StockSplit {
+                        new_shares: 2,
+                        old_shares: 1,
+                    },
+                )),
+                effective: None,
+            })
+        };
+        let action = service
+            .handle_schedule_corporate_action(split())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(action.ticker, "AAPL");
+
+        service.simulation.data_source = "replay";
+        let status = service
+            .handle_schedule_corporate_action(split())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(service.feed.corporate_actions(None).len(), 1);
+    }
+}
@@ -0,0 +1,343 @@
+use super::handlers::decimal_message;
+use super::service::StockServiceImpl;
+use super::stream::ResponseStream;
+use crate::finance::{
+    depth_event, DepthAction, DepthEvent, DepthLevel, DepthRequest, DepthSnapshot, DepthUpdate,
+    LevelUpdate, OrderSide,
+};
+use crate::market::{self, DepthBook};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// One side of a book as `quantity @ $price` pairs, best first.
+fn format_side(levels: &[market::DepthLevel]) -> String {
+    if levels.is_empty() {
+        return "none".to_string();
+    }
+    levels
+        .iter()
+        .map(|level| format!("{} @ ${}", level.quantity, level.price))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The wire form of `snapshot`.
+fn snapshot_message(snapshot: &market::DepthSnapshot) -> DepthSnapshot {
+    let levels = |levels: &[market::DepthLevel]| {
+        levels
+            .iter()
+            .map(|level| DepthLevel {
+                price: Some(decimal_message(level.price)),
+                quantity: level.quantity,
+            })
+            .collect()
+    };
+    DepthSnapshot {
+        ticker: snapshot.ticker.clone(),
+        sequence: snapshot.sequence,
+        timestamp: Some(snapshot.timestamp.into()),
+        bids: levels(&snapshot.bids),
+        asks: levels(&snapshot.asks),
+        formatted_message: format!(
+            "{} depth as of update {}\n  Bids: {}\n  Asks: {}\n",
+            snapshot.ticker,
+            snapshot.sequence,
+            format_side(&snapshot.bids),
+            format_side(&snapshot.asks)
+        ),
+    }
+}
+
+/// The wire form of `update`, whose levels may be a subset of the change
+/// the book went through.
+fn update_message(update: &market::DepthUpdate) -> DepthUpdate {
+    let changes: Vec<String> = update
+        .levels
+        .iter()
+        .map(|level| {
+            let side = match level.side {
+                market::Side::Buy => "bid",
+                market::Side::Sell => "ask",
+            };
+            match level.action {
+                market::DepthAction::Add => {
+                    format!("{} {} @ ${} added", side, level.quantity, level.price)
+                }
+                market::DepthAction::Modify => {
+                    format!("{} ${} now {}", side, level.price, level.quantity)
+                }
+                market::DepthAction::Delete => format!("{} ${} deleted", side, level.price),
+            }
+        })
+        .collect();
+    let formatted_message = format!(
+        "{} depth update {}: {}\n",
+        update.ticker,
+        update.sequence,
+        if changes.is_empty() {
+            "no change to the streamed levels".to_string()
+        } else {
+            changes.join(", ")
+        }
+    );
+    DepthUpdate {
+        ticker: update.ticker.clone(),
+        sequence: update.sequence,
+        timestamp: Some(update.timestamp.into()),
+        levels: update
+            .levels
+            .iter()
+            .map(|level| {
+                let side = match level.side {
+                    market::Side::Buy => OrderSide::Buy,
+                    market::Side::Sell => OrderSide::Sell,
+                };
+                let action = match level.action {
+                    market::DepthAction::Add => DepthAction::Add,
+                    market::DepthAction::Modify => DepthAction::Modify,
+                    market::DepthAction::Delete => DepthAction::Delete,
+                };
+                LevelUpdate {
+                    side: side.into(),
+                    price: Some(decimal_message(level.price)),
+                    quantity: level.quantity,
+                    action: action.into(),
+                }
+            })
+            .collect(),
+        formatted_message,
+    }
+}
+
+impl StockServiceImpl {
+    pub(crate) async fn handle_get_depth_snapshot(
+        &self,
+        request: Request<DepthRequest>,
+    ) -> Result<Response<DepthSnapshot>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let request = request.into_inner();
+        let ticker = request.ticker.to_uppercase();
+        let levels = request.levels as usize;
+        println!(
+            "Received depth snapshot request for {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+
+        Ok(Response::new(snapshot_message(
+            &self.feed.depth_snapshot(&ticker, levels),
+        )))
+    }
+
+    /// Streams a snapshot of the requested levels followed by every change
+    /// to the book, each carrying the book's sequence number. Changes that
+    /// leave the requested levels as they were still go out, with no levels,
+    /// so the sequence never skips; a stream that falls behind the feed gets
+    /// a fresh snapshot instead of the updates it missed.
+    pub(crate) async fn handle_stream_depth(
+        &self,
+        request: Request<DepthRequest>,
+    ) -> Result<Response<ResponseStream<DepthEvent>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let request = request.into_inner();
+        let ticker = request.ticker.to_uppercase();
+        let levels = request.levels as usize;
+        println!(
+            "Received depth streaming request for {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+
+        let (tx, rx) = mpsc::channel(32);
+        let service_clone = self.clone();
+        // Subscribing before the snapshot is taken means no update after it
+        // can be missed; those it already includes are skipped below.
+        let mut updates = self.feed.subscribe_depth();
+        let open_stream = self.open_stream(&remote_addr);
+
+        tokio::spawn(async move {
+            let _open_stream = open_stream;
+            println!("Starting depth stream for ticker: {}", ticker);
+
+            let mut resync = true;
+            let mut book = DepthBook::default();
+            let mut view = DepthBook::default();
+            let mut sequence = 0;
+            loop {
+                let event = if resync {
+                    resync = false;
+                    let snapshot = service_clone.feed.depth_snapshot(&ticker, 0);
+                    book = DepthBook::from_snapshot(&snapshot);
+                    view = book.top(levels);
+                    sequence = snapshot.sequence;
+                    depth_event::Event::Snapshot(snapshot_message(&view.snapshot(
+                        &ticker,
+                        sequence,
+                        snapshot.timestamp,
+                    )))
+                } else {
+                    let received = tokio::select! {
+                        received = updates.recv() => received,
+                        // A quiet book may not change for a long time.
+                        _ = tx.closed() => {
+                            println!(
+                                "Client disconnected from depth stream for ticker: {}",
+                                ticker
+                            );
+                            break;
+                        }
+                    };
+                    match received {
+                        Ok(update) if update.ticker == ticker && update.sequence > sequence => {
+                            book.apply(&update.levels);
+                            let next = book.top(levels);
+                            let levels = view.changes_to(&next);
+                            view = next;
+                            sequence = update.sequence;
+                            depth_event::Event::Update(update_message(&market::DepthUpdate {
+                                levels,
+                                ..update
+                            }))
+                        }
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            println!(
+                                "depth stream for {} lagged, skipped {} market events; resending snapshot",
+                                ticker, skipped
+                            );
+                            resync = true;
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                };
+
+                let event = DepthEvent { event: Some(event) };
+                if tx.send(Ok(event)).await.is_err() {
+                    println!(
+                        "Client disconnected from depth stream for ticker: {}",
+                        ticker
+                    );
+                    break;
+                }
+
+                // Update last activity timestamp for the client
+                if let Ok(addr) = remote_addr.parse() {
+                    service_clone.update_last_activity(Some(addr)).await;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finance::{OrderType, SubmitOrderRequest, TimeInForce};
+    use crate::market::update;
+    use crate::price::Price;
+    use futures::StreamExt;
+
+    fn depth(ticker: &str, levels: u32) -> Request<DepthRequest> {
+        Request::new(DepthRequest {
+            ticker: ticker.to_string(),
+            levels,
+        })
+    }
+
+    async fn next_event(stream: &mut ResponseStream<DepthEvent>) -> depth_event::Event {
+        stream.next().await.unwrap().unwrap().event.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_depth_stream_follows_the_book() {
+        let service = StockServiceImpl::new();
+        service.feed.publish(vec![update("AAPL", 100.0)]).await;
+        let mut whole = service
+            .handle_stream_depth(depth("aapl", 0))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut top = service
+            .handle_stream_depth(depth("AAPL", 1))
+            .await
+            .unwrap()
+            .into_inner();
+        let depth_event::Event::Snapshot(snapshot) = next_event(&mut whole).await else {
+            panic!("the stream did not start with a snapshot");
+        };
+        assert_eq!((snapshot.bids.len(), snapshot.asks.len()), (5, 5));
+        let depth_event::Event::Snapshot(top_snapshot) = next_event(&mut top).await else {
+            panic!("the stream did not start with a snapshot");
+        };
+        assert_eq!((top_snapshot.bids.len(), top_snapshot.asks.len()), (1, 1));
+        assert_eq!(top_snapshot.sequence, snapshot.sequence);
+
+        // A bid well below the market adds a level to the whole book only.
+        let price = Some(decimal_message(Price::from_f64(90.0).unwrap()));
+        service
+            .handle_submit_order(Request::new(SubmitOrderRequest {
+                ticker: "AAPL".to_string(),
+                side: OrderSide::Buy.into(),
+                order_type: OrderType::Limit.into(),
+                quantity: 10,
+                limit_price: price.clone(),
+                time_in_force: TimeInForce::Day.into(),
+                client_order_id: String::new(),
+            }))
+            .await
+            .unwrap();
+        let depth_event::Event::Update(update) = next_event(&mut whole).await else {
+            panic!("expected an update");
+        };
+        assert_eq!(update.sequence, snapshot.sequence + 1);
+        assert_eq!(update.levels.len(), 1);
+        let level = &update.levels[0];
+        assert_eq!(
+            (level.side(), level.action(), &level.price, level.quantity),
+            (OrderSide::Buy, DepthAction::Add, &price, 10)
+        );
+        let depth_event::Event::Update(update) = next_event(&mut top).await else {
+            panic!("expected an update");
+        };
+        assert_eq!(update.sequence, snapshot.sequence + 1);
+        assert!(update.levels.is_empty());
+
+        let current = service
+            .handle_get_depth_snapshot(depth("AAPL", 0))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(current.sequence, update.sequence);
+        assert_eq!(current.bids.last().unwrap().price, price);
+        assert_eq!(
+            service
+                .handle_get_depth_snapshot(depth("NOPE", 0))
+                .await
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
@@ -1,10 +1,409 @@
 use super::service::StockServiceImpl;
 use crate::finance::{
-    MultiplePricesRequest, MultiplePricesResponse, PriceRequest, PriceResponse, StatsRequest,
-    StatsResponse, TickerListRequest, TickerListResponse,
+    corporate_action, indicator_spec, BetaRequest, BetaResponse, BollingerSpec, Candle,
+    CandleInterval, CandlesRequest, CandlesResponse, CashDividend, CorporateAction,
+    CorporateActionStatus, CorporateActionsRequest, CorporateActionsResponse,
+    CorrelationMatrixRequest, CorrelationMatrixResponse, CorrelationRow, Decimal, EmaSpec,
+    HaltReason, HaltsRequest, HaltsResponse, IndicatorSpec, IndicatorValue, IndicatorsRequest,
+    IndicatorsResponse, Instrument, MacdSpec, MarketDataUpdate, MarketSession, MarketStatusRequest,
+    MarketStatusResponse, MultiplePricesRequest, MultiplePricesResponse, PercentileValue,
+    PricePoint, PriceRequest, PriceResponse, Quote, QuoteRequest, RsiSpec, ServerInfoRequest,
+    ServerInfoResponse, SessionChange, SmaSpec, StatsRequest, StatsResponse, StdDevMode,
+    StockSplit, TickerListRequest, TickerListResponse, TradingHalt,
 };
+use crate::indicators::{self, Indicator};
+use crate::market::{self, Tick, TRADING_SECONDS_PER_YEAR};
+use crate::price::Price;
+use crate::stats::{
+    self, aligned_log_returns, correlation_matrix, observed_periods_per_year, summarize,
+    volume_weighted, StdDevKind, SummaryStats,
+};
+use std::time::SystemTime;
 use tonic::{Request, Response, Status};
 
+/// The wire form of `price`.
+pub(crate) fn decimal_message(price: Price) -> Decimal {
+    Decimal {
+        units: price.units(),
+        nanos: price.subunit_nanos(),
+    }
+}
+
+/// Reads a price sent as a `Decimal`.
+pub(crate) fn parse_decimal(decimal: &Decimal) -> Result<Price, String> {
+    Price::from_units_and_nanos(decimal.units, decimal.nanos)
+}
+
+/// The wire form of the quote carried by `tick`.
+pub(crate) fn quote_message(tick: &Tick) -> Quote {
+    Quote {
+        ticker: tick.ticker.clone(),
+        bid: tick.quote.bid.to_f64(),
+        ask: tick.quote.ask.to_f64(),
+        bid_size: tick.quote.bid_size,
+        ask_size: tick.quote.ask_size,
+        last: tick.quote.last.to_f64(),
+        timestamp: Some(tick.timestamp.into()),
+        sequence: tick.sequence,
+        formatted_message: crate::utils::format_quote(&tick.ticker, &tick.quote, tick.decimals()),
+        exact_bid: Some(decimal_message(tick.quote.bid)),
+        exact_ask: Some(decimal_message(tick.quote.ask)),
+        exact_last: Some(decimal_message(tick.quote.last)),
+    }
+}
+
+/// The wire form of `instrument`.
+pub(crate) fn instrument_message(instrument: &market::Instrument) -> Instrument {
+    Instrument {
+        symbol: instrument.symbol.clone(),
+        name: instrument.name.clone(),
+        exchange: instrument.exchange.clone(),
+        currency: instrument.currency.clone(),
+        sector: instrument.sector.clone(),
+        tick_size: instrument.tick_size.to_f64(),
+        lot_size: instrument.lot_size,
+        exact_tick_size: Some(decimal_message(instrument.tick_size)),
+        price_decimals: instrument.decimals(),
+    }
+}
+
+/// The wire form of `tick` in a merged multi-ticker stream.
+pub(crate) fn market_data_message(tick: &Tick) -> MarketDataUpdate {
+    MarketDataUpdate {
+        ticker: tick.ticker.clone(),
+        price: tick.price.to_f64(),
+        timestamp: Some(tick.timestamp.into()),
+        sequence: tick.sequence,
+        quote: Some(quote_message(tick)),
+        formatted_message: crate::utils::format_price(&tick.ticker, tick.price, tick.decimals()),
+        exact_price: Some(decimal_message(tick.price)),
+    }
+}
+
+pub(crate) fn session_message(session: market::MarketSession) -> MarketSession {
+    match session {
+        market::MarketSession::PreMarket => MarketSession::PreMarket,
+        market::MarketSession::Regular => MarketSession::Regular,
+        market::MarketSession::AfterHours => MarketSession::AfterHours,
+        market::MarketSession::Closed => MarketSession::Closed,
+    }
+}
+
+/// The wire form of `change`.
+pub(crate) fn session_change_message(change: &market::SessionChange) -> SessionChange {
+    SessionChange {
+        previous: session_message(change.previous).into(),
+        current: session_message(change.current).into(),
+        timestamp: Some(change.timestamp.into()),
+    }
+}
+
+/// The wire form of `halt`.
+pub(crate) fn halt_message(halt: &market::Halt) -> TradingHalt {
+    let (reason, circuit_breaker_level) = match halt.reason {
+        market::HaltReason::LimitUp => (HaltReason::LimitUp, 0),
+        market::HaltReason::LimitDown => (HaltReason::LimitDown, 0),
+        market::HaltReason::CircuitBreaker { level } => (HaltReason::CircuitBreaker, level),
+    };
+    TradingHalt {
+        ticker: halt.ticker.clone().unwrap_or_default(),
+        reason: reason.into(),
+        circuit_breaker_level,
+        move_percent: halt.move_pct,
+        start: Some(halt.start.into()),
+        end: Some(halt.end.into()),
+    }
+}
+
+/// One line describing `halt`.
+pub(crate) fn format_halt(halt: &market::Halt) -> String {
+    let remaining = halt
+        .end
+        .duration_since(SystemTime::now())
+        .unwrap_or_default();
+    format!(
+        "{} halted: {} after a {:+.2}% move, {}s remaining",
+        halt.ticker.as_deref().unwrap_or("Market"),
+        halt.reason.label(),
+        halt.move_pct,
+        remaining.as_secs()
+    )
+}
+
+/// The wire form of `action`.
+pub(crate) fn corporate_action_message(action: &market::CorporateAction) -> CorporateAction {
+    let kind = match action.kind {
+        market::CorporateActionKind::Split {
+            new_shares,
+            old_shares,
+        } => corporate_action::Kind::Split(StockSplit {
+            new_shares,
+            old_shares,
+        }),
+        market::CorporateActionKind::Dividend { amount } => {
+            corporate_action::Kind::Dividend(CashDividend {
+                amount: Some(decimal_message(amount)),
+            })
+        }
+    };
+    let mut message = CorporateAction {
+        id: action.id,
+        ticker: action.ticker.clone(),
+        kind: Some(kind),
+        effective: Some(action.effective.into()),
+        ..CorporateAction::default()
+    };
+    match &action.status {
+        market::ActionStatus::Pending => {
+            message.set_status(CorporateActionStatus::Pending);
+        }
+        market::ActionStatus::Applied {
+            timestamp,
+            adjustment,
+        } => {
+            message.set_status(CorporateActionStatus::Applied);
+            message.resolved_at = Some((*timestamp).into());
+            message.price_before = Some(decimal_message(adjustment.price_before));
+            message.price_after = Some(decimal_message(adjustment.price_after));
+            message.price_factor = adjustment.price_factor;
+        }
+        market::ActionStatus::Rejected { timestamp, reason } => {
+            message.set_status(CorporateActionStatus::Rejected);
+            message.resolved_at = Some((*timestamp).into());
+            message.rejection_reason = reason.clone();
+        }
+    }
+    message
+}
+
+/// One line describing `action` and its outcome.
+pub(crate) fn format_corporate_action(action: &market::CorporateAction) -> String {
+    let outcome = match &action.status {
+        market::ActionStatus::Pending => "pending".to_string(),
+        market::ActionStatus::Applied { adjustment, .. } => format!(
+            "applied, ${} -> ${}",
+            adjustment.price_before, adjustment.price_after
+        ),
+        market::ActionStatus::Rejected { reason, .. } => format!("rejected: {}", reason),
+    };
+    let effective = chrono::DateTime::<chrono::Utc>::from(action.effective)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    format!(
+        "#{} {} {} effective at {} ({})",
+        action.id,
+        action.ticker,
+        action.kind.describe(),
+        effective,
+        outcome
+    )
+}
+
+/// Reads a requested candle interval; the unspecified value is rejected.
+pub(crate) fn parse_candle_interval(raw: i32) -> Result<market::CandleInterval, String> {
+    match CandleInterval::try_from(raw) {
+        Ok(CandleInterval::OneSecond) => Ok(market::CandleInterval::OneSecond),
+        Ok(CandleInterval::OneMinute) => Ok(market::CandleInterval::OneMinute),
+        Ok(CandleInterval::FiveMinutes) => Ok(market::CandleInterval::FiveMinutes),
+        Ok(CandleInterval::OneHour) => Ok(market::CandleInterval::OneHour),
+        Ok(CandleInterval::Unspecified) | Err(_) => {
+            Err(format!("Invalid candle interval: {}", raw))
+        }
+    }
+}
+
+fn candle_interval_message(interval: market::CandleInterval) -> CandleInterval {
+    match interval {
+        market::CandleInterval::OneSecond => CandleInterval::OneSecond,
+        market::CandleInterval::OneMinute => CandleInterval::OneMinute,
+        market::CandleInterval::FiveMinutes => CandleInterval::FiveMinutes,
+        market::CandleInterval::OneHour => CandleInterval::OneHour,
+    }
+}
+
+/// The wire form of `candle`.
+pub(crate) fn candle_message(candle: &market::Candle) -> Candle {
+    Candle {
+        ticker: candle.ticker.clone(),
+        interval: candle_interval_message(candle.interval).into(),
+        start: Some(candle.start.into()),
+        end: Some(candle.end().into()),
+        open: candle.open.to_f64(),
+        high: candle.high.to_f64(),
+        low: candle.low.to_f64(),
+        close: candle.close.to_f64(),
+        volume: candle.volume,
+        tick_count: candle.tick_count,
+        complete: candle.complete,
+        exact_open: Some(decimal_message(candle.open)),
+        exact_high: Some(decimal_message(candle.high)),
+        exact_low: Some(decimal_message(candle.low)),
+        exact_close: Some(decimal_message(candle.close)),
+    }
+}
+
+/// Most indicators one request may ask for.
+const MAX_INDICATORS_PER_REQUEST: usize = 32;
+
+/// Reads a requested indicator, filling periods left at zero with the
+/// conventional defaults.
+fn parse_indicator_spec(spec: &IndicatorSpec) -> Result<indicators::IndicatorSpec, String> {
+    let or = |value: u32, default: usize| if value == 0 { default } else { value as usize };
+    let spec = match &spec.kind {
+        Some(indicator_spec::Kind::Sma(sma)) => indicators::IndicatorSpec::Sma {
+            period: or(sma.period, 20),
+        },
+        Some(indicator_spec::Kind::Ema(ema)) => indicators::IndicatorSpec::Ema {
+            period: or(ema.period, 20),
+        },
+        Some(indicator_spec::Kind::Rsi(rsi)) => indicators::IndicatorSpec::Rsi {
+            period: or(rsi.period, 14),
+        },
+        Some(indicator_spec::Kind::Macd(macd)) => indicators::IndicatorSpec::Macd {
+            fast: or(macd.fast_period, 12),
+            slow: or(macd.slow_period, 26),
+            signal: or(macd.signal_period, 9),
+        },
+        Some(indicator_spec::Kind::Bollinger(bollinger)) => indicators::IndicatorSpec::Bollinger {
+            period: or(bollinger.period, 20),
+            num_std_dev: if bollinger.num_std_dev == 0.0 {
+                2.0
+            } else {
+                bollinger.num_std_dev
+            },
+        },
+        None => return Err("Indicator spec must name an indicator".to_string()),
+    };
+    spec.validate()?;
+    Ok(spec)
+}
+
+fn indicator_spec_message(spec: indicators::IndicatorSpec) -> IndicatorSpec {
+    let kind = match spec {
+        indicators::IndicatorSpec::Sma { period } => indicator_spec::Kind::Sma(SmaSpec {
+            period: period as u32,
+        }),
+        indicators::IndicatorSpec::Ema { period } => indicator_spec::Kind::Ema(EmaSpec {
+            period: period as u32,
+        }),
+        indicators::IndicatorSpec::Rsi { period } => indicator_spec::Kind::Rsi(RsiSpec {
+            period: period as u32,
+        }),
+        indicators::IndicatorSpec::Macd { fast, slow, signal } => {
+            indicator_spec::Kind::Macd(MacdSpec {
+                fast_period: fast as u32,
+                slow_period: slow as u32,
+                signal_period: signal as u32,
+            })
+        }
+        indicators::IndicatorSpec::Bollinger {
+            period,
+            num_std_dev,
+        } => indicator_spec::Kind::Bollinger(BollingerSpec {
+            period: period as u32,
+            num_std_dev,
+        }),
+    };
+    IndicatorSpec { kind: Some(kind) }
+}
+
+/// Fresh indicators for the requested specs, in request order.
+pub(crate) fn new_indicators(specs: &[IndicatorSpec]) -> Result<Vec<Indicator>, String> {
+    if specs.is_empty() {
+        return Err("At least one indicator must be requested".to_string());
+    }
+    if specs.len() > MAX_INDICATORS_PER_REQUEST {
+        return Err(format!(
+            "At most {} indicators may be requested at once",
+            MAX_INDICATORS_PER_REQUEST
+        ));
+    }
+    specs
+        .iter()
+        .map(|spec| Indicator::new(parse_indicator_spec(spec)?))
+        .collect()
+}
+
+/// The wire form of `indicators` as of `tick`, the newest price they include.
+pub(crate) fn indicators_message(indicators: &[Indicator], tick: &Tick) -> IndicatorsResponse {
+    let decimals = tick.decimals() as usize;
+    let lines: Vec<String> = indicators
+        .iter()
+        .map(|indicator| {
+            let label = indicator.spec().label();
+            match (indicator.spec(), indicator.value()) {
+                (_, None) => format!("{}: warming up", label),
+                (indicators::IndicatorSpec::Macd { .. }, Some(value)) => format!(
+                    "{}: {:.4} signal {:.4} histogram {:.4}",
+                    label, value.value, value.signal, value.histogram
+                ),
+                (indicators::IndicatorSpec::Bollinger { .. }, Some(value)) => format!(
+                    "{}: ${:.*} [${:.*}, ${:.*}]",
+                    label, decimals, value.value, decimals, value.lower, decimals, value.upper
+                ),
+                (indicators::IndicatorSpec::Rsi { .. }, Some(value)) => {
+                    format!("{}: {:.2}", label, value.value)
+                }
+                (_, Some(value)) => format!("{}: ${:.*}", label, decimals, value.value),
+            }
+        })
+        .collect();
+    IndicatorsResponse {
+        ticker: tick.ticker.clone(),
+        values: indicators
+            .iter()
+            .map(|indicator| {
+                let value = indicator.value().unwrap_or_default();
+                IndicatorValue {
+                    spec: Some(indicator_spec_message(indicator.spec())),
+                    ready: indicator.value().is_some(),
+                    value: value.value,
+                    signal: value.signal,
+                    histogram: value.histogram,
+                    upper: value.upper,
+                    lower: value.lower,
+                }
+            })
+            .collect(),
+        price: tick.price.to_f64(),
+        timestamp: Some(tick.timestamp.into()),
+        sequence: tick.sequence,
+        formatted_message: format!(
+            "Indicators for {} at ${}:\n{}",
+            tick.ticker,
+            tick.price.format(tick.decimals()),
+            lines.join("\n")
+        ),
+        exact_price: Some(decimal_message(tick.price)),
+    }
+}
+
+/// Upper-cases `tickers` and checks each is in `listed` and named once.
+pub(crate) fn parse_tickers(tickers: &[String], listed: &[String]) -> Result<Vec<String>, String> {
+    let mut parsed: Vec<String> = Vec::with_capacity(tickers.len());
+    for ticker in tickers {
+        let ticker = ticker.to_uppercase();
+        if !listed.contains(&ticker) {
+            return Err(format!("Invalid ticker: {}", ticker));
+        }
+        if parsed.contains(&ticker) {
+            return Err(format!("Ticker listed more than once: {}", ticker));
+        }
+        parsed.push(ticker);
+    }
+    Ok(parsed)
+}
+
+fn parse_timestamp(
+    timestamp: Option<prost_types::Timestamp>,
+    field: &str,
+) -> Result<Option<SystemTime>, String> {
+    timestamp
+        .map(|timestamp| {
+            SystemTime::try_from(timestamp).map_err(|e| format!("Invalid {}: {}", field, e))
+        })
+        .transpose()
+}
+
 impl StockServiceImpl {
     pub(crate) async fn handle_get_ticker_list(
         &self,
@@ -12,14 +411,17 @@ impl StockServiceImpl {
     ) -> Result<Response<TickerListResponse>, Status> {
         let remote_addr = request
             .remote_addr()
-            .unwrap_or_else(|| "unknown".parse().unwrap());
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
         println!("Received ticker list request from {}", remote_addr);
 
+        let instruments = self.feed.instruments();
         let response = TickerListResponse {
-            tickers: crate::utils::TICKERS
+            tickers: instruments
                 .iter()
-                .map(|&s| s.to_string())
+                .map(|instrument| instrument.symbol.clone())
                 .collect(),
+            instruments: instruments.iter().map(instrument_message).collect(),
         };
 
         println!("Sending ticker list response: {:?}", response.tickers);
@@ -32,14 +434,15 @@ impl StockServiceImpl {
     ) -> Result<Response<PriceResponse>, Status> {
         let remote_addr = request
             .remote_addr()
-            .unwrap_or_else(|| "unknown".parse().unwrap());
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
         let ticker = request.into_inner().ticker.to_uppercase();
         println!(
             "Received price request for ticker: {} from {}",
             ticker, remote_addr
         );
 
-        if !crate::utils::TICKERS.contains(&ticker.as_str()) {
+        if !self.feed.is_listed(&ticker) {
             println!("Error: Invalid ticker requested: {}", ticker);
             return Err(Status::invalid_argument(format!(
                 "Invalid ticker: {}",
@@ -47,17 +450,52 @@ impl StockServiceImpl {
             )));
         }
 
-        let (_, price) = crate::utils::generate_random_ticker_and_price();
-        let formatted_message = crate::utils::format_price(&ticker, price);
-
-        let mut tracker = self.price_tracker.lock().await;
-        tracker.add_price(&ticker, price);
+        // While the market is closed the price is the last regular session's
+        // close, when one has been seen.
+        let session = self.feed.session();
+        let close = match session {
+            market::MarketSession::Closed => self.feed.last_close(&ticker).await,
+            _ => None,
+        };
+        let last_close = close.is_some();
+        let tick = match close {
+            Some(tick) => tick,
+            None => self
+                .feed
+                .latest(&ticker)
+                .await
+                .ok_or_else(|| Status::unavailable(format!("No price yet for {}", ticker)))?,
+        };
+        let mut formatted_message = if last_close {
+            format!(
+                "Last close for {}: ${}\n",
+                ticker,
+                tick.price.format(tick.decimals())
+            )
+        } else {
+            crate::utils::format_price(&ticker, tick.price, tick.decimals())
+        };
+        let halt = self.feed.halt(&ticker);
+        if let Some(halt) = &halt {
+            formatted_message.push_str(&format_halt(halt));
+            formatted_message.push('\n');
+        }
 
         println!("Sending price response: {}", formatted_message.trim());
         Ok(Response::new(PriceResponse {
             ticker,
-            price,
+            price: tick.price.to_f64(),
             formatted_message,
+            timestamp: Some(tick.timestamp.into()),
+            sequence: tick.sequence,
+            conflated: false,
+            skipped_updates: 0,
+            exact_price: Some(decimal_message(tick.price)),
+            session: session_message(session).into(),
+            last_close,
+            session_change: None,
+            halt: halt.as_ref().map(halt_message),
+            halt_ended: false,
         }))
     }
 
@@ -67,7 +505,8 @@ impl StockServiceImpl {
     ) -> Result<Response<MultiplePricesResponse>, Status> {
         let remote_addr = request
             .remote_addr()
-            .unwrap_or_else(|| "unknown".parse().unwrap());
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
         let req = request.into_inner();
         let ticker = req.ticker.to_uppercase();
         let count = req.count;
@@ -77,27 +516,43 @@ impl StockServiceImpl {
             ticker, count, remote_addr
         );
 
-        if !crate::utils::TICKERS.contains(&ticker.as_str()) {
+        if !self.feed.is_listed(&ticker) {
             return Err(Status::invalid_argument(format!(
                 "Invalid ticker: {}",
                 ticker
             )));
         }
 
-        let mut prices = Vec::with_capacity(count as usize);
-        let mut price_messages = Vec::with_capacity(count as usize);
-        let mut tracker = self.price_tracker.lock().await;
-
-        for i in 0..count {
-            let (_, price) = crate::utils::generate_random_ticker_and_price();
-            tracker.add_price(&ticker, price);
-            prices.push(price);
-            price_messages.push(format!("{}. Price for {}: ${:.2}", i + 1, ticker, price));
+        if count < 0 {
+            return Err(Status::invalid_argument(format!(
+                "Count must not be negative: {}",
+                count
+            )));
         }
 
+        // The most recent `count` market ticks, oldest first.
+        let points = self
+            .price_tracker
+            .lock()
+            .await
+            .last_points(&ticker, count as usize);
+        let decimals = self.feed.decimals(&ticker);
+        let price_messages: Vec<String> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                format!(
+                    "{}. Price for {}: ${}",
+                    i + 1,
+                    ticker,
+                    point.price.format(decimals)
+                )
+            })
+            .collect();
+
         let formatted_message = format!(
-            "Generated {} prices for {}:\n{}",
-            count,
+            "Last {} prices for {}:\n{}",
+            points.len(),
             ticker,
             price_messages.join("\n")
         );
@@ -105,8 +560,17 @@ impl StockServiceImpl {
 
         Ok(Response::new(MultiplePricesResponse {
             ticker,
-            prices,
+            prices: points.iter().map(|point| point.price.to_f64()).collect(),
             formatted_message,
+            points: points
+                .into_iter()
+                .map(|point| PricePoint {
+                    price: point.price.to_f64(),
+                    timestamp: Some(point.timestamp.into()),
+                    sequence: point.sequence,
+                    exact_price: Some(decimal_message(point.price)),
+                })
+                .collect(),
         }))
     }
 
@@ -116,38 +580,1226 @@ impl StockServiceImpl {
     ) -> Result<Response<StatsResponse>, Status> {
         let remote_addr = request
             .remote_addr()
-            .unwrap_or_else(|| "unknown".parse().unwrap());
-        let ticker = request.into_inner().ticker.to_uppercase();
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
         println!(
             "Received stats request for ticker: {} from {}",
             ticker, remote_addr
         );
 
-        if !crate::utils::TICKERS.contains(&ticker.as_str()) {
+        if !self.feed.is_listed(&ticker) {
             return Err(Status::invalid_argument(format!(
                 "Invalid ticker: {}",
                 ticker
             )));
         }
 
+        let window = req
+            .window
+            .map(std::time::Duration::try_from)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("Invalid window: {}", e)))?;
+        let since = window.and_then(|window| SystemTime::now().checked_sub(window));
+        let kind = match StdDevMode::try_from(req.std_dev_mode) {
+            Ok(StdDevMode::Population) => StdDevKind::Population,
+            Ok(StdDevMode::Sample) => StdDevKind::Sample,
+            Err(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid std_dev_mode: {}",
+                    req.std_dev_mode
+                )))
+            }
+        };
+
+        let periods_per_year = (self.simulation.data_source == "simulation")
+            .then(|| TRADING_SECONDS_PER_YEAR / self.simulation.time_step_secs);
         let tracker = self.price_tracker.lock().await;
-        let (prices, average, std_deviation) = tracker.get_stats(&ticker);
+        // The running totals are over raw prices, so adjusted statistics
+        // come from a pass over the adjusted ones.
+        let adjust = req.adjusted && tracker.has_adjustments(&ticker);
+        let adjusted = |point| {
+            if adjust {
+                tracker.adjusted(&ticker, point)
+            } else {
+                point
+            }
+        };
+        let scan =
+            req.include_distribution || !req.percentiles.is_empty() || window.is_some() || adjust;
+        let running = tracker.summary(&ticker, kind, periods_per_year);
+        // Only windowed or distribution statistics need the prices themselves.
+        let points = if scan {
+            tracker
+                .points_since(&ticker, since)
+                .into_iter()
+                .map(adjusted)
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        let ewma = if adjust {
+            let alpha = tracker.ewma_alpha();
+            tracker
+                .points_since(&ticker, None)
+                .into_iter()
+                .map(|point| adjusted(point).price.to_f64())
+                .reduce(|ewma, price| ewma + alpha * (price - ewma))
+                .unwrap_or(0.0)
+        } else {
+            running.map_or(0.0, |running| running.ewma)
+        };
+        let (prices, next_page_token): (Vec<Price>, u64) =
+            if req.omit_prices || (scan && points.is_empty()) {
+                (Vec::new(), 0)
+            } else {
+                // The window is a suffix of the history, so paging can start
+                // from whichever is later: the token or the window's start.
+                let window_start = points.first().map_or(0, |point| point.sequence - 1);
+                let (page, more) = tracker.points_after(
+                    &ticker,
+                    req.page_token.max(window_start),
+                    req.page_size as usize,
+                );
+                let next_page_token = match page.last() {
+                    Some(last) if more => last.sequence,
+                    _ => 0,
+                };
+                (
+                    page.into_iter()
+                        .map(|point| adjusted(point).price)
+                        .collect(),
+                    next_page_token,
+                )
+            };
+        let (cumulative_volume, cumulative_vwap) = tracker.cumulative_volume(&ticker);
+        drop(tracker);
 
-        let formatted_message = format!(
-            "{} Statistics:\nAverage: ${:.2}\nStd Dev: ${:.2}\nSample Size: {}",
+        let stats = if scan {
+            let periods_per_year = periods_per_year
+                .or_else(|| {
+                    observed_periods_per_year(points.first()?, points.last()?, points.len())
+                })
+                .unwrap_or(0.0);
+            let window_prices: Vec<f64> = points.iter().map(|point| point.price.to_f64()).collect();
+            let (volume, vwap) = volume_weighted(&points);
+            SummaryStats {
+                volume,
+                vwap,
+                ..summarize(&window_prices, &req.percentiles, kind, periods_per_year)
+                    .map_err(Status::invalid_argument)?
+            }
+        } else {
+            running.map_or_else(SummaryStats::default, |running| SummaryStats {
+                count: running.count,
+                mean: running.mean,
+                std_deviation: running.std_deviation,
+                min: running.min,
+                max: running.max,
+                returns: running.returns,
+                volume: running.volume,
+                vwap: running.vwap,
+                ..SummaryStats::default()
+            })
+        };
+        // Price-valued statistics are shown at the instrument's precision.
+        let decimals = self.feed.decimals(&ticker);
+        let dp = decimals as usize;
+        let mut formatted_message = format!(
+            "{} Statistics:\nAverage: ${:.*}\nStd Dev: ${:.*}\nSample Size: {}\nMin: ${:.*}\nMax: ${:.*}\nEWMA: ${:.*}",
             ticker,
-            average,
-            std_deviation,
-            prices.len()
+            dp,
+            stats.mean,
+            dp,
+            stats.std_deviation,
+            stats.count,
+            dp,
+            stats.min,
+            dp,
+            stats.max,
+            dp,
+            ewma
         );
+        match stats.vwap {
+            Some(vwap) => formatted_message.push_str(&format!(
+                "\nVolume: {}\nVWAP: ${:.*}",
+                stats.volume, dp, vwap
+            )),
+            None => formatted_message.push_str("\nVolume: 0\nVWAP: n/a"),
+        }
+        match cumulative_vwap {
+            Some(vwap) => formatted_message.push_str(&format!(
+                "\nCumulative Volume: {}\nCumulative VWAP: ${:.*}",
+                cumulative_volume, dp, vwap
+            )),
+            None => formatted_message.push_str("\nCumulative Volume: 0\nCumulative VWAP: n/a"),
+        }
+        if scan {
+            formatted_message.push_str(&format!("\nMedian: ${:.*}", dp, stats.median));
+        }
+        for (percentile, value) in &stats.percentiles {
+            formatted_message.push_str(&format!("\nP{}: ${:.*}", percentile, dp, value));
+        }
+        match stats.returns {
+            Some(returns) => formatted_message.push_str(&format!(
+                "\nMean Log Return: {:.6}\nAnnualized Volatility: {:.2}%",
+                returns.mean,
+                returns.annualized_volatility * 100.0
+            )),
+            None => formatted_message.push_str("\nReturns: n/a"),
+        }
+        if scan {
+            formatted_message.push_str(&format!(
+                "\nMax Drawdown: {:.2}%",
+                stats.max_drawdown * 100.0
+            ));
+        }
 
         println!("Sending stats response for ticker: {}", ticker);
         Ok(Response::new(StatsResponse {
             ticker,
-            prices,
-            average,
-            std_deviation,
+            prices: prices.iter().map(|price| price.to_f64()).collect(),
+            average: stats.mean,
+            std_deviation: stats.std_deviation,
+            formatted_message,
+            sample_count: stats.count as u64,
+            next_page_token,
+            min: stats.min,
+            max: stats.max,
+            median: stats.median,
+            percentiles: stats
+                .percentiles
+                .iter()
+                .map(|&(percentile, value)| PercentileValue { percentile, value })
+                .collect(),
+            returns_available: stats.returns.is_some(),
+            log_return_mean: stats.returns.map_or(0.0, |returns| returns.mean),
+            annualized_volatility: stats
+                .returns
+                .map_or(0.0, |returns| returns.annualized_volatility),
+            max_drawdown: stats.max_drawdown,
+            ewma,
+            exact_prices: prices.into_iter().map(decimal_message).collect(),
+            price_decimals: decimals,
+            volume: stats.volume,
+            vwap: stats.vwap.unwrap_or(0.0),
+            cumulative_volume,
+            cumulative_vwap: cumulative_vwap.unwrap_or(0.0),
+        }))
+    }
+
+    pub(crate) async fn handle_get_server_info(
+        &self,
+        request: Request<ServerInfoRequest>,
+    ) -> Result<Response<ServerInfoResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("Received server info request from {}", remote_addr);
+
+        let simulation = &self.simulation;
+        let formatted_message = format!(
+            "Server version: {}\nData source: {}\nSimulation seed: {} ({})\nTick interval: {}ms\nTime step: {}s",
+            env!("CARGO_PKG_VERSION"),
+            simulation.data_source,
+            simulation.seed,
+            if simulation.seed_configured {
+                "configured"
+            } else {
+                "random"
+            },
+            simulation.tick_interval_ms,
+            simulation.time_step_secs
+        );
+
+        Ok(Response::new(ServerInfoResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed: simulation.seed,
+            seed_configured: simulation.seed_configured,
+            tick_interval_ms: simulation.tick_interval_ms,
+            time_step_secs: simulation.time_step_secs,
+            formatted_message,
+            data_source: simulation.data_source.to_string(),
+        }))
+    }
+
+    pub(crate) async fn handle_get_market_status(
+        &self,
+        request: Request<MarketStatusRequest>,
+    ) -> Result<Response<MarketStatusResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("Received market status request from {}", remote_addr);
+
+        let now = SystemTime::now();
+        let calendar = self.feed.calendar();
+        let session = self.feed.session();
+        let next = calendar.next_change(now);
+        let formatted_message = match next {
+            Some((at, next_session)) => format!(
+                "Market is {} ({}); {} in {}s\n",
+                session.label(),
+                calendar.timezone(),
+                next_session.label(),
+                at.duration_since(now).unwrap_or_default().as_secs()
+            ),
+            None if calendar.is_scheduled() => format!(
+                "Market is {} ({}); no session change scheduled\n",
+                session.label(),
+                calendar.timezone()
+            ),
+            None => "Market is always open\n".to_string(),
+        };
+
+        Ok(Response::new(MarketStatusResponse {
+            session: session_message(session).into(),
+            timezone: calendar.timezone(),
+            timestamp: Some(now.into()),
+            next_change: next.map(|(at, _)| at.into()),
+            next_session: next
+                .map_or(MarketSession::Unspecified, |(_, next_session)| {
+                    session_message(next_session)
+                })
+                .into(),
+            always_open: !calendar.is_scheduled(),
+            formatted_message,
+        }))
+    }
+
+    pub(crate) async fn handle_get_halts(
+        &self,
+        request: Request<HaltsRequest>,
+    ) -> Result<Response<HaltsResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let tickers =
+            parse_tickers(&req.tickers, &self.feed.symbols()).map_err(Status::invalid_argument)?;
+        println!(
+            "Received halts request for {} from {}",
+            if tickers.is_empty() {
+                "all tickers".to_string()
+            } else {
+                tickers.join(", ")
+            },
+            remote_addr
+        );
+
+        let halts: Vec<market::Halt> = self
+            .feed
+            .halts()
+            .into_iter()
+            .filter(|halt| tickers.is_empty() || tickers.iter().any(|t| halt.applies_to(t)))
+            .collect();
+        let formatted_message = if halts.is_empty() {
+            "No trading halts\n".to_string()
+        } else {
+            halts.iter().map(|halt| format_halt(halt) + "\n").collect()
+        };
+
+        Ok(Response::new(HaltsResponse {
+            halts: halts.iter().map(halt_message).collect(),
             formatted_message,
         }))
     }
+
+    pub(crate) async fn handle_get_corporate_actions(
+        &self,
+        request: Request<CorporateActionsRequest>,
+    ) -> Result<Response<CorporateActionsResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let ticker = request.into_inner().ticker.to_uppercase();
+        println!(
+            "Received corporate actions request for {} from {}",
+            if ticker.is_empty() {
+                "all tickers"
+            } else {
+                &ticker
+            },
+            remote_addr
+        );
+
+        // Delisted tickers keep their actions on record.
+        let actions = self
+            .feed
+            .corporate_actions((!ticker.is_empty()).then_some(ticker.as_str()));
+        let formatted_message = if actions.is_empty() {
+            "No corporate actions\n".to_string()
+        } else {
+            actions
+                .iter()
+                .map(|action| format_corporate_action(action) + "\n")
+                .collect()
+        };
+
+        Ok(Response::new(CorporateActionsResponse {
+            actions: actions.iter().map(corporate_action_message).collect(),
+            formatted_message,
+        }))
+    }
+
+    pub(crate) async fn handle_get_quote(
+        &self,
+        request: Request<QuoteRequest>,
+    ) -> Result<Response<Quote>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let ticker = request.into_inner().ticker.to_uppercase();
+        println!(
+            "Received quote request for ticker: {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            println!("Error: Invalid ticker requested: {}", ticker);
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+
+        let tick = self
+            .feed
+            .latest(&ticker)
+            .await
+            .ok_or_else(|| Status::unavailable(format!("No quote yet for {}", ticker)))?;
+        let quote = quote_message(&tick);
+
+        println!("Sending quote response: {}", quote.formatted_message.trim());
+        Ok(Response::new(quote))
+    }
+
+    pub(crate) async fn handle_get_candles(
+        &self,
+        request: Request<CandlesRequest>,
+    ) -> Result<Response<CandlesResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
+        println!(
+            "Received candles request for ticker: {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let interval = parse_candle_interval(req.interval).map_err(Status::invalid_argument)?;
+        let start = parse_timestamp(req.start, "start").map_err(Status::invalid_argument)?;
+        let end = parse_timestamp(req.end, "end").map_err(Status::invalid_argument)?;
+        if let (Some(start), Some(end)) = (start, end) {
+            if end < start {
+                return Err(Status::invalid_argument("end must not be before start"));
+            }
+        }
+
+        let mut candles = self.feed.candles(&ticker, interval, start, end).await;
+        if req.adjusted {
+            let tracker = self.price_tracker.lock().await;
+            for candle in &mut candles {
+                // A bar is adjusted for the actions that took effect once it
+                // had closed; the bar an action falls in stays as recorded.
+                let last_instant = candle.end() - std::time::Duration::from_nanos(1);
+                let (price_factor, volume_factor) =
+                    tracker.adjustment_factors(&ticker, last_instant);
+                if (price_factor, volume_factor) != (1.0, 1.0) {
+                    *candle = candle.adjusted(price_factor, volume_factor);
+                }
+            }
+        }
+        let decimals = self.feed.decimals(&ticker);
+        let candle_messages: Vec<String> = candles
+            .iter()
+            .enumerate()
+            .map(|(i, candle)| {
+                format!(
+                    "{}. O ${} H ${} L ${} C ${} V {}{}",
+                    i + 1,
+                    candle.open.format(decimals),
+                    candle.high.format(decimals),
+                    candle.low.format(decimals),
+                    candle.close.format(decimals),
+                    candle.volume,
+                    if candle.complete { "" } else { " (forming)" }
+                )
+            })
+            .collect();
+        let formatted_message = format!(
+            "{} {} candles for {}:\n{}",
+            candles.len(),
+            interval.label(),
+            ticker,
+            candle_messages.join("\n")
+        );
+
+        println!("Sending {} candles for ticker: {}", candles.len(), ticker);
+        Ok(Response::new(CandlesResponse {
+            ticker,
+            interval: req.interval,
+            candles: candles.iter().map(candle_message).collect(),
+            formatted_message,
+        }))
+    }
+
+    pub(crate) async fn handle_get_correlation_matrix(
+        &self,
+        request: Request<CorrelationMatrixRequest>,
+    ) -> Result<Response<CorrelationMatrixResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        println!(
+            "Received correlation request for {} tickers from {}",
+            req.tickers.len(),
+            remote_addr
+        );
+
+        let listed = self.feed.symbols();
+        let tickers = if req.tickers.is_empty() {
+            listed
+        } else {
+            parse_tickers(&req.tickers, &listed).map_err(Status::invalid_argument)?
+        };
+        if tickers.len() < 2 {
+            return Err(Status::invalid_argument(
+                "At least two tickers are needed for a correlation matrix",
+            ));
+        }
+
+        let histories: Vec<_> = {
+            let tracker = self.feed.tracker();
+            let tracker = tracker.lock().await;
+            tickers
+                .iter()
+                .map(|ticker| tracker.points_since(ticker, None))
+                .collect()
+        };
+        let names: Vec<&str> = tickers.iter().map(String::as_str).collect();
+        let series: Vec<_> = names
+            .iter()
+            .zip(&histories)
+            .map(|(name, points)| (*name, points.as_slice()))
+            .collect();
+        let returns = aligned_log_returns(&series, req.window as usize)
+            .map_err(Status::failed_precondition)?;
+        let matrix = correlation_matrix(&names, &returns).map_err(Status::failed_precondition)?;
+        let observations = returns.first().map_or(0, Vec::len);
+
+        let rows: Vec<String> = names
+            .iter()
+            .zip(&matrix)
+            .map(|(name, row)| {
+                let cells: Vec<String> =
+                    row.iter().map(|value| format!("{:>6.3}", value)).collect();
+                format!("{:<5} {}", name, cells.join(" "))
+            })
+            .collect();
+        let header: Vec<String> = names.iter().map(|name| format!("{:>6}", name)).collect();
+        let formatted_message = format!(
+            "Correlation of {} log returns:\n{:<5} {}\n{}",
+            observations,
+            "",
+            header.join(" "),
+            rows.join("\n")
+        );
+
+        println!(
+            "Sending {}x{} correlation matrix over {} returns",
+            tickers.len(),
+            tickers.len(),
+            observations
+        );
+        Ok(Response::new(CorrelationMatrixResponse {
+            tickers,
+            rows: matrix
+                .into_iter()
+                .map(|values| CorrelationRow { values })
+                .collect(),
+            observations: observations as u32,
+            formatted_message,
+        }))
+    }
+
+    pub(crate) async fn handle_get_beta(
+        &self,
+        request: Request<BetaRequest>,
+    ) -> Result<Response<BetaResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        println!(
+            "Received beta request for ticker: {} against {} from {}",
+            req.ticker, req.benchmark, remote_addr
+        );
+
+        let pair = parse_tickers(&[req.ticker, req.benchmark], &self.feed.symbols())
+            .map_err(Status::invalid_argument)?;
+        let (ticker, benchmark) = (&pair[0], &pair[1]);
+
+        let (points, benchmark_points) = {
+            let tracker = self.feed.tracker();
+            let tracker = tracker.lock().await;
+            (
+                tracker.points_since(ticker, None),
+                tracker.points_since(benchmark, None),
+            )
+        };
+        let returns = aligned_log_returns(
+            &[(ticker, &points), (benchmark, &benchmark_points)],
+            req.window as usize,
+        )
+        .map_err(Status::failed_precondition)?;
+        let beta = stats::beta(&returns[0], &returns[1]).map_err(Status::failed_precondition)?;
+
+        let formatted_message = format!(
+            "Beta of {} against {}: {:.4} (correlation {:.4}, {} returns)",
+            ticker, benchmark, beta.beta, beta.correlation, beta.observations
+        );
+        println!("Sending beta response: {}", formatted_message);
+        Ok(Response::new(BetaResponse {
+            ticker: ticker.clone(),
+            benchmark: benchmark.clone(),
+            beta: beta.beta,
+            correlation: beta.correlation,
+            observations: beta.observations as u32,
+            formatted_message,
+        }))
+    }
+
+    pub(crate) async fn handle_get_indicators(
+        &self,
+        request: Request<IndicatorsRequest>,
+    ) -> Result<Response<IndicatorsResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
+        println!(
+            "Received indicators request for ticker: {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            println!("Error: Invalid ticker requested: {}", ticker);
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let mut indicators = new_indicators(&req.specs).map_err(Status::invalid_argument)?;
+
+        // Holding the tracker lock keeps the latest tick in step with the
+        // history.
+        let (points, tick) = {
+            let tracker = self.feed.tracker();
+            let tracker = tracker.lock().await;
+            (
+                tracker.points_since(&ticker, None),
+                self.feed.latest(&ticker).await,
+            )
+        };
+        let tick =
+            tick.ok_or_else(|| Status::unavailable(format!("No prices yet for {}", ticker)))?;
+        for point in &points {
+            for indicator in &mut indicators {
+                indicator.update(point.price.to_f64());
+            }
+        }
+
+        let response = indicators_message(&indicators, &tick);
+        println!(
+            "Sending {} indicators over {} prices for ticker: {}",
+            indicators.len(),
+            points.len(),
+            ticker
+        );
+        Ok(Response::new(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{HaltConfig, MarketConfig};
+    use crate::market::{update, MarketFeed, MarketSimulator, SimulationInfo};
+    use std::collections::HashMap;
+
+    fn service_for(feed: &MarketFeed, config: &MarketConfig) -> StockServiceImpl {
+        let simulator = MarketSimulator::from_config(config).unwrap();
+        StockServiceImpl::with_feed(feed.clone(), SimulationInfo::new(config, &simulator))
+    }
+
+    #[tokio::test]
+    async fn test_price_and_stats_read_the_shared_market() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        for price in [100.0, 101.0, 102.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+
+        let price = service
+            .handle_get_price(Request::new(PriceRequest {
+                ticker: "aapl".to_string(),
+                interval: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(price.price, 102.0);
+        assert_eq!(price.sequence, 3);
+        assert!(price.timestamp.is_some());
+
+        let multiple = service
+            .handle_get_multiple_prices(Request::new(MultiplePricesRequest {
+                ticker: "AAPL".to_string(),
+                count: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(multiple.prices, vec![101.0, 102.0]);
+        let sequences: Vec<u64> = multiple.points.iter().map(|p| p.sequence).collect();
+        assert_eq!(sequences, vec![2, 3]);
+
+        // Reading prices must not add observations of its own.
+        let stats = service
+            .handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.prices, vec![100.0, 101.0, 102.0]);
+        assert_eq!(stats.average, 101.0);
+        assert_eq!((stats.sample_count, stats.next_page_token), (3, 0));
+        // Running totals only: the median needs `include_distribution`.
+        assert_eq!((stats.min, stats.max, stats.median), (100.0, 102.0, 0.0));
+        assert!(stats.ewma > 100.0 && stats.ewma < 102.0);
+    }
+
+    #[tokio::test]
+    async fn test_rich_stats() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        for price in [100.0, 110.0, 99.0, 121.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+
+        let stats = service
+            .handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                percentiles: vec![25.0, 75.0],
+                std_dev_mode: StdDevMode::Sample.into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((stats.min, stats.max, stats.median), (99.0, 121.0, 105.0));
+        let percentiles: Vec<(f64, f64)> = stats
+            .percentiles
+            .iter()
+            .map(|p| (p.percentile, p.value))
+            .collect();
+        assert_eq!(percentiles, vec![(25.0, 99.75), (75.0, 112.75)]);
+        assert!(stats.returns_available);
+        assert!(stats.annualized_volatility > 0.0);
+        assert!((stats.max_drawdown - 0.1).abs() < 1e-12);
+
+        // A window that ends before the prices were published sees none of them.
+        let empty = service
+            .handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                window: Some(prost_types::Duration {
+                    seconds: 0,
+                    nanos: 0,
+                }),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(empty.sample_count, 0);
+        assert!(empty.prices.is_empty());
+        assert!(!empty.returns_available);
+
+        let status = service
+            .handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                percentiles: vec![150.0],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_stats_prices_can_be_paged_or_omitted() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        for price in [100.0, 101.0, 102.0, 103.0, 104.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+        let stats = |omit_prices, page_token| {
+            service.handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                omit_prices,
+                page_size: 2,
+                page_token,
+                ..Default::default()
+            }))
+        };
+
+        let mut pages = Vec::new();
+        let mut page_token = 0;
+        loop {
+            let page = stats(false, page_token).await.unwrap().into_inner();
+            assert_eq!((page.sample_count, page.average), (5, 102.0));
+            pages.push(page.prices);
+            page_token = page.next_page_token;
+            if page_token == 0 {
+                break;
+            }
+        }
+        assert_eq!(
+            pages,
+            vec![vec![100.0, 101.0], vec![102.0, 103.0], vec![104.0]]
+        );
+
+        let omitted = stats(true, 0).await.unwrap().into_inner();
+        assert!(omitted.prices.is_empty());
+        assert_eq!(omitted.average, 102.0);
+    }
+
+    #[tokio::test]
+    async fn test_quote_matches_latest_price() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        feed.publish(vec![update("MSFT", 410.0)]).await;
+
+        let quote = service
+            .handle_get_quote(Request::new(QuoteRequest {
+                ticker: "msft".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(quote.ticker, "MSFT");
+        assert_eq!(quote.last, 410.0);
+        assert!(quote.bid < quote.last && quote.last < quote.ask);
+        assert_eq!(quote.sequence, 1);
+
+        let status = service
+            .handle_get_quote(Request::new(QuoteRequest {
+                ticker: "AAPL".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_closed_market_reports_the_last_close() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        let publish = |price| feed.publish(vec![update("AAPL", price)]);
+        let get_price = || {
+            service.handle_get_price(Request::new(PriceRequest {
+                ticker: "AAPL".to_string(),
+                interval: None,
+            }))
+        };
+
+        let status = service
+            .handle_get_market_status(Request::new(MarketStatusRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(status.always_open);
+        assert_eq!(status.session(), MarketSession::Regular);
+        assert_eq!(status.next_change, None);
+
+        publish(150.0).await;
+        let now = SystemTime::now();
+        feed.change_session(market::MarketSession::AfterHours, now)
+            .await;
+        publish(152.5).await;
+        let after_hours = get_price().await.unwrap().into_inner();
+        assert_eq!((after_hours.price, after_hours.last_close), (152.5, false));
+        assert_eq!(after_hours.session(), MarketSession::AfterHours);
+
+        feed.change_session(market::MarketSession::Closed, now)
+            .await;
+        let closed = get_price().await.unwrap().into_inner();
+        assert_eq!((closed.price, closed.last_close), (150.0, true));
+        assert_eq!(closed.session(), MarketSession::Closed);
+        assert_eq!(closed.formatted_message, "Last close for AAPL: $150.00\n");
+    }
+
+    #[tokio::test]
+    async fn test_halts_show_in_prices_and_are_queryable() {
+        let config = MarketConfig {
+            halts: Some(HaltConfig {
+                band_pct: 5.0,
+                bands: HashMap::new(),
+                reference_secs: 300.0,
+                halt_secs: 300.0,
+                circuit_breakers: Vec::new(),
+            }),
+            ..MarketConfig::default()
+        };
+        let feed = MarketFeed::from_config(&config, 1).unwrap();
+        let service = service_for(&feed, &config);
+        for price in [100.0, 110.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+
+        let price = service
+            .handle_get_price(Request::new(PriceRequest {
+                ticker: "AAPL".to_string(),
+                interval: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(price.price, 100.0);
+        let halt = price.halt.unwrap();
+        assert_eq!(halt.reason(), HaltReason::LimitUp);
+        assert_eq!(halt.ticker, "AAPL");
+
+        let halts = |tickers: &[&str]| {
+            service.handle_get_halts(Request::new(HaltsRequest {
+                tickers: tickers.iter().map(|t| t.to_string()).collect(),
+            }))
+        };
+        assert_eq!(halts(&[]).await.unwrap().into_inner().halts, vec![halt]);
+        assert!(halts(&["MSFT"])
+            .await
+            .unwrap()
+            .into_inner()
+            .halts
+            .is_empty());
+        assert_eq!(
+            halts(&["XYZ"]).await.unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prices_follow_the_instrument_tick_size() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        feed.modify_instrument(
+            "AAPL",
+            &crate::config::InstrumentConfig {
+                tick_size: Some(0.0001),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        for price in [123.456789, 0.1, 0.2] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+
+        let prices = service
+            .handle_get_multiple_prices(Request::new(MultiplePricesRequest {
+                ticker: "AAPL".to_string(),
+                count: 3,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            prices.points[0].exact_price,
+            Some(Decimal {
+                units: 123,
+                nanos: 456_800_000
+            })
+        );
+        assert!(prices
+            .formatted_message
+            .contains("1. Price for AAPL: $123.4568"));
+
+        let stats = service
+            .handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.price_decimals, 4);
+        assert_eq!(
+            stats.exact_prices[2],
+            decimal_message(Price::from_nanos(200_000_000))
+        );
+        assert!(stats.formatted_message.contains("Min: $0.1000"));
+        assert!(stats.formatted_message.contains("Average: $41.2523"));
+        assert_eq!(stats.average, 123.7568 / 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_candles() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        for price in [100.0, 104.0, 98.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+
+        let response = service
+            .handle_get_candles(Request::new(CandlesRequest {
+                ticker: "AAPL".to_string(),
+                interval: CandleInterval::OneHour.into(),
+                start: None,
+                end: None,
+                adjusted: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // All three ticks fall in the current hour unless it just rolled over.
+        let last = response.candles.last().unwrap();
+        assert_eq!(last.close, 98.0);
+        assert!(!last.complete);
+        assert!(response.formatted_message.contains(" C $98.00 "));
+        let (high, low) = response
+            .candles
+            .iter()
+            .fold((f64::MIN, f64::MAX), |(high, low), candle| {
+                (high.max(candle.high), low.min(candle.low))
+            });
+        assert_eq!((high, low), (104.0, 98.0));
+
+        let status = service
+            .handle_get_candles(Request::new(CandlesRequest {
+                ticker: "AAPL".to_string(),
+                interval: CandleInterval::Unspecified.into(),
+                start: None,
+                end: None,
+                adjusted: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_splits_adjust_stats_and_are_listed() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        for price in [200.0, 204.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+        let now = SystemTime::now();
+        let split = market::CorporateActionKind::Split {
+            new_shares: 4,
+            old_shares: 1,
+        };
+        feed.schedule_corporate_action("AAPL", split, now).unwrap();
+        assert_eq!(feed.apply_corporate_actions(now).await.len(), 1);
+
+        let stats = |adjusted| {
+            service.handle_get_stats(Request::new(StatsRequest {
+                ticker: "AAPL".to_string(),
+                adjusted,
+                ..Default::default()
+            }))
+        };
+        let raw = stats(false).await.unwrap().into_inner();
+        assert_eq!(raw.prices, vec![200.0, 204.0, 51.0]);
+        let adjusted = stats(true).await.unwrap().into_inner();
+        assert_eq!(adjusted.prices, vec![50.0, 51.0, 51.0]);
+        assert_eq!((adjusted.min, adjusted.max), (50.0, 51.0));
+
+        let actions = service
+            .handle_get_corporate_actions(Request::new(CorporateActionsRequest {
+                ticker: "aapl".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(actions.actions.len(), 1);
+        let action = &actions.actions[0];
+        assert_eq!(action.status(), CorporateActionStatus::Applied);
+        assert_eq!(
+            action.price_after,
+            Some(decimal_message(Price::from_f64(51.0).unwrap()))
+        );
+        assert!(actions.formatted_message.contains("AAPL 4-for-1 split"));
+    }
+
+    #[tokio::test]
+    async fn test_get_indicators() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        let request = |specs| {
+            Request::new(IndicatorsRequest {
+                ticker: "AAPL".to_string(),
+                specs,
+            })
+        };
+        let spec = |kind| IndicatorSpec { kind: Some(kind) };
+        let status = service
+            .handle_get_indicators(request(vec![spec(indicator_spec::Kind::Rsi(RsiSpec {
+                period: 0,
+            }))]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        for price in [1.0, 2.0, 3.0, 4.0] {
+            feed.publish(vec![update("AAPL", price)]).await;
+        }
+        let response = service
+            .handle_get_indicators(request(vec![
+                spec(indicator_spec::Kind::Sma(SmaSpec { period: 2 })),
+                spec(indicator_spec::Kind::Rsi(RsiSpec { period: 0 })),
+            ]))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((response.price, response.sequence), (4.0, 4));
+        assert!(response.values[0].ready);
+        assert_eq!(response.values[0].value, 3.5);
+        // Unset periods come back filled with their defaults.
+        assert!(!response.values[1].ready);
+        assert_eq!(
+            response.values[1].spec,
+            Some(spec(indicator_spec::Kind::Rsi(RsiSpec { period: 14 })))
+        );
+
+        for specs in [
+            vec![],
+            vec![IndicatorSpec { kind: None }],
+            vec![spec(indicator_spec::Kind::Macd(MacdSpec {
+                fast_period: 30,
+                ..Default::default()
+            }))],
+        ] {
+            let status = service
+                .handle_get_indicators(request(specs))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_correlation_and_beta() {
+        let feed = MarketFeed::new();
+        let service = service_for(&feed, &MarketConfig::default());
+        for price in [100.0, 101.0, 99.0, 102.0] {
+            feed.publish(vec![
+                update("AAPL", price),
+                update("MSFT", price * price / 100.0),
+            ])
+            .await;
+        }
+
+        let matrix = service
+            .handle_get_correlation_matrix(Request::new(CorrelationMatrixRequest {
+                tickers: vec!["aapl".to_string(), "MSFT".to_string()],
+                window: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(matrix.tickers, vec!["AAPL", "MSFT"]);
+        assert_eq!(matrix.observations, 3);
+        assert!((matrix.rows[0].values[1] - 1.0).abs() < 1e-9);
+
+        let beta = service
+            .handle_get_beta(Request::new(BetaRequest {
+                ticker: "MSFT".to_string(),
+                benchmark: "AAPL".to_string(),
+                window: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!((beta.beta - 2.0).abs() < 1e-9);
+        assert_eq!(beta.observations, 2);
+
+        // GOOG has no prices, and a window longer than the history is too short.
+        let status = service
+            .handle_get_beta(Request::new(BetaRequest {
+                ticker: "GOOG".to_string(),
+                benchmark: "AAPL".to_string(),
+                window: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let status = service
+            .handle_get_correlation_matrix(Request::new(CorrelationMatrixRequest {
+                tickers: vec!["AAPL".to_string(), "MSFT".to_string()],
+                window: 10,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let status = service
+            .handle_get_beta(Request::new(BetaRequest {
+                ticker: "AAPL".to_string(),
+                benchmark: "aapl".to_string(),
+                window: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_ticker_is_rejected() {
+        let service = StockServiceImpl::new();
+        let status = service
+            .handle_get_price(Request::new(PriceRequest {
+                ticker: "XYZ".to_string(),
+                interval: None,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_server_info_reports_seed() {
+        let config = MarketConfig {
+            seed: Some(99),
+            ..MarketConfig::default()
+        };
+        let service = service_for(&MarketFeed::new(), &config);
+        let info = service
+            .handle_get_server_info(Request::new(ServerInfoRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.seed, 99);
+        assert!(info.seed_configured);
+    }
 }
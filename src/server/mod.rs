@@ -1,3 +1,8 @@
+use crate::config::Config;
+use crate::market::{
+    spawn_candle_clock, spawn_market_clock, spawn_session_clock, start_replay, MarketFeed,
+    MarketSimulator, SimulationInfo,
+};
 use futures::Stream;
 use std::pin::Pin;
 use std::time::Duration;
@@ -5,9 +10,13 @@ use tokio::sync::watch;
 use tokio::time::sleep;
 use tonic::{service::Interceptor, transport::Server, Request, Response, Status};
 
+mod admin;
+mod depth;
 mod handlers;
+mod orders;
 mod service;
 mod stream;
+mod subscription;
 
 pub use service::StockServiceImpl;
 
@@ -29,10 +38,30 @@ impl Interceptor for ConnectionInterceptor {
     }
 }
 
-pub async fn run_server(host: &str, port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let addr = format!("{}:{}", host, port).parse()?;
+pub async fn run_server(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = format!("{}:{}", config.server.host, config.server.port).parse()?;
     let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
-    let service = StockServiceImpl::new();
+    let simulator = MarketSimulator::from_config(&config.market)?;
+    let simulation = SimulationInfo::new(&config.market, &simulator);
+    let feed = MarketFeed::from_config(&config.market, simulation.seed)?;
+    match &config.market.replay {
+        Some(replay) => {
+            println!("Replaying recorded market data");
+            start_replay(replay, &feed)?;
+        }
+        None => {
+            println!("Simulation seed: {}", simulation.seed);
+            spawn_market_clock(
+                simulator,
+                feed.clone(),
+                Duration::from_millis(config.market.tick_interval_ms.max(1)),
+            );
+        }
+    }
+    spawn_candle_clock(feed.clone());
+    spawn_session_clock(feed.clone());
+    let service = StockServiceImpl::with_feed(feed, simulation)
+        .with_stream_config(config.server.streams.clone())?;
     println!("Server starting up...");
     println!("Server listening on {}", addr);
 
@@ -59,6 +88,20 @@ pub async fn run_server(host: &str, port: u16) -> Result<(), Box<dyn std::error:
         }
     });
 
+    let intercepted_admin =
+        crate::finance::admin_service_server::AdminServiceServer::with_interceptor(
+            service.clone(),
+            ConnectionInterceptor {
+                service: service_for_interceptor.clone(),
+            },
+        );
+    let intercepted_orders =
+        crate::finance::order_service_server::OrderServiceServer::with_interceptor(
+            service.clone(),
+            ConnectionInterceptor {
+                service: service_for_interceptor.clone(),
+            },
+        );
     let intercepted_service =
         crate::finance::stock_service_server::StockServiceServer::with_interceptor(
             service,
@@ -69,6 +112,8 @@ pub async fn run_server(host: &str, port: u16) -> Result<(), Box<dyn std::error:
 
     let server = Server::builder()
         .add_service(intercepted_service)
+        .add_service(intercepted_admin)
+        .add_service(intercepted_orders)
         .serve_with_shutdown(addr, async move {
             shutdown_rx.changed().await.ok();
             println!("Initiating graceful shutdown...");
@@ -115,6 +160,22 @@ impl crate::finance::stock_service_server::StockService for StockServiceImpl {
         self.handle_get_stats(request).await
     }
 
+    async fn get_server_info(
+        &self,
+        request: Request<crate::finance::ServerInfoRequest>,
+    ) -> Result<Response<crate::finance::ServerInfoResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_server_info(request).await
+    }
+
+    async fn get_quote(
+        &self,
+        request: Request<crate::finance::QuoteRequest>,
+    ) -> Result<Response<crate::finance::Quote>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_quote(request).await
+    }
+
     type StreamPricesStream =
         Pin<Box<dyn Stream<Item = Result<crate::finance::PriceResponse, Status>> + Send + 'static>>;
 
@@ -125,4 +186,230 @@ impl crate::finance::stock_service_server::StockService for StockServiceImpl {
         self.update_last_activity(request.remote_addr()).await;
         self.handle_stream_prices(request).await
     }
+
+    type StreamMarketDataStream = Pin<
+        Box<dyn Stream<Item = Result<crate::finance::MarketDataUpdate, Status>> + Send + 'static>,
+    >;
+
+    async fn stream_market_data(
+        &self,
+        request: Request<crate::finance::SubscribeRequest>,
+    ) -> Result<Response<Self::StreamMarketDataStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_market_data(request).await
+    }
+
+    type ManageSubscriptionStream = Pin<
+        Box<dyn Stream<Item = Result<crate::finance::SubscriptionEvent, Status>> + Send + 'static>,
+    >;
+
+    async fn manage_subscription(
+        &self,
+        request: Request<tonic::Streaming<crate::finance::SubscriptionCommand>>,
+    ) -> Result<Response<Self::ManageSubscriptionStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_manage_subscription(request).await
+    }
+
+    type StreamQuotesStream =
+        Pin<Box<dyn Stream<Item = Result<crate::finance::Quote, Status>> + Send + 'static>>;
+
+    async fn stream_quotes(
+        &self,
+        request: Request<crate::finance::QuoteRequest>,
+    ) -> Result<Response<Self::StreamQuotesStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_quotes(request).await
+    }
+
+    async fn get_candles(
+        &self,
+        request: Request<crate::finance::CandlesRequest>,
+    ) -> Result<Response<crate::finance::CandlesResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_candles(request).await
+    }
+
+    type StreamCandlesStream =
+        Pin<Box<dyn Stream<Item = Result<crate::finance::Candle, Status>> + Send + 'static>>;
+
+    async fn stream_candles(
+        &self,
+        request: Request<crate::finance::StreamCandlesRequest>,
+    ) -> Result<Response<Self::StreamCandlesStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_candles(request).await
+    }
+
+    async fn get_correlation_matrix(
+        &self,
+        request: Request<crate::finance::CorrelationMatrixRequest>,
+    ) -> Result<Response<crate::finance::CorrelationMatrixResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_correlation_matrix(request).await
+    }
+
+    async fn get_beta(
+        &self,
+        request: Request<crate::finance::BetaRequest>,
+    ) -> Result<Response<crate::finance::BetaResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_beta(request).await
+    }
+
+    async fn get_market_status(
+        &self,
+        request: Request<crate::finance::MarketStatusRequest>,
+    ) -> Result<Response<crate::finance::MarketStatusResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_market_status(request).await
+    }
+
+    async fn get_halts(
+        &self,
+        request: Request<crate::finance::HaltsRequest>,
+    ) -> Result<Response<crate::finance::HaltsResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_halts(request).await
+    }
+
+    async fn get_corporate_actions(
+        &self,
+        request: Request<crate::finance::CorporateActionsRequest>,
+    ) -> Result<Response<crate::finance::CorporateActionsResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_corporate_actions(request).await
+    }
+
+    async fn get_indicators(
+        &self,
+        request: Request<crate::finance::IndicatorsRequest>,
+    ) -> Result<Response<crate::finance::IndicatorsResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_indicators(request).await
+    }
+
+    type StreamIndicatorsStream = Pin<
+        Box<dyn Stream<Item = Result<crate::finance::IndicatorsResponse, Status>> + Send + 'static>,
+    >;
+
+    async fn stream_indicators(
+        &self,
+        request: Request<crate::finance::IndicatorsRequest>,
+    ) -> Result<Response<Self::StreamIndicatorsStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_indicators(request).await
+    }
+
+    async fn get_depth_snapshot(
+        &self,
+        request: Request<crate::finance::DepthRequest>,
+    ) -> Result<Response<crate::finance::DepthSnapshot>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_depth_snapshot(request).await
+    }
+
+    type StreamDepthStream =
+        Pin<Box<dyn Stream<Item = Result<crate::finance::DepthEvent, Status>> + Send + 'static>>;
+
+    async fn stream_depth(
+        &self,
+        request: Request<crate::finance::DepthRequest>,
+    ) -> Result<Response<Self::StreamDepthStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_depth(request).await
+    }
+
+    type StreamTradesStream =
+        Pin<Box<dyn Stream<Item = Result<crate::finance::Trade, Status>> + Send + 'static>>;
+
+    async fn stream_trades(
+        &self,
+        request: Request<crate::finance::StreamTradesRequest>,
+    ) -> Result<Response<Self::StreamTradesStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_trades(request).await
+    }
+}
+
+#[tonic::async_trait]
+impl crate::finance::admin_service_server::AdminService for StockServiceImpl {
+    async fn add_instrument(
+        &self,
+        request: Request<crate::finance::AddInstrumentRequest>,
+    ) -> Result<Response<crate::finance::Instrument>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_add_instrument(request).await
+    }
+
+    async fn modify_instrument(
+        &self,
+        request: Request<crate::finance::ModifyInstrumentRequest>,
+    ) -> Result<Response<crate::finance::Instrument>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_modify_instrument(request).await
+    }
+
+    async fn delist_instrument(
+        &self,
+        request: Request<crate::finance::DelistInstrumentRequest>,
+    ) -> Result<Response<crate::finance::Instrument>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_delist_instrument(request).await
+    }
+
+    async fn schedule_corporate_action(
+        &self,
+        request: Request<crate::finance::ScheduleCorporateActionRequest>,
+    ) -> Result<Response<crate::finance::CorporateAction>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_schedule_corporate_action(request).await
+    }
+}
+
+#[tonic::async_trait]
+impl crate::finance::order_service_server::OrderService for StockServiceImpl {
+    async fn submit_order(
+        &self,
+        request: Request<crate::finance::SubmitOrderRequest>,
+    ) -> Result<Response<crate::finance::OrderResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_submit_order(request).await
+    }
+
+    async fn cancel_order(
+        &self,
+        request: Request<crate::finance::CancelOrderRequest>,
+    ) -> Result<Response<crate::finance::OrderResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_cancel_order(request).await
+    }
+
+    async fn replace_order(
+        &self,
+        request: Request<crate::finance::ReplaceOrderRequest>,
+    ) -> Result<Response<crate::finance::OrderResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_replace_order(request).await
+    }
+
+    async fn get_order(
+        &self,
+        request: Request<crate::finance::GetOrderRequest>,
+    ) -> Result<Response<crate::finance::OrderResponse>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_get_order(request).await
+    }
+
+    type StreamExecutionsStream = Pin<
+        Box<dyn Stream<Item = Result<crate::finance::ExecutionReport, Status>> + Send + 'static>,
+    >;
+
+    async fn stream_executions(
+        &self,
+        request: Request<crate::finance::StreamExecutionsRequest>,
+    ) -> Result<Response<Self::StreamExecutionsStream>, Status> {
+        self.update_last_activity(request.remote_addr()).await;
+        self.handle_stream_executions(request).await
+    }
 }
@@ -0,0 +1,500 @@
+use super::handlers::{decimal_message, parse_decimal};
+use super::service::StockServiceImpl;
+use super::stream::ResponseStream;
+use crate::finance::{
+    CancelOrderRequest, ExecutionReport, ExecutionType, GetOrderRequest, Order, OrderResponse,
+    OrderSide, OrderStatus, OrderType, ReplaceOrderRequest, StreamExecutionsRequest,
+    SubmitOrderRequest, TimeInForce,
+};
+use crate::market;
+use tonic::{Request, Response, Status};
+
+/// The wire form of `order`.
+fn order_message(order: &market::Order) -> Order {
+    let side = match order.side {
+        market::Side::Buy => OrderSide::Buy,
+        market::Side::Sell => OrderSide::Sell,
+    };
+    let order_type = match order.order_type {
+        market::OrderType::Market => OrderType::Market,
+        market::OrderType::Limit => OrderType::Limit,
+    };
+    let time_in_force = match order.time_in_force {
+        market::TimeInForce::Day => TimeInForce::Day,
+        market::TimeInForce::GoodTillCancel => TimeInForce::GoodTillCancel,
+        market::TimeInForce::ImmediateOrCancel => TimeInForce::ImmediateOrCancel,
+        market::TimeInForce::FillOrKill => TimeInForce::FillOrKill,
+    };
+    let status = match order.status {
+        market::OrderStatus::New => OrderStatus::New,
+        market::OrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+        market::OrderStatus::Filled => OrderStatus::Filled,
+        market::OrderStatus::Cancelled => OrderStatus::Cancelled,
+        market::OrderStatus::Rejected => OrderStatus::Rejected,
+        market::OrderStatus::Expired => OrderStatus::Expired,
+    };
+    Order {
+        order_id: order.id,
+        client_order_id: order.client_order_id.clone(),
+        ticker: order.ticker.clone(),
+        side: side.into(),
+        order_type: order_type.into(),
+        time_in_force: time_in_force.into(),
+        quantity: order.quantity,
+        limit_price: order.limit_price.map(decimal_message),
+        filled_quantity: order.filled_quantity,
+        average_price: order.average_price().map(decimal_message),
+        status: status.into(),
+        created: Some(order.created.into()),
+        updated: Some(order.updated.into()),
+    }
+}
+
+/// One line describing `order` and, as `event`, what just happened to it.
+fn describe_order(order: &market::Order, event: &str) -> String {
+    let terms = match order.limit_price {
+        Some(price) => format!("limit ${} {}", price, order.time_in_force.label()),
+        None => "market".to_string(),
+    };
+    let mut line = format!(
+        "Order {} {} {} {} {}: {} ({}/{} filled",
+        order.id,
+        order.side.label(),
+        order.quantity,
+        order.ticker,
+        terms,
+        event,
+        order.filled_quantity,
+        order.quantity
+    );
+    if let Some(average) = order.average_price() {
+        line += &format!(", average ${}", average);
+    }
+    line.push(')');
+    line
+}
+
+/// One line describing `report`.
+fn format_execution(report: &market::ExecutionReport) -> String {
+    let event = match (report.exec_type, report.last_price) {
+        (market::ExecutionType::Trade, Some(price)) => {
+            format!("filled {} at ${}", report.last_quantity, price)
+        }
+        (market::ExecutionType::New, _) => "accepted".to_string(),
+        (market::ExecutionType::Replaced, _) => "replaced".to_string(),
+        _ => report.order.status.label().to_string(),
+    };
+    let line = describe_order(&report.order, &event);
+    match &report.reason {
+        Some(reason) => format!("{}: {}", line, reason),
+        None => line,
+    }
+}
+
+/// The wire form of `report`.
+fn execution_message(report: &market::ExecutionReport) -> ExecutionReport {
+    let exec_type = match report.exec_type {
+        market::ExecutionType::New => ExecutionType::New,
+        market::ExecutionType::Trade => ExecutionType::Trade,
+        market::ExecutionType::Cancelled => ExecutionType::Cancelled,
+        market::ExecutionType::Replaced => ExecutionType::Replaced,
+        market::ExecutionType::Rejected => ExecutionType::Rejected,
+        market::ExecutionType::Expired => ExecutionType::Expired,
+    };
+    ExecutionReport {
+        execution_id: report.id,
+        exec_type: exec_type.into(),
+        order: Some(order_message(&report.order)),
+        last_price: report.last_price.map(decimal_message),
+        last_quantity: report.last_quantity,
+        timestamp: Some(report.timestamp.into()),
+        reason: report.reason.clone().unwrap_or_default(),
+        formatted_message: format_execution(report),
+    }
+}
+
+/// The response to an order request that produced `reports`, all for
+/// `order`.
+fn order_response(order: &market::Order, reports: &[market::ExecutionReport]) -> OrderResponse {
+    let formatted_message = if reports.is_empty() {
+        describe_order(order, order.status.label()) + "\n"
+    } else {
+        reports
+            .iter()
+            .map(|report| format_execution(report) + "\n")
+            .collect()
+    };
+    OrderResponse {
+        order: Some(order_message(order)),
+        executions: reports.iter().map(execution_message).collect(),
+        formatted_message,
+    }
+}
+
+/// The response to a submit or replace that produced `reports`, led by the
+/// order's own. Fills of the resting orders it traded with belong to their
+/// owners and are left out.
+fn taker_response(reports: &[market::ExecutionReport]) -> OrderResponse {
+    let id = reports.first().expect("orders always report").order.id;
+    let own: Vec<market::ExecutionReport> = reports
+        .iter()
+        .filter(|report| report.order.id == id)
+        .cloned()
+        .collect();
+    let order = &own.last().expect("the first report is the order's").order;
+    order_response(order, &own)
+}
+
+impl StockServiceImpl {
+    /// `NotFound` for an unknown order and `FailedPrecondition` for one
+    /// that is no longer working; `None` for an open order.
+    fn order_not_open(&self, id: u64) -> Option<Status> {
+        match self.feed.order(id) {
+            None => Some(Status::not_found(format!("Unknown order {}", id))),
+            Some(order) if !order.status.is_open() => Some(Status::failed_precondition(format!(
+                "Order {} is already {}",
+                id,
+                order.status.label()
+            ))),
+            Some(_) => None,
+        }
+    }
+
+    pub(crate) async fn handle_submit_order(
+        &self,
+        request: Request<SubmitOrderRequest>,
+    ) -> Result<Response<OrderResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
+        println!("Received order for {} from {}", ticker, remote_addr);
+
+        if !self.feed.is_listed(&ticker) {
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let side = match OrderSide::try_from(req.side) {
+            Ok(OrderSide::Buy) => market::Side::Buy,
+            Ok(OrderSide::Sell) => market::Side::Sell,
+            Ok(OrderSide::Unspecified) | Err(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid order side: {}",
+                    req.side
+                )))
+            }
+        };
+        let order_type = match OrderType::try_from(req.order_type) {
+            Ok(OrderType::Market) => market::OrderType::Market,
+            Ok(OrderType::Limit) => market::OrderType::Limit,
+            Ok(OrderType::Unspecified) | Err(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid order type: {}",
+                    req.order_type
+                )))
+            }
+        };
+        let time_in_force = match TimeInForce::try_from(req.time_in_force) {
+            Ok(TimeInForce::Unspecified | TimeInForce::Day) => market::TimeInForce::Day,
+            Ok(TimeInForce::GoodTillCancel) => market::TimeInForce::GoodTillCancel,
+            Ok(TimeInForce::ImmediateOrCancel) => market::TimeInForce::ImmediateOrCancel,
+            Ok(TimeInForce::FillOrKill) => market::TimeInForce::FillOrKill,
+            Err(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid time in force: {}",
+                    req.time_in_force
+                )))
+            }
+        };
+        let limit_price = req
+            .limit_price
+            .as_ref()
+            .map(parse_decimal)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("Invalid limit price: {}", e)))?;
+
+        let reports = self
+            .feed
+            .submit_order(market::OrderRequest {
+                ticker,
+                side,
+                order_type,
+                quantity: req.quantity,
+                limit_price,
+                time_in_force,
+                client_order_id: req.client_order_id,
+            })
+            .map_err(Status::invalid_argument)?;
+        println!("{}", format_execution(&reports[0]));
+        Ok(Response::new(taker_response(&reports)))
+    }
+
+    pub(crate) async fn handle_cancel_order(
+        &self,
+        request: Request<CancelOrderRequest>,
+    ) -> Result<Response<OrderResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let id = request.into_inner().order_id;
+        println!(
+            "Received request to cancel order {} from {}",
+            id, remote_addr
+        );
+
+        if let Some(status) = self.order_not_open(id) {
+            return Err(status);
+        }
+        let report = self
+            .feed
+            .cancel_order(id)
+            .map_err(Status::failed_precondition)?;
+        Ok(Response::new(order_response(
+            &report.order,
+            std::slice::from_ref(&report),
+        )))
+    }
+
+    pub(crate) async fn handle_replace_order(
+        &self,
+        request: Request<ReplaceOrderRequest>,
+    ) -> Result<Response<OrderResponse>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let req = request.into_inner();
+        println!(
+            "Received request to replace order {} from {}",
+            req.order_id, remote_addr
+        );
+
+        if let Some(status) = self.order_not_open(req.order_id) {
+            return Err(status);
+        }
+        let limit_price = req
+            .limit_price
+            .as_ref()
+            .map(parse_decimal)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("Invalid limit price: {}", e)))?;
+        let reports = self
+            .feed
+            .replace_order(
+                req.order_id,
+                (req.quantity != 0).then_some(req.quantity),
+                limit_price,
+            )
+            .map_err(Status::invalid_argument)?;
+        Ok(Response::new(taker_response(&reports)))
+    }
+
+    pub(crate) async fn handle_get_order(
+        &self,
+        request: Request<GetOrderRequest>,
+    ) -> Result<Response<OrderResponse>, Status> {
+        let id = request.into_inner().order_id;
+        let order = self
+            .feed
+            .order(id)
+            .ok_or_else(|| Status::not_found(format!("Unknown order {}", id)))?;
+        Ok(Response::new(order_response(&order, &[])))
+    }
+
+    pub(crate) async fn handle_stream_executions(
+        &self,
+        request: Request<StreamExecutionsRequest>,
+    ) -> Result<Response<ResponseStream<ExecutionReport>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let ticker = request.into_inner().ticker.to_uppercase();
+        println!(
+            "Received execution streaming request for {} from {}",
+            if ticker.is_empty() {
+                "all tickers"
+            } else {
+                &ticker
+            },
+            remote_addr
+        );
+
+        if !ticker.is_empty() && !self.feed.is_listed(&ticker) {
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let label = if ticker.is_empty() {
+            "all tickers".to_string()
+        } else {
+            ticker.clone()
+        };
+        Ok(Response::new(self.forward(
+            self.feed.subscribe_executions(),
+            None,
+            move |report: &market::ExecutionReport| {
+                ticker.is_empty() || report.order.ticker == ticker
+            },
+            execution_message,
+            label,
+            remote_addr,
+            "execution",
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::update;
+    use crate::price::Price;
+    use futures::StreamExt;
+
+    fn limit(value: f64) -> Option<crate::finance::Decimal> {
+        Some(decimal_message(Price::from_f64(value).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_orders_trade_against_the_simulated_book() {
+        let service = StockServiceImpl::new();
+        service.feed.publish(vec![update("AAPL", 100.0)]).await;
+        let mut executions = service
+            .handle_stream_executions(Request::new(StreamExecutionsRequest {
+                ticker: "AAPL".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let submit = |side: OrderSide, order_type: OrderType, limit_price| {
+            service.handle_submit_order(Request::new(SubmitOrderRequest {
+                ticker: "aapl".to_string(),
+                side: side.into(),
+                order_type: order_type.into(),
+                quantity: 10,
+                limit_price,
+                time_in_force: TimeInForce::GoodTillCancel.into(),
+                client_order_id: "mine".to_string(),
+            }))
+        };
+        let bought = submit(OrderSide::Buy, OrderType::Market, None)
+            .await
+            .unwrap()
+            .into_inner();
+        let order = bought.order.unwrap();
+        assert_eq!(order.status(), OrderStatus::Filled);
+        assert_eq!(order.client_order_id, "mine");
+        assert!(order.average_price.unwrap().units >= 100);
+        let reported: Vec<ExecutionType> = bought
+            .executions
+            .iter()
+            .map(|report| report.exec_type())
+            .collect();
+        // Small simulated levels can take more than one fill.
+        assert_eq!(reported[0], ExecutionType::New);
+        assert!(reported.len() > 1);
+        assert!(reported[1..].iter().all(|&t| t == ExecutionType::Trade));
+        let streamed = executions.next().await.unwrap().unwrap();
+        assert_eq!(streamed.execution_id, bought.executions[0].execution_id);
+
+        // A bid far below the market rests until it is cancelled.
+        let resting = submit(OrderSide::Buy, OrderType::Limit, limit(90.0))
+            .await
+            .unwrap()
+            .into_inner()
+            .order
+            .unwrap();
+        assert_eq!(resting.status(), OrderStatus::New);
+        let replace = |order_id, limit_price| {
+            service.handle_replace_order(Request::new(ReplaceOrderRequest {
+                order_id,
+                quantity: 5,
+                limit_price,
+            }))
+        };
+        let replaced = replace(resting.order_id, limit(91.0))
+            .await
+            .unwrap()
+            .into_inner()
+            .order
+            .unwrap();
+        assert_eq!((replaced.quantity, replaced.limit_price), (5, limit(91.0)));
+        assert_eq!(
+            replace(resting.order_id, limit(91.005))
+                .await
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+        let cancel = || {
+            service.handle_cancel_order(Request::new(CancelOrderRequest {
+                order_id: resting.order_id,
+            }))
+        };
+        cancel().await.unwrap();
+        assert_eq!(
+            cancel().await.unwrap_err().code(),
+            tonic::Code::FailedPrecondition
+        );
+        let fetched = service
+            .handle_get_order(Request::new(GetOrderRequest {
+                order_id: resting.order_id,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(fetched.order.unwrap().status(), OrderStatus::Cancelled);
+        assert!(fetched.executions.is_empty());
+        assert_eq!(
+            service
+                .handle_get_order(Request::new(GetOrderRequest { order_id: 999 }))
+                .await
+                .unwrap_err()
+                .code(),
+            tonic::Code::NotFound
+        );
+
+        // Crossing another resting client order answers with the taker's
+        // own order and reports only.
+        let maker = submit(OrderSide::Sell, OrderType::Limit, limit(100.0))
+            .await
+            .unwrap()
+            .into_inner()
+            .order
+            .unwrap();
+        let taker = submit(OrderSide::Buy, OrderType::Limit, limit(100.0))
+            .await
+            .unwrap()
+            .into_inner();
+        let order = taker.order.unwrap();
+        assert_ne!(order.order_id, maker.order_id);
+        assert_eq!(order.side(), OrderSide::Buy);
+        assert_eq!(order.status(), OrderStatus::Filled);
+        assert!(taker
+            .executions
+            .iter()
+            .all(|report| report.order.as_ref().unwrap().order_id == order.order_id));
+
+        assert_eq!(
+            submit(OrderSide::Buy, OrderType::Limit, None)
+                .await
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+        assert_eq!(
+            submit(OrderSide::Unspecified, OrderType::Market, None)
+                .await
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
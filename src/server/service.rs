@@ -1,14 +1,43 @@
+use crate::config::{MarketConfig, StreamConfig};
+use crate::market::{MarketFeed, MarketSimulator, SimulationInfo};
 use crate::utils::PriceTracker;
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, SystemTime};
 use tokio::sync::Mutex;
 
 #[derive(Clone)]
 pub struct StockServiceImpl {
     pub(crate) price_tracker: Arc<Mutex<PriceTracker>>,
+    pub(crate) feed: MarketFeed,
+    pub(crate) simulation: SimulationInfo,
+    pub(crate) streams: StreamConfig,
     pub(crate) active_clients: Arc<Mutex<HashMap<SocketAddr, SystemTime>>>,
+    /// Streams each client has open, which keep it active however long they
+    /// go without sending anything.
+    pub(crate) open_streams: Arc<StdMutex<HashMap<SocketAddr, usize>>>,
+}
+
+/// Counts a client's stream as open until the task serving it ends.
+pub(crate) struct OpenStream {
+    open_streams: Arc<StdMutex<HashMap<SocketAddr, usize>>>,
+    addr: Option<SocketAddr>,
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        let Some(addr) = self.addr else {
+            return;
+        };
+        let mut open_streams = self.open_streams.lock().unwrap();
+        if let Some(count) = open_streams.get_mut(&addr) {
+            *count -= 1;
+            if *count == 0 {
+                open_streams.remove(&addr);
+            }
+        }
+    }
 }
 
 impl Default for StockServiceImpl {
@@ -19,10 +48,38 @@ impl Default for StockServiceImpl {
 
 impl StockServiceImpl {
     pub fn new() -> Self {
+        let config = MarketConfig::default();
+        // The feed draws its quotes and trades from the reported seed, so the
+        // seed reproduces what this service serves.
+        let simulation = SimulationInfo::new(&config, &MarketSimulator::default());
+        let feed = MarketFeed::from_config(&config, simulation.seed)
+            .expect("default market config is valid");
+        Self::with_feed(feed, simulation)
+    }
+
+    /// Creates a service that serves prices from `feed`; the caller is
+    /// responsible for driving the feed, e.g. with `spawn_market_clock`.
+    pub fn with_feed(feed: MarketFeed, simulation: SimulationInfo) -> Self {
         StockServiceImpl {
-            price_tracker: Arc::new(Mutex::new(PriceTracker::new())),
+            price_tracker: feed.tracker(),
+            feed,
+            simulation,
+            streams: StreamConfig::default(),
             active_clients: Arc::new(Mutex::new(HashMap::new())),
+            open_streams: Arc::new(StdMutex::new(HashMap::new())),
+        }
+    }
+
+    /// Applies the server's policy for client-requested stream intervals.
+    pub fn with_stream_config(mut self, streams: StreamConfig) -> Result<Self, String> {
+        if streams.min_interval_ms == 0 || streams.min_interval_ms > streams.max_interval_ms {
+            return Err(format!(
+                "Stream intervals need 0 < min_interval_ms <= max_interval_ms, got {} and {}",
+                streams.min_interval_ms, streams.max_interval_ms
+            ));
         }
+        self.streams = streams;
+        Ok(self)
     }
 
     pub async fn register_client(&self, addr: SocketAddr) {
@@ -44,15 +101,58 @@ impl StockServiceImpl {
         }
     }
 
+    /// Keeps the client at `remote_addr` active for as long as the returned
+    /// guard lives; stream tasks hold one, as a quiet stream (a closed
+    /// market, a halted ticker) sends nothing to count as activity.
+    pub(crate) fn open_stream(&self, remote_addr: &str) -> OpenStream {
+        let addr = remote_addr.parse().ok();
+        if let Some(addr) = addr {
+            *self.open_streams.lock().unwrap().entry(addr).or_default() += 1;
+        }
+        OpenStream {
+            open_streams: self.open_streams.clone(),
+            addr,
+        }
+    }
+
     pub async fn get_active_client_count(&self) -> usize {
         let mut clients = self.active_clients.lock().await;
+        let open_streams = self.open_streams.lock().unwrap();
         // Remove clients that haven't been active for more than 30 seconds
-        clients.retain(|_, last_active| {
-            last_active
-                .elapsed()
-                .map(|elapsed| elapsed < Duration::from_secs(30))
-                .unwrap_or(false)
+        // and have no stream open
+        clients.retain(|addr, last_active| {
+            open_streams.contains_key(addr)
+                || last_active
+                    .elapsed()
+                    .map(|elapsed| elapsed < Duration::from_secs(30))
+                    .unwrap_or(false)
         });
+        for addr in open_streams.keys() {
+            clients.entry(*addr).or_insert_with(SystemTime::now);
+        }
         clients.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_open_streams_keep_quiet_clients_active() {
+        let service = StockServiceImpl::new();
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(60);
+        service.active_clients.lock().await.insert(addr, long_ago);
+
+        let stream = service.open_stream(&addr.to_string());
+        let other = service.open_stream(&addr.to_string());
+        assert_eq!(service.get_active_client_count().await, 1);
+        drop(stream);
+        assert_eq!(service.get_active_client_count().await, 1);
+
+        drop(other);
+        service.active_clients.lock().await.insert(addr, long_ago);
+        assert_eq!(service.get_active_client_count().await, 0);
+    }
+}
@@ -1,88 +1,908 @@
+use super::handlers::{
+    candle_message, decimal_message, format_halt, halt_message, indicators_message,
+    market_data_message, new_indicators, parse_candle_interval, parse_tickers, quote_message,
+    session_change_message, session_message,
+};
 use super::service::StockServiceImpl;
-use crate::finance::PriceResponse;
+use crate::config::StreamConfig;
+use crate::finance::{
+    Candle, IndicatorsRequest, IndicatorsResponse, MarketDataUpdate, OrderSide, PriceResponse,
+    Quote, QuoteRequest, StreamCandlesRequest, StreamTradesRequest, SubscribeRequest, Trade,
+};
+use crate::market::{self, HaltChange, SessionChange, Tick};
 use futures::Stream;
+use std::collections::{HashSet, VecDeque};
 use std::pin::Pin;
-use tokio::sync::mpsc;
-use tokio::time::{interval, Duration};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
+pub(crate) type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+/// A change in trading conditions announced on a price stream.
+enum Notice {
+    Session(SessionChange),
+    Halt(HaltChange),
+}
+
+/// The wire form of `trade`.
+fn trade_message(trade: &market::Trade) -> Trade {
+    let aggressor = match trade.aggressor {
+        market::Side::Buy => OrderSide::Buy,
+        market::Side::Sell => OrderSide::Sell,
+    };
+    Trade {
+        trade_id: trade.id,
+        ticker: trade.ticker.clone(),
+        price: Some(decimal_message(trade.price)),
+        size: trade.size,
+        aggressor: aggressor.into(),
+        timestamp: Some(trade.timestamp.into()),
+        formatted_message: format!(
+            "Trade {}: {} {} @ ${}, {} aggressor\n",
+            trade.id,
+            trade.size,
+            trade.ticker,
+            trade.price,
+            trade.aggressor.label()
+        ),
+    }
+}
+
+/// Reads a requested update interval; zero or unset means every update.
+/// Anything else must lie within the server's configured bounds.
+pub(crate) fn parse_stream_interval(
+    raw: Option<prost_types::Duration>,
+    policy: &StreamConfig,
+) -> Result<Option<Duration>, String> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    let interval = Duration::try_from(raw).map_err(|e| format!("Invalid interval: {}", e))?;
+    if interval.is_zero() {
+        return Ok(None);
+    }
+    let min = Duration::from_millis(policy.min_interval_ms);
+    let max = Duration::from_millis(policy.max_interval_ms);
+    if !(min..=max).contains(&interval) {
+        return Err(format!(
+            "Interval must be between {:?} and {:?}, got {:?}",
+            min, max, interval
+        ));
+    }
+    Ok(Some(interval))
+}
+
+/// A timer ticking every `period` from one period from now, if any.
+pub(crate) fn flush_timer(period: Option<Duration>) -> Option<Interval> {
+    period.map(|period| {
+        let mut timer = interval_at(Instant::now() + period, period);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        timer
+    })
+}
+
+/// Resolves on the timer's next tick, or never when there is no timer.
+pub(crate) async fn next_flush(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
 impl StockServiceImpl {
     pub(crate) async fn handle_stream_prices(
         &self,
         request: Request<crate::finance::PriceRequest>,
-    ) -> Result<
-        Response<Pin<Box<dyn Stream<Item = Result<PriceResponse, Status>> + Send + 'static>>>,
-        Status,
-    > {
+    ) -> Result<Response<ResponseStream<PriceResponse>>, Status> {
         let remote_addr = request
             .remote_addr()
-            .unwrap_or_else(|| "unknown".parse().unwrap());
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
 
-        let ticker = request.into_inner().ticker.to_uppercase();
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
         println!(
             "Received streaming request for ticker: {} from {}",
             ticker, remote_addr
         );
 
-        if !crate::utils::TICKERS.contains(&ticker.as_str()) {
+        if !self.feed.is_listed(&ticker) {
             println!("Error: Invalid ticker requested: {}", ticker);
             return Err(Status::invalid_argument(format!(
                 "Invalid ticker: {}",
                 ticker
             )));
         }
+        let interval =
+            parse_stream_interval(req.interval, &self.streams).map_err(Status::invalid_argument)?;
 
-        let (tx, rx) = mpsc::channel(32);
-        let price_tracker = self.price_tracker.clone();
+        println!("Established price stream for ticker: {}", ticker);
+        let current = self.feed.latest(&ticker).await;
+        let stream_ticker = ticker.clone();
+        let feed = self.feed.clone();
+        Ok(Response::new(self.conflate(
+            self.feed.subscribe(),
+            self.feed.subscribe_sessions(),
+            self.feed.subscribe_halts(),
+            current,
+            move |tick: &Tick| tick.ticker == stream_ticker,
+            interval,
+            move |tick, skipped, notice| {
+                let price = crate::utils::format_price(&tick.ticker, tick.price, tick.decimals());
+                let formatted_message = match notice {
+                    Some(Notice::Session(change)) => format!(
+                        "Market {} ({} was {}): {}",
+                        change.current.label(),
+                        tick.ticker,
+                        change.previous.label(),
+                        price
+                    ),
+                    Some(Notice::Halt(change)) if change.ended => format!(
+                        "{} resumed after {} halt: {}",
+                        tick.ticker,
+                        change.halt.reason.label(),
+                        price
+                    ),
+                    Some(Notice::Halt(change)) => {
+                        format!("{}: {}", format_halt(&change.halt), price)
+                    }
+                    None => price,
+                };
+                println!("Streaming price: {}", formatted_message.trim());
+                let (session, session_change) = match notice {
+                    Some(Notice::Session(change)) => {
+                        (change.current, Some(session_change_message(change)))
+                    }
+                    _ => (feed.session(), None),
+                };
+                let (halt, halt_ended) = match notice {
+                    Some(Notice::Halt(change)) => (Some(change.halt.clone()), change.ended),
+                    _ => (feed.halt(&tick.ticker), false),
+                };
+                PriceResponse {
+                    ticker: tick.ticker.clone(),
+                    price: tick.price.to_f64(),
+                    formatted_message,
+                    timestamp: Some(tick.timestamp.into()),
+                    sequence: tick.sequence,
+                    conflated: skipped > 0,
+                    skipped_updates: skipped,
+                    exact_price: Some(decimal_message(tick.price)),
+                    session: session_message(session).into(),
+                    last_close: false,
+                    session_change,
+                    halt: halt.as_ref().map(halt_message),
+                    halt_ended,
+                }
+            },
+            ticker,
+            remote_addr,
+        )))
+    }
+
+    pub(crate) async fn handle_stream_market_data(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<ResponseStream<MarketDataUpdate>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let req = request.into_inner();
+        let listed = self.feed.symbols();
+        let tickers = if req.all {
+            listed
+        } else {
+            parse_tickers(&req.tickers, &listed).map_err(Status::invalid_argument)?
+        };
+        if tickers.is_empty() {
+            return Err(Status::invalid_argument(
+                "Subscribe to at least one ticker, or set `all`",
+            ));
+        }
+        let label = tickers.join(", ");
+        println!(
+            "Received market data streaming request for {} from {}",
+            label, remote_addr
+        );
+
+        // One task per client follows the shared feed and filters it, however
+        // many tickers the client watches.
+        let market = self.feed.subscribe();
+        let mut current = Vec::with_capacity(tickers.len());
+        for ticker in &tickers {
+            current.extend(self.feed.latest(ticker).await);
+        }
+        let watched: HashSet<String> = tickers.into_iter().collect();
+
+        println!("Established market data stream for {}", label);
+        Ok(Response::new(self.forward(
+            market,
+            current,
+            move |tick: &Tick| watched.contains(&tick.ticker),
+            |tick| {
+                let update = market_data_message(tick);
+                println!("Streaming market data: {}", update.formatted_message.trim());
+                update
+            },
+            label,
+            remote_addr,
+            "market data",
+        )))
+    }
+
+    pub(crate) async fn handle_stream_quotes(
+        &self,
+        request: Request<QuoteRequest>,
+    ) -> Result<Response<ResponseStream<Quote>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let ticker = request.into_inner().ticker.to_uppercase();
+        println!(
+            "Received quote streaming request for ticker: {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            println!("Error: Invalid ticker requested: {}", ticker);
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+
+        println!("Established quote stream for ticker: {}", ticker);
+        Ok(Response::new(
+            self.stream_ticks(ticker, remote_addr, "quote", |tick| {
+                let quote = quote_message(tick);
+                println!("Streaming quote: {}", quote.formatted_message.trim());
+                quote
+            })
+            .await,
+        ))
+    }
+
+    pub(crate) async fn handle_stream_trades(
+        &self,
+        request: Request<StreamTradesRequest>,
+    ) -> Result<Response<ResponseStream<Trade>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let ticker = request.into_inner().ticker.to_uppercase();
+        println!(
+            "Received trade streaming request for {} from {}",
+            if ticker.is_empty() {
+                "all tickers"
+            } else {
+                &ticker
+            },
+            remote_addr
+        );
+
+        if !ticker.is_empty() && !self.feed.is_listed(&ticker) {
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let label = if ticker.is_empty() {
+            "all tickers".to_string()
+        } else {
+            ticker.clone()
+        };
+        Ok(Response::new(self.forward(
+            self.feed.subscribe_trades(),
+            None,
+            move |trade: &market::Trade| ticker.is_empty() || trade.ticker == ticker,
+            trade_message,
+            label,
+            remote_addr,
+            "trade",
+        )))
+    }
+
+    pub(crate) async fn handle_stream_candles(
+        &self,
+        request: Request<StreamCandlesRequest>,
+    ) -> Result<Response<ResponseStream<Candle>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
+        println!(
+            "Received candle streaming request for ticker: {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            println!("Error: Invalid ticker requested: {}", ticker);
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let interval = parse_candle_interval(req.interval).map_err(Status::invalid_argument)?;
+
+        println!(
+            "Established {} candle stream for ticker: {}",
+            interval.label(),
+            ticker
+        );
+        // Only completed bars are streamed, so there is no current value to
+        // send first.
         let stream_ticker = ticker.clone();
+        let feed = self.feed.clone();
+        Ok(Response::new(self.forward(
+            self.feed.subscribe_candles(),
+            None,
+            move |candle: &crate::market::Candle| {
+                candle.ticker == stream_ticker && candle.interval == interval
+            },
+            move |candle| {
+                println!(
+                    "Streaming {} candle for {}: close ${}",
+                    candle.interval.label(),
+                    candle.ticker,
+                    candle.close.format(feed.decimals(&candle.ticker))
+                );
+                candle_message(candle)
+            },
+            ticker,
+            remote_addr,
+            "candle",
+        )))
+    }
+
+    pub(crate) async fn handle_stream_indicators(
+        &self,
+        request: Request<IndicatorsRequest>,
+    ) -> Result<Response<ResponseStream<IndicatorsResponse>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let req = request.into_inner();
+        let ticker = req.ticker.to_uppercase();
+        println!(
+            "Received indicator streaming request for ticker: {} from {}",
+            ticker, remote_addr
+        );
+
+        if !self.feed.is_listed(&ticker) {
+            println!("Error: Invalid ticker requested: {}", ticker);
+            return Err(Status::invalid_argument(format!(
+                "Invalid ticker: {}",
+                ticker
+            )));
+        }
+        let mut indicators = new_indicators(&req.specs).map_err(Status::invalid_argument)?;
+
+        // Subscribe before reading the history so no tick falls between the
+        // two; ticks already in the history are skipped by sequence number.
+        let market = self.feed.subscribe();
+        let (points, current) = {
+            let tracker = self.feed.tracker();
+            let tracker = tracker.lock().await;
+            (
+                tracker.points_since(&ticker, None),
+                self.feed.latest(&ticker).await,
+            )
+        };
+        for point in &points {
+            for indicator in &mut indicators {
+                indicator.update(point.price.to_f64());
+            }
+        }
+        let mut last_sequence = points.last().map_or(0, |point| point.sequence);
+
+        println!("Established indicator stream for ticker: {}", ticker);
+        let stream_ticker = ticker.clone();
+        Ok(Response::new(self.forward(
+            market,
+            current,
+            move |tick: &Tick| tick.ticker == stream_ticker,
+            move |tick| {
+                if tick.sequence > last_sequence {
+                    for indicator in &mut indicators {
+                        indicator.update(tick.price.to_f64());
+                    }
+                    last_sequence = tick.sequence;
+                }
+                let response = indicators_message(&indicators, tick);
+                println!("Streaming indicators for {}", tick.ticker);
+                response
+            },
+            ticker,
+            remote_addr,
+            "indicator",
+        )))
+    }
+
+    /// Streams `ticker`'s latest tick followed by every new one from the
+    /// market feed, each converted to a response message by `to_message`.
+    async fn stream_ticks<T, F>(
+        &self,
+        ticker: String,
+        remote_addr: String,
+        kind: &'static str,
+        to_message: F,
+    ) -> ResponseStream<T>
+    where
+        T: Send + 'static,
+        F: Fn(&Tick) -> T + Send + 'static,
+    {
+        let current = self.feed.latest(&ticker).await;
+        let stream_ticker = ticker.clone();
+        self.forward(
+            self.feed.subscribe(),
+            current,
+            move |tick: &Tick| tick.ticker == stream_ticker,
+            to_message,
+            ticker,
+            remote_addr,
+            kind,
+        )
+    }
+
+    /// Like [`forward`](Self::forward) for ticks, but never queues stale
+    /// ones: while the client has not taken the previous message, or within
+    /// `interval` of it, each newer accepted tick replaces the held one.
+    /// `to_message` receives the number of ticks skipped since the last
+    /// message, including any the broadcast dropped.
+    ///
+    /// Session changes on `sessions`, and the halts on `halts` that stop
+    /// trading in `ticker`, go out as soon as the client can take them,
+    /// whatever the interval, each with the newest accepted tick.
+    #[allow(clippy::too_many_arguments)]
+    fn conflate<T, A, F>(
+        &self,
+        mut market: broadcast::Receiver<Tick>,
+        mut sessions: broadcast::Receiver<SessionChange>,
+        mut halts: broadcast::Receiver<HaltChange>,
+        first: Option<Tick>,
+        accept: A,
+        interval: Option<Duration>,
+        mut to_message: F,
+        ticker: String,
+        remote_addr: String,
+    ) -> ResponseStream<T>
+    where
+        T: Send + 'static,
+        A: Fn(&Tick) -> bool + Send + 'static,
+        F: FnMut(&Tick, u64, Option<&Notice>) -> T + Send + 'static,
+    {
+        // A single slot, so a slow client holds back at most one message.
+        let (tx, rx) = mpsc::channel(1);
         let service_clone = self.clone();
+        let open_stream = self.open_stream(&remote_addr);
 
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(1));
+            let _open_stream = open_stream;
             println!("Starting price stream for ticker: {}", ticker);
 
+            let mut timer = flush_timer(interval);
+            let mut held = first;
+            // The newest tick sent, repeated with a notice when no newer one
+            // is held.
+            let mut sent: Option<Tick> = None;
+            let mut announce: VecDeque<Notice> = VecDeque::new();
+            // Without an interval every tick may go at once; with one, a
+            // message may go once per elapsed interval.
+            let mut due = true;
+
             loop {
-                interval.tick().await;
-                let (_, price) = crate::utils::generate_random_ticker_and_price();
-                let formatted_message = crate::utils::format_price(&ticker, price);
+                tokio::select! {
+                    received = market.recv() => match received {
+                        Ok(tick) if accept(&tick) => {
+                            held = Some(tick);
+                        }
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                            println!(
+                                "Price stream for {} lagged, skipped {} market events",
+                                ticker, dropped
+                            );
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    received = sessions.recv() => match received {
+                        Ok(change) => announce.push_back(Notice::Session(change)),
+                        Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    received = halts.recv() => match received {
+                        Ok(change) if change.halt.applies_to(&ticker) => {
+                            announce.push_back(Notice::Halt(change));
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = next_flush(&mut timer), if !due => {
+                        due = true;
+                    }
+                    // A halted ticker or a closed market may hold nothing to
+                    // send for a long time.
+                    _ = tx.closed() => {
+                        println!("Client disconnected from price stream for ticker: {}", ticker);
+                        break;
+                    }
+                    permit = tx.reserve(), if (held.is_some() && due) || !announce.is_empty() => {
+                        let Ok(permit) = permit else {
+                            println!("Client disconnected from price stream for ticker: {}", ticker);
+                            break;
+                        };
+                        let notice = announce.pop_front();
+                        if notice.is_none() {
+                            due = timer.is_none();
+                        }
+                        // A notice with no price to carry has nothing to say
+                        // about this ticker.
+                        let last = sent.as_ref().map(|tick| tick.sequence);
+                        if let Some(tick) = held.take().or_else(|| sent.take()) {
+                            // The bus carries every ticker, so only the
+                            // ticker's own sequence tells how many were skipped.
+                            let skipped =
+                                last.map_or(0, |last| tick.sequence.saturating_sub(last + 1));
+                            permit.send(Ok(to_message(&tick, skipped, notice.as_ref())));
+                            sent = Some(tick);
+                        }
 
-                {
-                    let mut tracker = price_tracker.lock().await;
-                    tracker.add_price(&ticker, price);
+                        // Update last activity timestamp for the client
+                        if let Ok(addr) = remote_addr.parse() {
+                            service_clone.update_last_activity(Some(addr)).await;
+                        }
+                    }
                 }
+            }
+        });
 
-                println!("Streaming price: {}", formatted_message.trim());
+        Box::pin(ReceiverStream::new(rx))
+    }
+
+    /// Forwards the events on `market` accepted by `accept`, preceded by the
+    /// events in `first`, to a new response stream.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn forward<E, I, T, A, F>(
+        &self,
+        mut market: broadcast::Receiver<E>,
+        first: I,
+        accept: A,
+        mut to_message: F,
+        ticker: String,
+        remote_addr: String,
+        kind: &'static str,
+    ) -> ResponseStream<T>
+    where
+        E: Clone + Send + 'static,
+        I: IntoIterator<Item = E>,
+        I::IntoIter: Send + 'static,
+        T: Send + 'static,
+        A: Fn(&E) -> bool + Send + 'static,
+        F: FnMut(&E) -> T + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(32);
+        let service_clone = self.clone();
+        let mut first = first.into_iter();
+        let open_stream = self.open_stream(&remote_addr);
+
+        tokio::spawn(async move {
+            let _open_stream = open_stream;
+            println!("Starting {} stream for ticker: {}", kind, ticker);
+
+            loop {
+                let event = match first.next() {
+                    Some(event) => event,
+                    None => tokio::select! {
+                        received = market.recv() => match received {
+                            Ok(event) if accept(&event) => event,
+                            Ok(_) => continue,
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                println!(
+                                    "{} stream for {} lagged, skipped {} market events",
+                                    kind, ticker, skipped
+                                );
+                                continue;
+                            }
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        },
+                        // A ticker that no longer publishes, e.g. once
+                        // delisted, would otherwise never show the client left.
+                        _ = tx.closed() => {
+                            println!(
+                                "Client disconnected from {} stream for ticker: {}",
+                                kind, ticker
+                            );
+                            break;
+                        }
+                    },
+                };
 
-                if tx
-                    .send(Ok(PriceResponse {
-                        ticker: ticker.clone(),
-                        price,
-                        formatted_message,
-                    }))
-                    .await
-                    .is_err()
-                {
+                if tx.send(Ok(to_message(&event))).await.is_err() {
                     println!(
-                        "Client disconnected from price stream for ticker: {}",
-                        ticker
+                        "Client disconnected from {} stream for ticker: {}",
+                        kind, ticker
                     );
                     // No need to explicitly unregister - inactivity monitoring will handle it
                     break;
                 }
 
                 // Update last activity timestamp for the client
-                if let Ok(addr) = remote_addr.to_string().parse() {
+                if let Ok(addr) = remote_addr.parse() {
                     service_clone.update_last_activity(Some(addr)).await;
                 }
             }
         });
 
-        println!("Established price stream for ticker: {}", stream_ticker);
-        let output_stream = ReceiverStream::new(rx);
-        Ok(Response::new(Box::pin(output_stream)
-            as Pin<
-                Box<dyn Stream<Item = Result<PriceResponse, Status>> + Send + 'static>,
-            >))
+        Box::pin(ReceiverStream::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::{update, PriceUpdate};
+    use futures::StreamExt;
+    use std::time::{Duration, SystemTime};
+
+    #[tokio::test]
+    async fn test_quote_stream_follows_the_feed() {
+        let service = StockServiceImpl::new();
+        service.feed.publish(vec![update("AAPL", 190.0)]).await;
+
+        let mut stream = service
+            .handle_stream_quotes(Request::new(QuoteRequest {
+                ticker: "AAPL".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!((first.last, first.sequence), (190.0, 1));
+
+        service.feed.publish(vec![update("AAPL", 191.0)]).await;
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!((second.last, second.sequence), (191.0, 2));
+        assert!(second.bid < 191.0 && second.ask > 191.0);
+    }
+
+    #[tokio::test]
+    async fn test_trade_stream_prints_every_update() {
+        let service = StockServiceImpl::new();
+        let mut trades = service
+            .handle_stream_trades(Request::new(StreamTradesRequest {
+                ticker: "aapl".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        for (ticker, price, volume) in [
+            ("AAPL", 100.0, Some(100)),
+            ("MSFT", 400.0, None),
+            ("AAPL", 101.0, Some(300)),
+            ("AAPL", 100.5, None),
+        ] {
+            service
+                .feed
+                .publish(vec![PriceUpdate {
+                    volume,
+                    ..update(ticker, price)
+                }])
+                .await;
+        }
+
+        let first = trades.next().await.unwrap().unwrap();
+        let up = trades.next().await.unwrap().unwrap();
+        let down = trades.next().await.unwrap().unwrap();
+        assert_eq!((first.size, up.size), (100, 300));
+        assert_eq!(up.trade_id, first.trade_id + 2);
+        assert_eq!(up.price.as_ref().unwrap().units, 101);
+        assert_eq!(
+            (up.aggressor(), down.aggressor()),
+            (OrderSide::Buy, OrderSide::Sell)
+        );
+        assert!(down.size > 0);
+
+        let stats = service
+            .handle_get_stats(Request::new(crate::finance::StatsRequest {
+                ticker: "AAPL".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let volume = 400 + down.size;
+        let vwap = (100.0 * 100.0 + 101.0 * 300.0 + 100.5 * down.size as f64) / volume as f64;
+        assert_eq!(stats.volume, volume);
+        assert!((stats.vwap - vwap).abs() < 1e-9);
+        assert!(stats.formatted_message.contains("VWAP: $"));
+    }
+
+    #[tokio::test]
+    async fn test_candle_stream_emits_closed_bars() {
+        let service = StockServiceImpl::new();
+        let mut stream = service
+            .handle_stream_candles(Request::new(StreamCandlesRequest {
+                ticker: "AAPL".to_string(),
+                interval: crate::finance::CandleInterval::OneSecond.into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        for ticker in ["MSFT", "AAPL"] {
+            service
+                .feed
+                .publish(vec![PriceUpdate {
+                    volume: Some(3),
+                    ..update(ticker, 100.0)
+                }])
+                .await;
+        }
+        service
+            .feed
+            .close_candles(SystemTime::now() + Duration::from_secs(3600))
+            .await;
+
+        let candle = stream.next().await.unwrap().unwrap();
+        assert_eq!(candle.ticker, "AAPL");
+        assert_eq!(
+            candle.interval,
+            i32::from(crate::finance::CandleInterval::OneSecond)
+        );
+        assert_eq!((candle.close, candle.volume), (100.0, 3));
+        assert!(candle.complete);
+    }
+
+    #[tokio::test]
+    async fn test_slow_price_stream_conflates_to_latest() {
+        let service = StockServiceImpl::new();
+        service.feed.publish(vec![update("AAPL", 190.0)]).await;
+
+        let mut stream = service
+            .handle_stream_prices(Request::new(crate::finance::PriceRequest {
+                ticker: "AAPL".to_string(),
+                interval: None,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!((first.sequence, first.conflated), (1, false));
+
+        // The client reads nothing while five more prices arrive.
+        for price in [191.0, 192.0, 193.0, 194.0, 195.0] {
+            service.feed.publish(vec![update("AAPL", price)]).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // At most one message waits in the outgoing slot; the rest collapse
+        // into the newest price, and every price is either sent or counted.
+        let mut received = Vec::new();
+        while received
+            .last()
+            .map_or(true, |last: &PriceResponse| last.sequence < 6)
+        {
+            received.push(stream.next().await.unwrap().unwrap());
+        }
+        assert!(received.len() <= 2);
+        let latest = received.last().unwrap();
+        assert_eq!(latest.price, 195.0);
+        assert!(received.iter().any(|price| price.conflated));
+        let skipped: u64 = received.iter().map(|price| price.skipped_updates).sum();
+        assert_eq!(skipped + received.len() as u64, 5);
+
+        // Other tickers' traffic, enough to overrun the bus, is not counted
+        // against this stream.
+        let mut burst = vec![update("AAPL", 196.0)];
+        burst.extend((0..2048).map(|i| update("MSFT", 400.0 + (i % 2) as f64)));
+        burst.push(update("AAPL", 197.0));
+        service.feed.publish(burst).await;
+        let mut received = Vec::new();
+        while received
+            .last()
+            .map_or(true, |last: &PriceResponse| last.sequence < 8)
+        {
+            received.push(stream.next().await.unwrap().unwrap());
+        }
+        let skipped: u64 = received.iter().map(|price| price.skipped_updates).sum();
+        assert_eq!(skipped + received.len() as u64, 2);
+
+        let too_fast = service
+            .handle_stream_prices(Request::new(crate::finance::PriceRequest {
+                ticker: "AAPL".to_string(),
+                interval: Some(prost_types::Duration {
+                    seconds: 0,
+                    nanos: 1_000_000,
+                }),
+            }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(too_fast.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_market_data_stream_merges_tickers() {
+        let service = StockServiceImpl::new();
+        service.feed.publish(vec![update("AAPL", 190.0)]).await;
+
+        let mut stream = service
+            .handle_stream_market_data(Request::new(SubscribeRequest {
+                tickers: vec!["aapl".to_string(), "MSFT".to_string()],
+                all: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!((first.ticker.as_str(), first.price), ("AAPL", 190.0));
+
+        service
+            .feed
+            .publish(vec![update("GOOG", 140.0), update("MSFT", 410.0)])
+            .await;
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!((second.ticker.as_str(), second.sequence), ("MSFT", 1));
+        assert_eq!(second.quote.unwrap().last, 410.0);
+
+        for request in [
+            SubscribeRequest::default(),
+            SubscribeRequest {
+                tickers: vec!["XYZ".to_string()],
+                all: false,
+            },
+        ] {
+            let status = service
+                .handle_stream_market_data(Request::new(request))
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_indicator_stream_continues_from_history() {
+        let service = StockServiceImpl::new();
+        service.feed.publish(vec![update("AAPL", 1.0)]).await;
+        service.feed.publish(vec![update("AAPL", 2.0)]).await;
+
+        let sma = crate::finance::IndicatorSpec {
+            kind: Some(crate::finance::indicator_spec::Kind::Sma(
+                crate::finance::SmaSpec { period: 3 },
+            )),
+        };
+        let mut stream = service
+            .handle_stream_indicators(Request::new(IndicatorsRequest {
+                ticker: "AAPL".to_string(),
+                specs: vec![sma],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.sequence, 2);
+        assert!(!first.values[0].ready);
+
+        service.feed.publish(vec![update("AAPL", 6.0)]).await;
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!((second.price, second.sequence), (6.0, 3));
+        assert!(second.values[0].ready);
+        assert_eq!(second.values[0].value, 3.0);
     }
 }
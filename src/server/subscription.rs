@@ -0,0 +1,369 @@
+use super::handlers::{market_data_message, parse_tickers};
+use super::service::StockServiceImpl;
+use super::stream::{flush_timer, next_flush, parse_stream_interval, ResponseStream};
+use crate::config::StreamConfig;
+use crate::finance::{
+    subscription_command, subscription_event, CommandAck, SubscribeRequest, SubscriptionCommand,
+    SubscriptionEvent,
+};
+use crate::market::Tick;
+use futures::{Stream, StreamExt};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::Interval;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+/// One client's watch list and update interval.
+#[derive(Default)]
+struct Subscription {
+    tickers: BTreeSet<String>,
+    interval: Option<Duration>,
+    /// Latest unsent tick per ticker while an interval is set.
+    pending: BTreeMap<String, Tick>,
+}
+
+impl Subscription {
+    /// Applies `command` and returns the tickers it newly added.
+    fn apply(
+        &mut self,
+        command: Option<subscription_command::Command>,
+        policy: &StreamConfig,
+        listed: &[String],
+    ) -> Result<Vec<String>, String> {
+        let selected = |request: SubscribeRequest| {
+            let tickers = if request.all {
+                listed.to_vec()
+            } else {
+                parse_tickers(&request.tickers, listed)?
+            };
+            if tickers.is_empty() {
+                return Err("Name at least one ticker, or set `all`".to_string());
+            }
+            Ok(tickers)
+        };
+        match command {
+            Some(subscription_command::Command::Subscribe(request)) => Ok(selected(request)?
+                .into_iter()
+                .filter(|ticker| self.tickers.insert(ticker.clone()))
+                .collect()),
+            Some(subscription_command::Command::Unsubscribe(request)) => {
+                for ticker in selected(request)? {
+                    self.tickers.remove(&ticker);
+                    self.pending.remove(&ticker);
+                }
+                Ok(Vec::new())
+            }
+            Some(subscription_command::Command::ChangeInterval(raw)) => {
+                self.interval = parse_stream_interval(Some(raw), policy)?;
+                Ok(Vec::new())
+            }
+            None => Err("Command must be subscribe, unsubscribe or change_interval".to_string()),
+        }
+    }
+
+    fn ack(&self, command_id: u64, error: Option<String>) -> SubscriptionEvent {
+        SubscriptionEvent {
+            event: Some(subscription_event::Event::Ack(CommandAck {
+                command_id,
+                error: error.unwrap_or_default(),
+                tickers: self.tickers.iter().cloned().collect(),
+                interval: self.interval.and_then(|interval| interval.try_into().ok()),
+            })),
+        }
+    }
+}
+
+fn update_event(tick: &Tick) -> SubscriptionEvent {
+    SubscriptionEvent {
+        event: Some(subscription_event::Event::Update(market_data_message(tick))),
+    }
+}
+
+impl StockServiceImpl {
+    pub(crate) async fn handle_manage_subscription(
+        &self,
+        request: Request<Streaming<SubscriptionCommand>>,
+    ) -> Result<Response<ResponseStream<SubscriptionEvent>>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("Established subscription stream from {}", remote_addr);
+        Ok(Response::new(
+            self.run_subscription(request.into_inner(), remote_addr),
+        ))
+    }
+
+    /// Runs one subscription session: applies each command from `commands`
+    /// and answers it with an ack, while forwarding the shared feed's ticks
+    /// for the current watch list. With an interval set, ticks are held back
+    /// and only each ticker's latest is sent when the interval elapses.
+    ///
+    /// The session outlives the command stream, so a client that has sent
+    /// its last command keeps receiving updates until it disconnects.
+    fn run_subscription<S>(
+        &self,
+        mut commands: S,
+        remote_addr: String,
+    ) -> ResponseStream<SubscriptionEvent>
+    where
+        S: Stream<Item = Result<SubscriptionCommand, Status>> + Send + Unpin + 'static,
+    {
+        let (tx, rx) = mpsc::channel(32);
+        let service = self.clone();
+        let mut market = self.feed.subscribe();
+        let open_stream = self.open_stream(&remote_addr);
+
+        tokio::spawn(async move {
+            let _open_stream = open_stream;
+            let mut subscription = Subscription::default();
+            let mut timer: Option<Interval> = None;
+            let mut commands_open = true;
+
+            loop {
+                let events: Vec<SubscriptionEvent> = tokio::select! {
+                    command = commands.next(), if commands_open => match command {
+                        Some(Ok(command)) => {
+                            let before = subscription.interval;
+                            match subscription.apply(command.command, &service.streams, &service.feed.symbols()) {
+                                Ok(added) => {
+                                    let mut events = vec![subscription.ack(command.command_id, None)];
+                                    if subscription.interval != before {
+                                        timer = flush_timer(subscription.interval);
+                                        // Held ticks go out now rather than waiting on the old timer.
+                                        let pending = std::mem::take(&mut subscription.pending);
+                                        events.extend(pending.values().map(update_event));
+                                    }
+                                    // Newly watched tickers start from their latest price.
+                                    for ticker in added {
+                                        if let Some(tick) = service.feed.latest(&ticker).await {
+                                            events.push(update_event(&tick));
+                                        }
+                                    }
+                                    events
+                                }
+                                Err(error) => {
+                                    println!(
+                                        "Rejected subscription command {} from {}: {}",
+                                        command.command_id, remote_addr, error
+                                    );
+                                    vec![subscription.ack(command.command_id, Some(error))]
+                                }
+                            }
+                        }
+                        Some(Err(status)) => {
+                            println!("Subscription stream from {} failed: {}", remote_addr, status);
+                            break;
+                        }
+                        None => {
+                            commands_open = false;
+                            continue;
+                        }
+                    },
+                    received = market.recv() => match received {
+                        Ok(tick) if subscription.tickers.contains(&tick.ticker) => {
+                            if subscription.interval.is_some() {
+                                subscription.pending.insert(tick.ticker.clone(), tick);
+                                continue;
+                            }
+                            vec![update_event(&tick)]
+                        }
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            println!(
+                                "Subscription stream for {} lagged, skipped {} market events",
+                                remote_addr, skipped
+                            );
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = next_flush(&mut timer) => {
+                        std::mem::take(&mut subscription.pending)
+                            .values()
+                            .map(update_event)
+                            .collect()
+                    }
+                    // Nothing may be due to send for a long time, or ever.
+                    _ = tx.closed() => {
+                        println!(
+                            "Client {} disconnected from subscription stream",
+                            remote_addr
+                        );
+                        return;
+                    }
+                };
+
+                for event in events {
+                    if tx.send(Ok(event)).await.is_err() {
+                        println!(
+                            "Client {} disconnected from subscription stream",
+                            remote_addr
+                        );
+                        return;
+                    }
+                }
+                if let Ok(addr) = remote_addr.parse() {
+                    service.update_last_activity(Some(addr)).await;
+                }
+            }
+        });
+
+        Box::pin(ReceiverStream::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market;
+
+    fn command(command_id: u64, command: subscription_command::Command) -> SubscriptionCommand {
+        SubscriptionCommand {
+            command_id,
+            command: Some(command),
+        }
+    }
+
+    fn subscribe(tickers: &[&str]) -> subscription_command::Command {
+        subscription_command::Command::Subscribe(SubscribeRequest {
+            tickers: tickers.iter().map(|t| t.to_string()).collect(),
+            all: false,
+        })
+    }
+
+    fn ack(event: SubscriptionEvent) -> CommandAck {
+        match event.event {
+            Some(subscription_event::Event::Ack(ack)) => ack,
+            other => panic!("expected an ack, got {:?}", other),
+        }
+    }
+
+    fn update(event: SubscriptionEvent) -> crate::finance::MarketDataUpdate {
+        match event.event {
+            Some(subscription_event::Event::Update(update)) => update,
+            other => panic!("expected an update, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commands_change_the_watch_list() {
+        let service = StockServiceImpl::new();
+        let publish = |ticker: &str, price| {
+            let feed = service.feed.clone();
+            let updates = vec![market::update(ticker, price)];
+            async move {
+                feed.publish(updates).await;
+            }
+        };
+        publish("AAPL", 190.0).await;
+
+        let (commands, command_rx) = mpsc::channel(8);
+        let mut events =
+            service.run_subscription(ReceiverStream::new(command_rx), "test".to_string());
+
+        commands
+            .send(Ok(command(1, subscribe(&["aapl"]))))
+            .await
+            .unwrap();
+        let first = ack(events.next().await.unwrap().unwrap());
+        assert_eq!((first.command_id, first.error.as_str()), (1, ""));
+        assert_eq!(first.tickers, vec!["AAPL"]);
+        assert_eq!(update(events.next().await.unwrap().unwrap()).price, 190.0);
+
+        // A bad command is answered in-band and leaves the stream running.
+        commands
+            .send(Ok(command(2, subscribe(&["XYZ"]))))
+            .await
+            .unwrap();
+        let rejected = ack(events.next().await.unwrap().unwrap());
+        assert_eq!(rejected.command_id, 2);
+        assert!(rejected.error.contains("XYZ"));
+        assert_eq!(rejected.tickers, vec!["AAPL"]);
+
+        commands
+            .send(Ok(command(3, subscribe(&["MSFT"]))))
+            .await
+            .unwrap();
+        assert_eq!(
+            ack(events.next().await.unwrap().unwrap()).tickers,
+            vec!["AAPL", "MSFT"]
+        );
+        let unsubscribe = subscription_command::Command::Unsubscribe(SubscribeRequest {
+            tickers: vec!["AAPL".to_string()],
+            all: false,
+        });
+        commands.send(Ok(command(4, unsubscribe))).await.unwrap();
+        assert_eq!(
+            ack(events.next().await.unwrap().unwrap()).tickers,
+            vec!["MSFT"]
+        );
+
+        publish("AAPL", 191.0).await;
+        publish("MSFT", 410.0).await;
+        let next = update(events.next().await.unwrap().unwrap());
+        assert_eq!((next.ticker.as_str(), next.price), ("MSFT", 410.0));
+    }
+
+    #[tokio::test]
+    async fn test_interval_sends_only_the_latest_price() {
+        let service = StockServiceImpl::new();
+        let (commands, command_rx) = mpsc::channel(8);
+        let mut events =
+            service.run_subscription(ReceiverStream::new(command_rx), "test".to_string());
+
+        commands
+            .send(Ok(command(1, subscribe(&["AAPL"]))))
+            .await
+            .unwrap();
+        ack(events.next().await.unwrap().unwrap());
+        let too_fast = subscription_command::Command::ChangeInterval(prost_types::Duration {
+            seconds: 0,
+            nanos: 1_000_000,
+        });
+        commands.send(Ok(command(2, too_fast))).await.unwrap();
+        assert!(!ack(events.next().await.unwrap().unwrap()).error.is_empty());
+
+        let interval = subscription_command::Command::ChangeInterval(prost_types::Duration {
+            seconds: 0,
+            nanos: 50_000_000,
+        });
+        commands.send(Ok(command(3, interval))).await.unwrap();
+        let changed = ack(events.next().await.unwrap().unwrap());
+        assert_eq!(changed.interval.unwrap().nanos, 50_000_000);
+
+        for price in [1.0, 2.0, 3.0] {
+            service
+                .feed
+                .publish(vec![market::update("AAPL", price)])
+                .await;
+        }
+        let conflated = update(events.next().await.unwrap().unwrap());
+        assert_eq!((conflated.price, conflated.sequence), (3.0, 3));
+    }
+
+    #[tokio::test]
+    async fn test_idle_session_ends_when_the_client_leaves() {
+        let service = StockServiceImpl::new();
+        let (commands, command_rx) = mpsc::channel(8);
+        let events = service.run_subscription(
+            ReceiverStream::new(command_rx),
+            "127.0.0.1:6000".to_string(),
+        );
+        drop(commands);
+        let open = || !service.open_streams.lock().unwrap().is_empty();
+        assert!(open());
+
+        // Nothing is ever sent on an empty watch list, yet the session ends.
+        drop(events);
+        tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            while open() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,866 @@
+//! Descriptive statistics over a ticker's price history.
+
+use crate::market::TRADING_SECONDS_PER_YEAR;
+use crate::price::{Price, NANOS_PER_UNIT};
+use crate::utils::PricePoint;
+use std::collections::{HashSet, VecDeque};
+use std::time::SystemTime;
+
+/// Which estimator `std_deviation` uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StdDevKind {
+    /// Divide by `n`: the spread of exactly these prices.
+    #[default]
+    Population,
+    /// Divide by `n - 1`: an unbiased estimate of the underlying variance.
+    Sample,
+}
+
+/// Mean and volatility of the log returns between consecutive prices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReturnStats {
+    /// Mean log return per price update.
+    pub mean: f64,
+    /// Sample standard deviation of log returns, scaled to one trading year.
+    pub annualized_volatility: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SummaryStats {
+    pub count: usize,
+    pub mean: f64,
+    pub std_deviation: f64,
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+    /// `(percentile, value)` pairs in the order requested.
+    pub percentiles: Vec<(f64, f64)>,
+    /// `None` with fewer than two prices, or when a non-positive price leaves
+    /// log returns undefined.
+    pub returns: Option<ReturnStats>,
+    /// Largest fall from a running peak, as a fraction of that peak.
+    pub max_drawdown: f64,
+    /// Shares traded over the prices summarized.
+    pub volume: u64,
+    /// Volume-weighted average price; `None` when nothing traded.
+    pub vwap: Option<f64>,
+}
+
+/// Summarizes `prices`, oldest first. `percentiles` are in `[0, 100]` and
+/// interpolate linearly between ranks; `periods_per_year` is how many price
+/// updates make up a trading year, used to annualize volatility.
+pub fn summarize(
+    prices: &[f64],
+    percentiles: &[f64],
+    kind: StdDevKind,
+    periods_per_year: f64,
+) -> Result<SummaryStats, String> {
+    if let Some(bad) = percentiles
+        .iter()
+        .find(|pct| !(pct.is_finite() && (0.0..=100.0).contains(*pct)))
+    {
+        return Err(format!("Percentiles must be within 0 and 100, got {}", bad));
+    }
+    if prices.is_empty() {
+        return Ok(SummaryStats::default());
+    }
+
+    let mut sorted = prices.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mean = prices.iter().sum::<f64>() / prices.len() as f64;
+
+    Ok(SummaryStats {
+        count: prices.len(),
+        mean,
+        std_deviation: std_deviation(prices, mean, kind),
+        min: sorted[0],
+        max: sorted[sorted.len() - 1],
+        median: percentile(&sorted, 50.0),
+        percentiles: percentiles
+            .iter()
+            .map(|&pct| (pct, percentile(&sorted, pct)))
+            .collect(),
+        returns: return_stats(prices, periods_per_year),
+        max_drawdown: max_drawdown(prices),
+        // Prices alone carry no volume; see `volume_weighted`.
+        volume: 0,
+        vwap: None,
+    })
+}
+
+/// Total shares traded over `points` and their volume-weighted average
+/// price, `None` when nothing traded.
+pub fn volume_weighted(points: &[PricePoint]) -> (u64, Option<f64>) {
+    let mut traded = TradedVolume::default();
+    for point in points {
+        traded.push(point);
+    }
+    (traded.shares, traded.vwap())
+}
+
+/// Price updates per trading year implied by the average spacing between
+/// `first` and `last`, `count` updates apart, for histories whose spacing is
+/// not known up front.
+pub fn observed_periods_per_year(
+    first: &PricePoint,
+    last: &PricePoint,
+    count: usize,
+) -> Option<f64> {
+    let span = last.timestamp.duration_since(first.timestamp).ok()?;
+    if count < 2 || span.is_zero() {
+        return None;
+    }
+    let spacing = span.as_secs_f64() / (count - 1) as f64;
+    Some(TRADING_SECONDS_PER_YEAR / spacing)
+}
+
+/// Log returns of each series over the timestamps all of them share,
+/// oldest first, keeping the most recent `window` returns (all of them when
+/// `window` is zero).
+///
+/// Every ticker in a published batch carries the same timestamp, so series
+/// from one feed line up exactly; a timestamp missing from any series is
+/// dropped from all of them. Fails when fewer than `window` (and at least
+/// two) aligned returns remain, or when a non-positive price leaves log
+/// returns undefined.
+pub fn aligned_log_returns(
+    series: &[(&str, &[PricePoint])],
+    window: usize,
+) -> Result<Vec<Vec<f64>>, String> {
+    let required = window.max(2);
+    let Some(((first_name, first), rest)) = series.split_first() else {
+        return Ok(Vec::new());
+    };
+    for (name, points) in series {
+        if points.len() <= required {
+            return Err(format!(
+                "{} has {} prices, need at least {} for {} returns",
+                name,
+                points.len(),
+                required + 1,
+                required
+            ));
+        }
+    }
+
+    let others: Vec<HashSet<SystemTime>> = rest
+        .iter()
+        .map(|(_, points)| points.iter().map(|point| point.timestamp).collect())
+        .collect();
+    let shared: Vec<SystemTime> = first
+        .iter()
+        .map(|point| point.timestamp)
+        .filter(|timestamp| others.iter().all(|set| set.contains(timestamp)))
+        .collect();
+    if shared.len() <= required {
+        let names: Vec<&str> = series.iter().map(|(name, _)| *name).collect();
+        return Err(format!(
+            "Price series for {} are misaligned: only {} of {}'s {} timestamps are shared, need {}",
+            names.join(", "),
+            shared.len(),
+            first_name,
+            first.len(),
+            required + 1
+        ));
+    }
+    let start = if window == 0 {
+        0
+    } else {
+        shared.len() - (window + 1)
+    };
+    let shared = &shared[start..];
+
+    series
+        .iter()
+        .map(|(name, points)| {
+            // Histories are in timestamp order, so one forward pass finds
+            // every shared timestamp.
+            let mut points = points.iter().peekable();
+            let mut prices = Vec::with_capacity(shared.len());
+            for timestamp in shared {
+                while points
+                    .next_if(|point| point.timestamp < *timestamp)
+                    .is_some()
+                {}
+                let point = points
+                    .next_if(|point| point.timestamp == *timestamp)
+                    .ok_or_else(|| {
+                        format!("Price series for {} is not in timestamp order", name)
+                    })?;
+                prices.push(point.price.to_f64());
+            }
+            if prices.iter().any(|&price| price <= 0.0) {
+                return Err(format!(
+                    "{} has non-positive prices in the window, so log returns are undefined",
+                    name
+                ));
+            }
+            Ok(prices
+                .windows(2)
+                .map(|pair| (pair[1] / pair[0]).ln())
+                .collect())
+        })
+        .collect()
+}
+
+/// Sample covariance of two equally long series.
+fn covariance(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - mean_a) * (y - mean_b))
+        .sum::<f64>()
+        / (n - 1.0)
+}
+
+/// Pearson correlation between every pair of the aligned `returns`, named
+/// by `names` in errors. Fails when a series does not move at all.
+pub fn correlation_matrix(names: &[&str], returns: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, String> {
+    let std_devs: Vec<f64> = returns
+        .iter()
+        .map(|series| covariance(series, series).sqrt())
+        .collect();
+    if let Some(flat) = std_devs.iter().position(|&std_dev| std_dev == 0.0) {
+        return Err(format!(
+            "{} has constant prices over the window, so its correlation is undefined",
+            names[flat]
+        ));
+    }
+    Ok((0..returns.len())
+        .map(|i| {
+            (0..returns.len())
+                .map(|j| {
+                    if i == j {
+                        1.0
+                    } else {
+                        (covariance(&returns[i], &returns[j]) / (std_devs[i] * std_devs[j]))
+                            .clamp(-1.0, 1.0)
+                    }
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Sensitivity of one return series to a benchmark's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BetaStats {
+    /// Covariance with the benchmark over the benchmark's variance.
+    pub beta: f64,
+    pub correlation: f64,
+    /// Number of returns used.
+    pub observations: usize,
+}
+
+/// Beta of `returns` against `benchmark`, both aligned. Fails when the
+/// benchmark does not move at all.
+pub fn beta(returns: &[f64], benchmark: &[f64]) -> Result<BetaStats, String> {
+    if returns.len() != benchmark.len() || returns.len() < 2 {
+        return Err(format!(
+            "Beta needs two equally long series of at least 2 returns, got {} and {}",
+            returns.len(),
+            benchmark.len()
+        ));
+    }
+    let benchmark_variance = covariance(benchmark, benchmark);
+    if benchmark_variance == 0.0 {
+        return Err("Benchmark has constant prices over the window, so beta is undefined".into());
+    }
+    let covariance = covariance(returns, benchmark);
+    let variance = self::covariance(returns, returns);
+    let correlation = if variance == 0.0 {
+        0.0
+    } else {
+        (covariance / (variance * benchmark_variance).sqrt()).clamp(-1.0, 1.0)
+    };
+    Ok(BetaStats {
+        beta: covariance / benchmark_variance,
+        correlation,
+        observations: returns.len(),
+    })
+}
+
+/// Welford's online mean and variance, extended to remove values again so
+/// it can follow a sliding window.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Welford {
+    count: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl Welford {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Removes a value previously pushed.
+    pub fn remove(&mut self, value: f64) {
+        if self.count <= 1 {
+            *self = Welford::default();
+            return;
+        }
+        self.count -= 1;
+        let delta = value - self.mean;
+        self.mean -= delta / self.count as f64;
+        self.m2 = if self.count == 1 {
+            // A single value has no spread; don't keep the rounding residue.
+            0.0
+        } else {
+            // Rounding can leave a tiny negative sum of squares behind.
+            (self.m2 - delta * (value - self.mean)).max(0.0)
+        };
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn std_deviation(&self, kind: StdDevKind) -> f64 {
+        let divisor = match kind {
+            StdDevKind::Population => self.count,
+            StdDevKind::Sample => self.count.saturating_sub(1),
+        };
+        if divisor == 0 {
+            0.0
+        } else {
+            (self.m2 / divisor as f64).sqrt()
+        }
+    }
+}
+
+/// Count, sum and sum of squares of fixed-point prices, kept exactly so a
+/// window that has seen millions of pushes and removals reports the same
+/// mean and variance as one computed from scratch.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct PriceMoments {
+    count: usize,
+    /// In nanos. Wrapping arithmetic keeps these exact as long as the
+    /// window's true totals fit, whatever the order of pushes and removals.
+    sum: i128,
+    sum_squares: i128,
+}
+
+impl PriceMoments {
+    fn push(&mut self, price: Price) {
+        let nanos = price.as_nanos() as i128;
+        self.count += 1;
+        self.sum = self.sum.wrapping_add(nanos);
+        self.sum_squares = self.sum_squares.wrapping_add(nanos * nanos);
+    }
+
+    fn remove(&mut self, price: Price) {
+        let nanos = price.as_nanos() as i128;
+        self.count -= 1;
+        self.sum = self.sum.wrapping_sub(nanos);
+        self.sum_squares = self.sum_squares.wrapping_sub(nanos * nanos);
+    }
+
+    fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum as f64 / self.count as f64 / NANOS_PER_UNIT as f64
+    }
+
+    fn std_deviation(&self, kind: StdDevKind) -> f64 {
+        let divisor = match kind {
+            StdDevKind::Population => self.count,
+            StdDevKind::Sample => self.count.saturating_sub(1),
+        };
+        if divisor == 0 {
+            return 0.0;
+        }
+        let count = self.count as i128;
+        // n * sum(x^2) - sum(x)^2 is n^2 times the population variance.
+        let spread = count
+            .checked_mul(self.sum_squares)
+            .zip(self.sum.checked_mul(self.sum))
+            .and_then(|(scaled, squared)| scaled.checked_sub(squared))
+            .map(|spread| spread as f64)
+            .unwrap_or_else(|| {
+                let mean = self.sum as f64 / self.count as f64;
+                (self.sum_squares as f64 / self.count as f64 - mean * mean).max(0.0)
+                    * (self.count * self.count) as f64
+            });
+        let variance = spread / self.count as f64 / divisor as f64;
+        variance.sqrt() / NANOS_PER_UNIT as f64
+    }
+}
+
+/// Shares traded and their value, kept exactly like [`PriceMoments`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct TradedVolume {
+    pub(crate) shares: u64,
+    /// Price in nanos times shares, summed.
+    notional: i128,
+}
+
+impl TradedVolume {
+    pub(crate) fn push(&mut self, point: &PricePoint) {
+        self.shares += point.volume;
+        self.notional = self
+            .notional
+            .wrapping_add(point.price.as_nanos() as i128 * point.volume as i128);
+    }
+
+    fn remove(&mut self, point: &PricePoint) {
+        self.shares -= point.volume;
+        self.notional = self
+            .notional
+            .wrapping_sub(point.price.as_nanos() as i128 * point.volume as i128);
+    }
+
+    pub(crate) fn vwap(&self) -> Option<f64> {
+        (self.shares > 0).then(|| self.notional as f64 / self.shares as f64 / NANOS_PER_UNIT as f64)
+    }
+}
+
+/// Minimum or maximum of a sliding window in amortized constant time.
+///
+/// Keeps a deque of candidates, each better than everything pushed after it;
+/// a value that can never again be the extreme is dropped as soon as a
+/// better one arrives after it.
+#[derive(Debug, Clone)]
+struct WindowExtreme {
+    candidates: VecDeque<(u64, f64)>,
+    is_better: fn(f64, f64) -> bool,
+}
+
+impl WindowExtreme {
+    fn min() -> Self {
+        WindowExtreme {
+            candidates: VecDeque::new(),
+            is_better: |a, b| a < b,
+        }
+    }
+
+    fn max() -> Self {
+        WindowExtreme {
+            candidates: VecDeque::new(),
+            is_better: |a, b| a > b,
+        }
+    }
+
+    fn push(&mut self, sequence: u64, value: f64) {
+        while self
+            .candidates
+            .back()
+            .is_some_and(|&(_, back)| !(self.is_better)(back, value))
+        {
+            self.candidates.pop_back();
+        }
+        self.candidates.push_back((sequence, value));
+    }
+
+    fn evict(&mut self, sequence: u64) {
+        if self
+            .candidates
+            .front()
+            .is_some_and(|&(front, _)| front == sequence)
+        {
+            self.candidates.pop_front();
+        }
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.candidates.front().map(|&(_, value)| value)
+    }
+}
+
+/// Statistics kept up to date as prices enter and leave a ticker's retained
+/// history, so reading them never rescans the history.
+#[derive(Debug, Clone)]
+pub struct RunningStats {
+    prices: PriceMoments,
+    /// Log returns between consecutive retained prices that are both positive.
+    returns: Welford,
+    non_positive: usize,
+    min: WindowExtreme,
+    max: WindowExtreme,
+    ewma: Option<f64>,
+    ewma_alpha: f64,
+    traded: TradedVolume,
+}
+
+/// A constant-time snapshot of [`RunningStats`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunningSummary {
+    pub count: usize,
+    pub mean: f64,
+    pub std_deviation: f64,
+    pub min: f64,
+    pub max: f64,
+    pub returns: Option<ReturnStats>,
+    /// Exponentially weighted moving average of every price seen, including
+    /// those since evicted.
+    pub ewma: f64,
+    /// Shares traded over the retained prices.
+    pub volume: u64,
+    /// Their volume-weighted average price; `None` when nothing traded.
+    pub vwap: Option<f64>,
+}
+
+impl RunningStats {
+    /// `ewma_alpha` is the weight of each new price in the moving average.
+    pub fn new(ewma_alpha: f64) -> Self {
+        RunningStats {
+            prices: PriceMoments::default(),
+            returns: Welford::default(),
+            non_positive: 0,
+            min: WindowExtreme::min(),
+            max: WindowExtreme::max(),
+            ewma: None,
+            ewma_alpha,
+            traded: TradedVolume::default(),
+        }
+    }
+
+    /// Adds `point`, whose predecessor in the window (if any) was `previous`.
+    pub fn push(&mut self, point: &PricePoint, previous: Option<Price>) {
+        self.prices.push(point.price);
+        self.traded.push(point);
+        let price = point.price.to_f64();
+        if let Some(log_return) = previous.and_then(|previous| log_return(previous.to_f64(), price))
+        {
+            self.returns.push(log_return);
+        }
+        if price <= 0.0 {
+            self.non_positive += 1;
+        }
+        self.min.push(point.sequence, price);
+        self.max.push(point.sequence, price);
+        self.ewma = Some(match self.ewma {
+            Some(ewma) => ewma + self.ewma_alpha * (price - ewma),
+            None => price,
+        });
+    }
+
+    /// Removes the oldest point in the window, `point`, now followed by `next`.
+    pub fn evict(&mut self, point: &PricePoint, next: Option<Price>) {
+        self.prices.remove(point.price);
+        self.traded.remove(point);
+        let price = point.price.to_f64();
+        if let Some(log_return) = next.and_then(|next| log_return(price, next.to_f64())) {
+            self.returns.remove(log_return);
+        }
+        if price <= 0.0 {
+            self.non_positive -= 1;
+        }
+        self.min.evict(point.sequence);
+        self.max.evict(point.sequence);
+    }
+
+    pub fn summary(&self, kind: StdDevKind, periods_per_year: f64) -> RunningSummary {
+        let returns_defined = self.prices.count >= 2 && self.non_positive == 0;
+        RunningSummary {
+            count: self.prices.count,
+            mean: self.prices.mean(),
+            std_deviation: self.prices.std_deviation(kind),
+            min: self.min.value().unwrap_or(0.0),
+            max: self.max.value().unwrap_or(0.0),
+            returns: returns_defined.then(|| ReturnStats {
+                mean: self.returns.mean(),
+                annualized_volatility: self.returns.std_deviation(StdDevKind::Sample)
+                    * periods_per_year.sqrt(),
+            }),
+            ewma: self.ewma.unwrap_or(0.0),
+            volume: self.traded.shares,
+            vwap: self.traded.vwap(),
+        }
+    }
+}
+
+fn log_return(from: f64, to: f64) -> Option<f64> {
+    (from > 0.0 && to > 0.0).then(|| (to / from).ln())
+}
+
+fn std_deviation(values: &[f64], mean: f64, kind: StdDevKind) -> f64 {
+    let divisor = match kind {
+        StdDevKind::Population => values.len(),
+        StdDevKind::Sample => values.len().saturating_sub(1),
+    };
+    if divisor == 0 {
+        return 0.0;
+    }
+    let squares: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+    (squares / divisor as f64).sqrt()
+}
+
+/// Value at `pct` percent of `sorted`, interpolating between the closest ranks.
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = pct / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn return_stats(prices: &[f64], periods_per_year: f64) -> Option<ReturnStats> {
+    if prices.len() < 2 || prices.iter().any(|&price| price <= 0.0) {
+        return None;
+    }
+    let returns: Vec<f64> = prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    Some(ReturnStats {
+        mean,
+        annualized_volatility: std_deviation(&returns, mean, StdDevKind::Sample)
+            * periods_per_year.sqrt(),
+    })
+}
+
+fn max_drawdown(prices: &[f64]) -> f64 {
+    let mut peak = f64::NEG_INFINITY;
+    let mut worst: f64 = 0.0;
+    for &price in prices {
+        peak = peak.max(price);
+        if peak > 0.0 {
+            worst = worst.max((peak - price) / peak);
+        }
+    }
+    worst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::price;
+    use crate::utils::point;
+
+    #[test]
+    fn test_summary_of_prices() {
+        let prices = [100.0, 110.0, 99.0, 121.0];
+        let stats = summarize(&prices, &[0.0, 25.0, 100.0], StdDevKind::Population, 1.0).unwrap();
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean, 107.5);
+        assert_eq!((stats.min, stats.max), (99.0, 121.0));
+        assert_eq!(stats.median, 105.0);
+        // Sorted: 99, 100, 110, 121; the 25th percentile sits at rank 0.75.
+        assert_eq!(
+            stats.percentiles,
+            vec![(0.0, 99.0), (25.0, 99.75), (100.0, 121.0)]
+        );
+        // From the 110 peak down to 99.
+        assert!((stats.max_drawdown - 0.1).abs() < 1e-12);
+
+        let returns = stats.returns.unwrap();
+        assert!((returns.mean - (121.0f64 / 100.0).ln() / 3.0).abs() < 1e-12);
+        let quarterly = summarize(&prices, &[], StdDevKind::Population, 4.0).unwrap();
+        let ratio =
+            quarterly.returns.unwrap().annualized_volatility / returns.annualized_volatility;
+        assert!((ratio - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sample_and_population_std_deviation() {
+        let prices = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let population = summarize(&prices, &[], StdDevKind::Population, 1.0).unwrap();
+        assert_eq!(population.std_deviation, 2.0);
+        let sample = summarize(&prices, &[], StdDevKind::Sample, 1.0).unwrap();
+        assert!((sample.std_deviation - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+
+        let single = summarize(&[5.0], &[50.0], StdDevKind::Sample, 1.0).unwrap();
+        assert_eq!((single.std_deviation, single.median), (0.0, 5.0));
+        assert_eq!(single.returns, None);
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert!(summarize(&[1.0], &[101.0], StdDevKind::Population, 1.0).is_err());
+        assert_eq!(
+            summarize(&[], &[50.0], StdDevKind::Population, 1.0).unwrap(),
+            SummaryStats::default()
+        );
+        // Log returns are undefined once a mean-reverting series crosses zero.
+        let spread = summarize(&[0.5, -0.25, 0.1], &[], StdDevKind::Population, 1.0).unwrap();
+        assert_eq!(spread.returns, None);
+    }
+
+    #[test]
+    fn test_observed_periods_per_year() {
+        let first = point(1.0, 0, 0);
+        assert_eq!(
+            observed_periods_per_year(&first, &point(1.0, 4, 4), 3),
+            Some(TRADING_SECONDS_PER_YEAR / 2.0)
+        );
+        assert_eq!(observed_periods_per_year(&first, &first, 1), None);
+    }
+
+    #[test]
+    fn test_ewma() {
+        let mut stats = RunningStats::new(0.5);
+        for (sequence, value) in [(1, 10.0), (2, 20.0), (3, 20.0)] {
+            stats.push(&point(value, 0, sequence), None);
+        }
+        assert_eq!(stats.summary(StdDevKind::Population, 1.0).ewma, 17.5);
+    }
+
+    #[test]
+    fn test_vwap_follows_the_window() {
+        let points: Vec<PricePoint> = [(10.0, 100), (20.0, 300), (30.0, 0)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(value, volume))| PricePoint {
+                volume,
+                ..point(value, 0, i as u64 + 1)
+            })
+            .collect();
+        let mut stats = RunningStats::new(0.5);
+        for point in &points {
+            stats.push(point, None);
+        }
+        let summary = stats.summary(StdDevKind::Population, 1.0);
+        assert_eq!((summary.volume, summary.vwap), (400, Some(17.5)));
+        assert_eq!(volume_weighted(&points), (400, Some(17.5)));
+
+        stats.evict(&points[0], None);
+        stats.evict(&points[1], None);
+        let summary = stats.summary(StdDevKind::Population, 1.0);
+        assert_eq!((summary.volume, summary.vwap), (0, None));
+    }
+
+    fn series(start: u64, prices: &[f64]) -> Vec<PricePoint> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| point(value, start + i as u64, i as u64 + 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_correlation_and_beta() {
+        let index = series(0, &[100.0, 101.0, 99.0, 102.0, 103.0]);
+        // Twice the index's log returns, and the mirror image of them.
+        let levered: Vec<PricePoint> = index
+            .iter()
+            .map(|point| PricePoint {
+                price: price(point.price.to_f64().powi(2) / 100.0),
+                ..*point
+            })
+            .collect();
+        let inverse: Vec<PricePoint> = index
+            .iter()
+            .map(|point| PricePoint {
+                price: price(10_000.0 / point.price.to_f64()),
+                ..*point
+            })
+            .collect();
+        let returns =
+            aligned_log_returns(&[("IDX", &index), ("LEV", &levered), ("INV", &inverse)], 3)
+                .unwrap();
+        assert_eq!(returns[0].len(), 3);
+
+        let matrix = correlation_matrix(&["IDX", "LEV", "INV"], &returns).unwrap();
+        assert!((matrix[0][1] - 1.0).abs() < 1e-9);
+        assert!((matrix[0][2] + 1.0).abs() < 1e-9);
+        assert_eq!(matrix[2][2], 1.0);
+
+        let stats = beta(&returns[1], &returns[0]).unwrap();
+        assert!((stats.beta - 2.0).abs() < 1e-9);
+        assert!((stats.correlation - 1.0).abs() < 1e-9);
+        assert_eq!(stats.observations, 3);
+    }
+
+    #[test]
+    fn test_misaligned_and_short_series_are_rejected() {
+        let a = series(0, &[1.0, 2.0, 3.0, 4.0]);
+        let shifted = series(2, &[1.0, 2.0, 3.0, 4.0]);
+        let error = aligned_log_returns(&[("A", &a), ("B", &shifted)], 0).unwrap_err();
+        assert!(error.contains("misaligned"), "{}", error);
+
+        let error = aligned_log_returns(&[("A", &a), ("B", &a)], 5).unwrap_err();
+        assert!(error.contains("need at least 6"), "{}", error);
+
+        let flat = series(0, &[1.0, 1.0, 1.0, 1.0]);
+        let returns = aligned_log_returns(&[("A", &a), ("FLAT", &flat)], 0).unwrap();
+        assert!(correlation_matrix(&["A", "FLAT"], &returns).is_err());
+        assert!(beta(&returns[0], &returns[1]).is_err());
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn close(a: f64, b: f64) -> bool {
+            (a - b).abs() <= 1e-6 * (1.0 + a.abs().max(b.abs()))
+        }
+
+        /// Removing values costs variance accuracy relative to the square of
+        /// the largest value seen, not to the (possibly tiny) variance itself.
+        fn close_std(a: f64, b: f64, scale: f64) -> bool {
+            (a * a - b * b).abs() <= 1e-9 * (1.0 + scale * scale)
+        }
+
+        proptest! {
+            /// Pushing prices through a sliding window of `window` points
+            /// must match recomputing from scratch over that window.
+            #[test]
+            fn running_stats_match_naive(
+                prices in prop::collection::vec(-50.0f64..500.0, 1..200),
+                window in 1usize..50,
+                sample in any::<bool>(),
+            ) {
+                let kind = if sample { StdDevKind::Sample } else { StdDevKind::Population };
+                let points: Vec<PricePoint> = prices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &value)| point(value, 0, i as u64 + 1))
+                    .collect();
+
+                let mut running = RunningStats::new(0.2);
+                let mut retained: VecDeque<PricePoint> = VecDeque::new();
+                for point in &points {
+                    running.push(point, retained.back().map(|p| p.price));
+                    retained.push_back(*point);
+                    if retained.len() > window {
+                        let evicted = retained.pop_front().unwrap();
+                        running.evict(&evicted, retained.front().map(|p| p.price));
+                    }
+
+                    let window_prices: Vec<f64> =
+                        retained.iter().map(|p| p.price.to_f64()).collect();
+                    let naive = summarize(&window_prices, &[], kind, 252.0).unwrap();
+                    let fast = running.summary(kind, 252.0);
+                    prop_assert_eq!(fast.count, naive.count);
+                    prop_assert!(close(fast.mean, naive.mean));
+                    let scale = prices.iter().fold(0.0f64, |scale, p| scale.max(p.abs()));
+                    prop_assert!(close_std(fast.std_deviation, naive.std_deviation, scale));
+                    prop_assert_eq!(fast.min, naive.min);
+                    prop_assert_eq!(fast.max, naive.max);
+                    prop_assert_eq!(fast.returns.is_some(), naive.returns.is_some());
+                    if let (Some(fast), Some(naive)) = (fast.returns, naive.returns) {
+                        prop_assert!(close(fast.mean, naive.mean));
+                        // Log returns of prices near zero can reach about 25.
+                        prop_assert!(close_std(
+                            fast.annualized_volatility / 252f64.sqrt(),
+                            naive.annualized_volatility / 252f64.sqrt(),
+                            25.0,
+                        ));
+                    }
+                }
+            }
+
+            #[test]
+            fn ewma_matches_naive(
+                prices in prop::collection::vec(1.0f64..500.0, 1..100),
+                alpha in 0.01f64..1.0,
+            ) {
+                let mut running = RunningStats::new(alpha);
+                for (i, &value) in prices.iter().enumerate() {
+                    running.push(&point(value, 0, i as u64 + 1), None);
+                }
+                let naive = prices[1..]
+                    .iter()
+                    .fold(prices[0], |ewma, price| alpha * price + (1.0 - alpha) * ewma);
+                prop_assert!(close(running.summary(StdDevKind::Population, 1.0).ewma, naive));
+            }
+        }
+    }
+}
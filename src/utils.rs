@@ -1,108 +1,561 @@
-use rand::Rng;
-use std::collections::HashMap;
+use crate::config::{MarketConfig, RetentionConfig};
+use crate::price::Price;
+use crate::stats::{
+    observed_periods_per_year, RunningStats, RunningSummary, StdDevKind, TradedVolume,
+};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
 
+/// The built-in universe; the running server's listings live in
+/// `market::TickerRegistry`.
 pub static TICKERS: &[&str] = &[
     "AAPL", "MSFT", "GOOG", "AMZN", "META", "NFLX", "TSLA", "NVDA", "AMD", "INTC",
 ];
 
-pub fn generate_random_ticker_and_price() -> (String, f64) {
-    let mut rng = rand::thread_rng();
-    let ticker = TICKERS[rng.gen_range(0..TICKERS.len())];
-    let price = rng.gen_range(10.0..1000.0);
-    (ticker.to_string(), price)
+/// Formats `price` with `decimals` places, the instrument's precision.
+pub fn format_price(ticker: &str, price: Price, decimals: u32) -> String {
+    format!(
+        "Current price for {}: ${}\n",
+        ticker,
+        price.format(decimals)
+    )
 }
 
-pub fn format_price(ticker: &str, price: f64) -> String {
-    format!("Current price for {}: ${:.2}\n", ticker, price)
+pub fn format_quote(ticker: &str, quote: &crate::market::Quote, decimals: u32) -> String {
+    format!(
+        "Quote for {}: {} x ${} / ${} x {} (last ${})\n",
+        ticker,
+        quote.bid_size,
+        quote.bid.format(decimals),
+        quote.ask.format(decimals),
+        quote.ask_size,
+        quote.last.format(decimals)
+    )
 }
 
-#[derive(Default)]
+/// A recorded price together with when it happened and its position in the
+/// ticker's sequence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PricePoint {
+    pub price: Price,
+    pub timestamp: SystemTime,
+    pub sequence: u64,
+    /// Shares traded at the price; zero when none were recorded.
+    pub volume: u64,
+}
+
+/// Weight of each new price in the moving average when none is configured,
+/// equivalent to a 20-price span.
+const DEFAULT_EWMA_ALPHA: f64 = 2.0 / 21.0;
+
+struct TickerHistory {
+    /// Retained points, oldest first. Used as a ring buffer: new points are
+    /// pushed at the back and evicted ones popped from the front.
+    points: VecDeque<PricePoint>,
+    last_sequence: u64,
+    /// Kept in step with `points` on every push and eviction.
+    stats: RunningStats,
+    /// Every trade ever recorded; retention never evicts from it.
+    traded: TradedVolume,
+}
+
+impl TickerHistory {
+    fn new(ewma_alpha: f64) -> Self {
+        TickerHistory {
+            points: VecDeque::new(),
+            last_sequence: 0,
+            stats: RunningStats::new(ewma_alpha),
+            traded: TradedVolume::default(),
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some(evicted) = self.points.pop_front() {
+            let next = self.points.front().map(|point| point.price);
+            self.stats.evict(&evicted, next);
+        }
+    }
+}
+
+/// A split or dividend's effect on the prices and volumes recorded before it
+/// took effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HistoryAdjustment {
+    effective: SystemTime,
+    price_factor: f64,
+    volume_factor: f64,
+}
+
+/// Per-ticker price history, bounded by the configured retention.
+///
+/// History is kept as it was recorded. Corporate actions are noted
+/// alongside it, so readers can ask for prices adjusted to be comparable
+/// with the latest ones.
 pub struct PriceTracker {
-    prices: HashMap<String, Vec<f64>>,
+    history: HashMap<String, TickerHistory>,
+    max_samples: Option<usize>,
+    max_age: Option<Duration>,
+    ewma_alpha: f64,
+    adjustments: HashMap<String, Vec<HistoryAdjustment>>,
+}
+
+impl Default for PriceTracker {
+    fn default() -> Self {
+        PriceTracker {
+            history: HashMap::new(),
+            max_samples: None,
+            max_age: None,
+            ewma_alpha: DEFAULT_EWMA_ALPHA,
+            adjustments: HashMap::new(),
+        }
+    }
 }
 
 impl PriceTracker {
+    /// A tracker that keeps every price; see [`with_retention`](Self::with_retention).
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn add_price(&mut self, ticker: &str, price: f64) {
-        self.prices
+    /// A tracker with the configured retention and moving-average span.
+    pub fn from_config(config: &MarketConfig) -> Result<Self, String> {
+        let span = config.stats.ewma_span;
+        if !(span.is_finite() && span >= 1.0) {
+            return Err(format!(
+                "market.stats.ewma_span must be at least 1, got {}",
+                span
+            ));
+        }
+        Ok(PriceTracker {
+            ewma_alpha: 2.0 / (span + 1.0),
+            ..Self::with_retention(&config.retention)?
+        })
+    }
+
+    /// A tracker that keeps at most `max_samples` prices per ticker and drops
+    /// prices older than `max_age_secs` relative to the ticker's newest one.
+    pub fn with_retention(retention: &RetentionConfig) -> Result<Self, String> {
+        let max_age = match retention.max_age_secs {
+            Some(secs) if secs.is_finite() && secs > 0.0 => Some(Duration::from_secs_f64(secs)),
+            Some(secs) => {
+                return Err(format!(
+                    "market.retention.max_age_secs must be positive, got {}",
+                    secs
+                ))
+            }
+            None => None,
+        };
+        Ok(PriceTracker {
+            max_samples: (retention.max_samples > 0).then_some(retention.max_samples),
+            max_age,
+            ..Self::default()
+        })
+    }
+
+    pub fn add_price(&mut self, ticker: &str, price: Price) {
+        self.record_price(ticker, price, SystemTime::now());
+    }
+
+    /// Records `price` observed at `timestamp` with no traded volume; see
+    /// [`record_trade`](Self::record_trade).
+    pub fn record_price(&mut self, ticker: &str, price: Price, timestamp: SystemTime) -> u64 {
+        self.record_trade(ticker, price, 0, timestamp)
+    }
+
+    /// Records `volume` shares trading at `price` at `timestamp` and returns
+    /// the price's sequence number. Sequence numbers start at 1 and increase
+    /// by one per price for each ticker, so a gap tells a consumer it missed
+    /// updates.
+    ///
+    /// Each insert updates the running statistics and evicts only the points
+    /// that fell out of retention, so the cost stays constant however long
+    /// the tracker runs.
+    pub fn record_trade(
+        &mut self,
+        ticker: &str,
+        price: Price,
+        volume: u64,
+        timestamp: SystemTime,
+    ) -> u64 {
+        let ewma_alpha = self.ewma_alpha;
+        let history = self
+            .history
+            .entry(ticker.to_string())
+            .or_insert_with(|| TickerHistory::new(ewma_alpha));
+        history.last_sequence += 1;
+        let point = PricePoint {
+            price,
+            timestamp,
+            sequence: history.last_sequence,
+            volume,
+        };
+        let previous = history.points.back().map(|point| point.price);
+        history.stats.push(&point, previous);
+        history.traded.push(&point);
+        history.points.push_back(point);
+
+        if let Some(max_samples) = self.max_samples {
+            while history.points.len() > max_samples {
+                history.evict_oldest();
+            }
+        }
+        if let Some(cutoff) = self.max_age.and_then(|age| timestamp.checked_sub(age)) {
+            while history
+                .points
+                .front()
+                .is_some_and(|point| point.timestamp < cutoff)
+            {
+                history.evict_oldest();
+            }
+        }
+        history.last_sequence
+    }
+
+    /// Constant-time statistics over every retained price for `ticker`.
+    /// Volatility is annualized with `periods_per_year` price updates per
+    /// year, or with the spacing observed between retained prices when that
+    /// is `None`.
+    pub fn summary(
+        &self,
+        ticker: &str,
+        kind: StdDevKind,
+        periods_per_year: Option<f64>,
+    ) -> Option<RunningSummary> {
+        let history = self.history.get(ticker)?;
+        let periods_per_year = periods_per_year
+            .or_else(|| {
+                observed_periods_per_year(
+                    history.points.front()?,
+                    history.points.back()?,
+                    history.points.len(),
+                )
+            })
+            .unwrap_or(0.0);
+        Some(history.stats.summary(kind, periods_per_year))
+    }
+
+    pub fn get_prices(&self, ticker: &str) -> Option<Vec<Price>> {
+        self.history
+            .get(ticker)
+            .map(|history| history.points.iter().map(|point| point.price).collect())
+    }
+
+    /// Shares traded in `ticker` since the tracker started, and their
+    /// volume-weighted average price, as traded; unlike the retained history
+    /// these totals are never evicted.
+    pub fn cumulative_volume(&self, ticker: &str) -> (u64, Option<f64>) {
+        self.history.get(ticker).map_or((0, None), |history| {
+            (history.traded.shares, history.traded.vwap())
+        })
+    }
+
+    /// Number of prices currently retained for `ticker`.
+    pub fn sample_count(&self, ticker: &str) -> usize {
+        self.history
+            .get(ticker)
+            .map_or(0, |history| history.points.len())
+    }
+
+    /// The most recent `count` price points for `ticker`, oldest first.
+    pub fn last_points(&self, ticker: &str, count: usize) -> Vec<PricePoint> {
+        self.history
+            .get(ticker)
+            .map(|history| {
+                let start = history.points.len().saturating_sub(count);
+                history.points.range(start..).copied().collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Retained points for `ticker` stamped at or after `since` (all of them
+    /// when `since` is `None`), oldest first.
+    pub fn points_since(&self, ticker: &str, since: Option<SystemTime>) -> Vec<PricePoint> {
+        self.history
+            .get(ticker)
+            .map(|history| {
+                let start = since.map_or(0, |since| {
+                    history
+                        .points
+                        .partition_point(|point| point.timestamp < since)
+                });
+                history.points.range(start..).copied().collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Up to `limit` retained points with a sequence number above
+    /// `after_sequence`, oldest first, and whether more follow. A `limit` of
+    /// zero means no limit.
+    ///
+    /// Paging by sequence number rather than position keeps pages stable
+    /// while old points are evicted.
+    pub fn points_after(
+        &self,
+        ticker: &str,
+        after_sequence: u64,
+        limit: usize,
+    ) -> (Vec<PricePoint>, bool) {
+        let Some(history) = self.history.get(ticker) else {
+            return (Vec::new(), false);
+        };
+        let start = history
+            .points
+            .partition_point(|point| point.sequence <= after_sequence);
+        let remaining = history.points.len() - start;
+        let take = if limit == 0 {
+            remaining
+        } else {
+            limit.min(remaining)
+        };
+        (
+            history.points.range(start..start + take).copied().collect(),
+            take < remaining,
+        )
+    }
+
+    /// Weight of each new price in the moving average.
+    pub fn ewma_alpha(&self) -> f64 {
+        self.ewma_alpha
+    }
+
+    /// Notes a corporate action on `ticker` that took effect at `effective`:
+    /// prices recorded before then are multiplied by `price_factor`, and
+    /// volumes by `volume_factor`, in adjusted history.
+    pub fn record_adjustment(
+        &mut self,
+        ticker: &str,
+        effective: SystemTime,
+        price_factor: f64,
+        volume_factor: f64,
+    ) {
+        self.adjustments
             .entry(ticker.to_string())
             .or_default()
-            .push(price);
+            .push(HistoryAdjustment {
+                effective,
+                price_factor,
+                volume_factor,
+            });
     }
 
-    pub fn get_prices(&self, ticker: &str) -> Option<&Vec<f64>> {
-        self.prices.get(ticker)
+    /// Whether any corporate action has adjusted `ticker`'s history.
+    pub fn has_adjustments(&self, ticker: &str) -> bool {
+        self.adjustments.contains_key(ticker)
+    }
+
+    /// The price and volume factors for something observed at `at`: the
+    /// products over the actions that took effect after it.
+    pub fn adjustment_factors(&self, ticker: &str, at: SystemTime) -> (f64, f64) {
+        self.adjustments
+            .get(ticker)
+            .into_iter()
+            .flatten()
+            .filter(|adjustment| adjustment.effective > at)
+            .fold((1.0, 1.0), |(price, volume), adjustment| {
+                (
+                    price * adjustment.price_factor,
+                    volume * adjustment.volume_factor,
+                )
+            })
+    }
+
+    /// `point` with its price and volume adjusted for the corporate actions
+    /// since.
+    pub fn adjusted(&self, ticker: &str, point: PricePoint) -> PricePoint {
+        let (price_factor, volume_factor) = self.adjustment_factors(ticker, point.timestamp);
+        PricePoint {
+            price: point.price.scaled(price_factor),
+            volume: (point.volume as f64 * volume_factor).round() as u64,
+            ..point
+        }
     }
 
     pub fn average(&self, ticker: &str) -> Option<f64> {
-        self.get_prices(ticker).map(|prices| {
-            if prices.is_empty() {
-                0.0
-            } else {
-                prices.iter().sum::<f64>() / prices.len() as f64
-            }
-        })
+        self.summary(ticker, StdDevKind::Population, None)
+            .map(|summary| summary.mean)
     }
 
     pub fn std_deviation(&self, ticker: &str) -> Option<f64> {
-        self.get_prices(ticker).map(|prices| {
-            if prices.is_empty() {
-                0.0
-            } else {
-                let avg = prices.iter().sum::<f64>() / prices.len() as f64;
-                let variance = prices
-                    .iter()
-                    .map(|value| {
-                        let diff = value - avg;
-                        diff * diff
-                    })
-                    .sum::<f64>()
-                    / prices.len() as f64;
-                variance.sqrt()
-            }
-        })
+        self.summary(ticker, StdDevKind::Population, None)
+            .map(|summary| summary.std_deviation)
     }
 
-    pub fn get_stats(&self, ticker: &str) -> (Vec<f64>, f64, f64) {
-        let prices = self.get_prices(ticker).cloned().unwrap_or_default();
+    pub fn get_stats(&self, ticker: &str) -> (Vec<Price>, f64, f64) {
+        let prices = self.get_prices(ticker).unwrap_or_default();
         let average = self.average(ticker).unwrap_or(0.0);
         let std_dev = self.std_deviation(ticker).unwrap_or(0.0);
         (prices, average, std_dev)
     }
 }
 
+/// `secs` after the Unix epoch, for tests.
+#[cfg(test)]
+pub(crate) fn at(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// An untraded `value` at `at(secs)`, `sequence`th in its ticker, for tests.
+#[cfg(test)]
+pub(crate) fn point(value: f64, secs: u64, sequence: u64) -> PricePoint {
+    PricePoint {
+        price: crate::price::price(value),
+        timestamp: at(secs),
+        sequence,
+        volume: 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::price::price;
+
+    fn prices(values: &[f64]) -> Option<Vec<Price>> {
+        Some(values.iter().map(|&value| price(value)).collect())
+    }
 
     #[test]
     fn test_price_tracker() {
         let mut tracker = PriceTracker::new();
         let ticker = "AAPL";
-        tracker.add_price(ticker, 150.0);
-        tracker.add_price(ticker, 160.0);
-        tracker.add_price(ticker, 170.0);
-        assert_eq!(tracker.get_prices(ticker), Some(&vec![150.0, 160.0, 170.0]));
+        tracker.add_price(ticker, price(150.0));
+        tracker.add_price(ticker, price(160.0));
+        tracker.add_price(ticker, price(170.0));
+        assert_eq!(tracker.get_prices(ticker), prices(&[150.0, 160.0, 170.0]));
         assert_eq!(tracker.average(ticker), Some(160.0));
         let std_dev = tracker.std_deviation(ticker).unwrap();
         assert!((std_dev - 8.16496580927726).abs() < 0.000001);
+
+        let summary = tracker
+            .summary(ticker, StdDevKind::Sample, Some(1.0))
+            .unwrap();
+        assert_eq!((summary.min, summary.max, summary.count), (150.0, 170.0, 3));
+        assert!((summary.std_deviation - 10.0).abs() < 1e-9);
+        assert!(tracker.summary("MSFT", StdDevKind::Sample, None).is_none());
     }
 
     #[test]
-    fn test_format_price() {
+    fn test_price_points_are_sequenced() {
+        let mut tracker = PriceTracker::new();
+        let t0 = SystemTime::UNIX_EPOCH;
+        assert_eq!(tracker.record_price("AAPL", price(150.0), t0), 1);
+        assert_eq!(tracker.record_price("MSFT", price(400.0), t0), 1);
+        assert_eq!(tracker.record_price("AAPL", price(151.0), t0), 2);
+        tracker.add_price("AAPL", price(152.0));
+
+        let points = tracker.last_points("AAPL", 2);
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[0].price, points[0].sequence), (price(151.0), 2));
+        assert_eq!((points[1].price, points[1].sequence), (price(152.0), 3));
+        assert!(points[1].timestamp > t0);
+        assert!(tracker.last_points("GOOG", 5).is_empty());
+    }
+
+    #[test]
+    fn test_retention_evicts_oldest_prices() {
+        let mut tracker = PriceTracker::with_retention(&RetentionConfig {
+            max_samples: 3,
+            max_age_secs: Some(10.0),
+        })
+        .unwrap();
+        for (secs, value) in [(0, 1.0), (1, 2.0), (2, 3.0), (3, 4.0)] {
+            tracker.record_price("AAPL", price(value), at(secs));
+        }
+        assert_eq!(tracker.get_prices("AAPL"), prices(&[2.0, 3.0, 4.0]));
+
+        // Everything older than ten seconds before the newest point goes.
+        assert_eq!(tracker.record_price("AAPL", price(5.0), at(13)), 5);
+        assert_eq!(tracker.get_prices("AAPL"), prices(&[4.0, 5.0]));
+        assert_eq!(tracker.average("AAPL"), Some(4.5));
+
+        let recent = tracker.points_since("AAPL", Some(at(10)));
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].price, price(5.0));
+        assert_eq!(tracker.points_since("AAPL", None).len(), 2);
+    }
+
+    #[test]
+    fn test_cumulative_volume_outlives_retention() {
+        let mut tracker = PriceTracker::with_retention(&RetentionConfig {
+            max_samples: 2,
+            max_age_secs: None,
+        })
+        .unwrap();
+        let now = SystemTime::now();
+        for (value, volume) in [(10.0, 100), (20.0, 300), (30.0, 100), (40.0, 0)] {
+            tracker.record_trade("AAPL", price(value), volume, now);
+        }
+        assert_eq!(tracker.sample_count("AAPL"), 2);
+        let summary = tracker.summary("AAPL", StdDevKind::Sample, None).unwrap();
+        assert_eq!(summary.volume, 100);
+        // (10 * 100 + 20 * 300 + 30 * 100) / 500
+        assert_eq!(tracker.cumulative_volume("AAPL"), (500, Some(20.0)));
+        assert_eq!(tracker.cumulative_volume("MSFT"), (0, None));
+    }
+
+    #[test]
+    fn test_points_after_pages_by_sequence() {
+        let mut tracker = PriceTracker::with_retention(&RetentionConfig {
+            max_samples: 4,
+            max_age_secs: None,
+        })
+        .unwrap();
+        for value in 1..=6 {
+            tracker.add_price("AAPL", price(value as f64));
+        }
+        // Sequences 3 to 6 are retained.
+        let (page, more) = tracker.points_after("AAPL", 0, 3);
+        let sequences: Vec<u64> = page.iter().map(|point| point.sequence).collect();
+        assert_eq!((sequences, more), (vec![3, 4, 5], true));
+
+        let (page, more) = tracker.points_after("AAPL", 5, 3);
+        assert_eq!((page.len(), page[0].price, more), (1, price(6.0), false));
+        assert_eq!(tracker.points_after("AAPL", 0, 0).0.len(), 4);
+        assert_eq!(tracker.sample_count("AAPL"), 4);
+        assert!(PriceTracker::with_retention(&RetentionConfig {
+            max_samples: 0,
+            max_age_secs: Some(-1.0),
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_adjusted_history_compounds_later_actions() {
+        let mut tracker = PriceTracker::new();
+        for (secs, value) in [(0, 200.0), (10, 50.0), (20, 49.0)] {
+            tracker.record_price("AAPL", price(value), at(secs));
+        }
+        assert!(!tracker.has_adjustments("AAPL"));
+        // A 4-for-1 split, then a dividend taking 2% off the price.
+        tracker.record_adjustment("AAPL", at(10), 0.25, 4.0);
+        tracker.record_adjustment("AAPL", at(20), 0.98, 1.0);
+
+        assert_eq!(tracker.adjustment_factors("AAPL", at(0)), (0.245, 4.0));
+        assert_eq!(tracker.adjustment_factors("AAPL", at(10)), (0.98, 1.0));
+        assert_eq!(tracker.adjustment_factors("AAPL", at(20)), (1.0, 1.0));
+        assert_eq!(tracker.adjustment_factors("MSFT", at(0)), (1.0, 1.0));
+        let adjusted: Vec<Price> = tracker
+            .points_since("AAPL", None)
+            .into_iter()
+            .map(|point| tracker.adjusted("AAPL", point).price)
+            .collect();
+        assert_eq!(adjusted, vec![price(49.0), price(49.0), price(49.0)]);
+        // The recorded history is untouched.
         assert_eq!(
-            format_price("AAPL", 150.50),
-            "Current price for AAPL: $150.50\n"
+            tracker.get_prices("AAPL"),
+            Some(vec![price(200.0), price(50.0), price(49.0)])
         );
     }
 
     #[test]
-    fn test_random_ticker_and_price() {
-        let (ticker, price) = generate_random_ticker_and_price();
-        assert!(TICKERS.contains(&ticker.as_str()));
-        assert!(price >= 10.0 && price < 1000.0);
+    fn test_format_price() {
+        assert_eq!(
+            format_price("AAPL", price(150.5), 2),
+            "Current price for AAPL: $150.50\n"
+        );
+        assert_eq!(
+            format_price("EURUSD", price(1.08456), 4),
+            "Current price for EURUSD: $1.0846\n"
+        );
     }
 }